@@ -1,3 +1,8 @@
 fn main() {
-    tauri_build::build()
+    #[cfg(feature = "desktop")]
+    tauri_build::build();
+
+    // Tauri sets these for the app; the headless library is built without them
+    #[cfg(not(feature = "desktop"))]
+    println!("cargo:rustc-check-cfg=cfg(dev, mobile)");
 }
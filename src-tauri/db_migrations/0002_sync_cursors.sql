@@ -0,0 +1,47 @@
+-- How far each kind of event, and each group's messages, have been caught up on per account.
+-- Replaces the single accounts.last_synced timestamp for catch-up.
+CREATE TABLE sync_cursors (
+    account_pubkey TEXT NOT NULL,
+    scope TEXT NOT NULL,  -- 'kind:<kind>', 'contacts_metadata' or 'group:<nostr_group_id>'
+    synced_until INTEGER NOT NULL,  -- all events up to this timestamp have been processed
+    updated_at INTEGER NOT NULL,
+    PRIMARY KEY (account_pubkey, scope),
+    FOREIGN KEY (account_pubkey) REFERENCES accounts(pubkey) ON DELETE CASCADE
+);
+
+-- Time ranges a relay couldn't be synced for because it was unreachable.
+-- Gaps are retried on every catch-up and removed once they've been filled.
+CREATE TABLE sync_gaps (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    account_pubkey TEXT NOT NULL,
+    scope TEXT NOT NULL,
+    relay_url TEXT NOT NULL,
+    since INTEGER NOT NULL,
+    until INTEGER NOT NULL,
+    created_at INTEGER NOT NULL,
+    FOREIGN KEY (account_pubkey) REFERENCES accounts(pubkey) ON DELETE CASCADE
+);
+
+CREATE INDEX idx_sync_gaps_account ON sync_gaps(account_pubkey, scope);
+
+-- Start existing accounts and groups from where they were last synced
+INSERT INTO sync_cursors (account_pubkey, scope, synced_until, updated_at)
+SELECT accounts.pubkey, scopes.scope, accounts.last_synced, accounts.last_synced
+FROM accounts
+CROSS JOIN (
+    SELECT 'kind:0' AS scope
+    UNION ALL SELECT 'kind:3'
+    UNION ALL SELECT 'contacts_metadata'
+    UNION ALL SELECT 'kind:10002'
+    UNION ALL SELECT 'kind:10050'
+    UNION ALL SELECT 'kind:10051'
+    UNION ALL SELECT 'kind:443'
+    UNION ALL SELECT 'kind:1059'
+) AS scopes
+WHERE accounts.last_synced > 0;
+
+INSERT OR IGNORE INTO sync_cursors (account_pubkey, scope, synced_until, updated_at)
+SELECT accounts.pubkey, 'group:' || groups.nostr_group_id, accounts.last_synced, accounts.last_synced
+FROM groups
+JOIN accounts ON accounts.pubkey = groups.account_pubkey
+WHERE accounts.last_synced > 0;
@@ -0,0 +1,9 @@
+-- Key/value state kept by bots, per bot account. Values are JSON.
+CREATE TABLE bot_state (
+    account_pubkey TEXT NOT NULL,
+    key TEXT NOT NULL,
+    value TEXT NOT NULL,
+    updated_at INTEGER NOT NULL,
+    PRIMARY KEY (account_pubkey, key),
+    FOREIGN KEY (account_pubkey) REFERENCES accounts(pubkey) ON DELETE CASCADE
+);
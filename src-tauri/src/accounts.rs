@@ -1,14 +1,16 @@
-use crate::database::DatabaseError;
+use crate::database::{convert_rows, DatabaseError};
 use crate::groups::{Group, GroupRow};
 use crate::invites::{Invite, InviteRow};
 use crate::nostr_manager;
+use crate::nostr_manager::auth::RelayAuthPolicy;
+use crate::nostr_manager::remote_signer;
+use crate::nostr_manager::PublishTarget;
 use crate::relays::RelayType;
 use crate::secrets_store;
 use crate::Whitenoise;
-use nostr_openmls::NostrMls;
 use nostr_sdk::prelude::*;
 use serde::{Deserialize, Serialize};
-use tauri::Emitter;
+use std::sync::Arc;
 use thiserror::Error;
 
 #[derive(Error, Debug)]
@@ -25,9 +27,6 @@ pub enum AccountError {
     #[error("Error with secrets store: {0}")]
     SecretsStoreError(#[from] secrets_store::SecretsStoreError),
 
-    #[error("Tauri error: {0}")]
-    TauriError(#[from] tauri::Error),
-
     #[error("No active account found")]
     NoActiveAccount,
 
@@ -36,20 +35,180 @@ pub enum AccountError {
 
     #[error("SQLx error: {0}")]
     SqlxError(#[from] sqlx::Error),
+
+    #[error("Invalid settings: {0}")]
+    InvalidSettings(String),
+
+    #[error("Invalid profile: {0}")]
+    InvalidProfile(String),
+
+    #[error("Invalid relay: {0}")]
+    InvalidRelay(String),
+
+    #[error("File error: {0}")]
+    FileError(#[from] std::io::Error),
+
+    #[error("Wrong password for encrypted private key")]
+    WrongPassword,
+
+    #[error("Invalid password: {0}")]
+    InvalidPassword(String),
+
+    #[error("Invalid encrypted private key: {0}")]
+    InvalidEncryptedKey(String),
+
+    #[error("Invalid mnemonic: {0}")]
+    InvalidMnemonic(String),
+
+    #[error("Account uses a remote signer, its private key is not stored on this device")]
+    RemoteSignerAccount,
+
+    #[error("Nostr event error: {0}")]
+    NostrEventError(#[from] nostr_sdk::event::builder::Error),
+
+    #[error("Signer error: {0}")]
+    SignerError(#[from] nostr_sdk::signer::SignerError),
+
+    #[error("Account is not the active account")]
+    NotActiveAccount,
 }
 
 pub type Result<T> = std::result::Result<T, AccountError>;
 
+/// The number of words in mnemonics generated for new identities.
+const MNEMONIC_WORD_COUNT: usize = 12;
+
+/// Generates a new random BIP-39 mnemonic for a NIP-06 identity.
+pub fn generate_mnemonic() -> Result<String> {
+    use nostr_sdk::secp256k1::rand::{rngs::OsRng, RngCore};
+
+    // 128 bits of entropy gives a 12 word mnemonic
+    let mut entropy = [0u8; MNEMONIC_WORD_COUNT * 4 / 3];
+    OsRng.fill_bytes(&mut entropy);
+    let mnemonic = nostr_sdk::bip39::Mnemonic::from_entropy(&entropy)
+        .map_err(|e| AccountError::InvalidMnemonic(e.to_string()))?;
+    Ok(mnemonic.to_string())
+}
+
+/// Returns true if the given login string looks like a BIP-39 mnemonic rather than a key.
+pub fn is_mnemonic(value: &str) -> bool {
+    let words: Vec<&str> = value.split_whitespace().collect();
+    matches!(words.len(), 12 | 15 | 18 | 21 | 24)
+        && words
+            .iter()
+            .all(|word| word.chars().all(|c| c.is_ascii_alphabetic()))
+}
+
+/// Derives the keys for a BIP-39 mnemonic along the NIP-06 path (`m/44'/1237'/0'/0/0`).
+///
+/// # Errors
+/// Returns `AccountError::InvalidMnemonic` if the mnemonic has unknown words or a bad checksum.
+pub fn keys_from_mnemonic(mnemonic: &str, passphrase: Option<&str>) -> Result<Keys> {
+    let mnemonic = mnemonic
+        .split_whitespace()
+        .map(|word| word.to_lowercase())
+        .collect::<Vec<_>>()
+        .join(" ");
+    let passphrase = passphrase.filter(|p| !p.is_empty()).map(|p| p.to_string());
+    Keys::from_mnemonic(mnemonic, passphrase)
+        .map_err(|e| AccountError::InvalidMnemonic(e.to_string()))
+}
+
+/// The smallest scrypt work factor (`log_n`) accepted for NIP-49 encryption. Uses 64 MiB of memory.
+pub const NCRYPTSEC_MIN_LOG_N: u8 = 16;
+/// The largest scrypt work factor (`log_n`) accepted for NIP-49 encryption. Uses 4 GiB of memory.
+pub const NCRYPTSEC_MAX_LOG_N: u8 = 22;
+
+/// Encrypts a private key with a password as a NIP-49 `ncryptsec` string.
+///
+/// # Errors
+/// Returns `AccountError::InvalidPassword` if the password is empty and
+/// `AccountError::InvalidEncryptedKey` if `log_n` is outside
+/// [`NCRYPTSEC_MIN_LOG_N`]..=[`NCRYPTSEC_MAX_LOG_N`].
+pub fn encrypt_secret_key(secret_key: &SecretKey, password: &str, log_n: u8) -> Result<String> {
+    if password.is_empty() {
+        return Err(AccountError::InvalidPassword(
+            "Password must not be empty".to_string(),
+        ));
+    }
+    if !(NCRYPTSEC_MIN_LOG_N..=NCRYPTSEC_MAX_LOG_N).contains(&log_n) {
+        return Err(AccountError::InvalidEncryptedKey(format!(
+            "Work factor must be between {} and {}, got {}",
+            NCRYPTSEC_MIN_LOG_N, NCRYPTSEC_MAX_LOG_N, log_n
+        )));
+    }
+
+    let encrypted = EncryptedSecretKey::new(secret_key, password, log_n, KeySecurity::Unknown)
+        .map_err(|e| AccountError::InvalidEncryptedKey(e.to_string()))?;
+    encrypted
+        .to_bech32()
+        .map_err(|e| AccountError::InvalidEncryptedKey(e.to_string()))
+}
+
+/// Decrypts a NIP-49 `ncryptsec` string with a password.
+///
+/// # Errors
+/// Returns `AccountError::WrongPassword` if the password doesn't match and
+/// `AccountError::InvalidEncryptedKey` if the string isn't a valid `ncryptsec`.
+pub fn decrypt_ncryptsec(ncryptsec: &str, password: &str) -> Result<Keys> {
+    let encrypted = EncryptedSecretKey::from_bech32(ncryptsec.trim())
+        .map_err(|e| AccountError::InvalidEncryptedKey(e.to_string()))?;
+    let secret_key = encrypted.to_secret_key(password).map_err(|e| match e {
+        nip49::Error::ChaCha20Poly1305(_) => AccountError::WrongPassword,
+        e => AccountError::InvalidEncryptedKey(e.to_string()),
+    })?;
+    Ok(Keys::new(secret_key))
+}
+
 #[derive(Serialize, Deserialize, Debug, Clone, sqlx::FromRow)]
 pub struct ActiveAccount {
     pub pubkey: String,
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone, sqlx::FromRow)]
+/// Per-account settings, stored as JSON on the account row.
+///
+/// New fields must have a default (either via `Default` or a `#[serde(default = ...)]`
+/// attribute) so that rows written by older versions still deserialize.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(default)]
 pub struct AccountSettings {
     pub dark_theme: bool,
     pub dev_mode: bool,
     pub lockdown_mode: bool,
+    /// Which relays we authenticate to (NIP-42) when they ask, since that reveals our pubkey
+    pub relay_auth: RelayAuthPolicy,
+}
+
+impl AccountSettings {
+    /// Validates a set of settings before they are saved
+    ///
+    /// # Errors
+    /// Returns `AccountError::InvalidSettings` if:
+    /// - Both dev mode and lockdown mode are enabled. Dev mode surfaces raw events
+    ///   and debug information, which lockdown mode is meant to hide.
+    /// - Lockdown mode is enabled and we'd authenticate to any relay that asks,
+    ///   revealing our pubkey to relays we don't use.
+    pub fn validate(&self) -> Result<()> {
+        if self.dev_mode && self.lockdown_mode {
+            return Err(AccountError::InvalidSettings(
+                "Dev mode can't be enabled while lockdown mode is on".to_string(),
+            ));
+        }
+        if self.lockdown_mode && self.relay_auth == RelayAuthPolicy::Always {
+            return Err(AccountError::InvalidSettings(
+                "Authenticating to any relay can't be enabled while lockdown mode is on"
+                    .to_string(),
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// Payload of the `settings_changed` event
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct SettingsChangedEvent {
+    pub pubkey: String,
+    pub settings: AccountSettings,
 }
 
 impl Default for AccountSettings {
@@ -58,6 +217,7 @@ impl Default for AccountSettings {
             dark_theme: true,
             dev_mode: false,
             lockdown_mode: false,
+            relay_auth: RelayAuthPolicy::default(),
         }
     }
 }
@@ -69,6 +229,116 @@ pub struct AccountOnboarding {
     pub publish_key_package: bool,
 }
 
+/// A partial update to an account's profile metadata (kind 0).
+///
+/// `None` leaves a field untouched, an empty string clears it.
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct ProfileUpdate {
+    pub name: Option<String>,
+    pub display_name: Option<String>,
+    pub about: Option<String>,
+    pub picture: Option<String>,
+    pub banner: Option<String>,
+    pub website: Option<String>,
+    pub nip05: Option<String>,
+    pub lud16: Option<String>,
+}
+
+impl ProfileUpdate {
+    /// Validates the update and merges it into the given metadata
+    ///
+    /// # Errors
+    /// Returns `AccountError::InvalidProfile` if:
+    /// - `picture`, `banner` or `website` is not a valid URL
+    /// - `nip05` or `lud16` is not in `name@domain` form
+    pub fn merge_into(&self, mut metadata: Metadata) -> Result<Metadata> {
+        fn value(field: &Option<String>) -> Option<Option<String>> {
+            field.as_ref().map(|v| {
+                let v = v.trim();
+                if v.is_empty() {
+                    None
+                } else {
+                    Some(v.to_string())
+                }
+            })
+        }
+
+        fn url(name: &str, field: &Option<String>) -> Result<Option<Option<Url>>> {
+            match value(field) {
+                Some(Some(v)) => Url::parse(&v).map(|u| Some(Some(u))).map_err(|e| {
+                    AccountError::InvalidProfile(format!("Invalid {} URL: {}", name, e))
+                }),
+                Some(None) => Ok(Some(None)),
+                None => Ok(None),
+            }
+        }
+
+        fn identifier(name: &str, field: &Option<String>) -> Result<Option<Option<String>>> {
+            match value(field) {
+                Some(Some(v)) => {
+                    let valid = v
+                        .split_once('@')
+                        .is_some_and(|(local, domain)| !local.is_empty() && domain.contains('.'));
+                    if !valid {
+                        return Err(AccountError::InvalidProfile(format!(
+                            "Invalid {}: {}",
+                            name, v
+                        )));
+                    }
+                    Ok(Some(Some(v)))
+                }
+                other => Ok(other),
+            }
+        }
+
+        if let Some(name) = value(&self.name) {
+            metadata.name = name;
+        }
+        if let Some(display_name) = value(&self.display_name) {
+            metadata.display_name = display_name;
+        }
+        if let Some(about) = value(&self.about) {
+            metadata.about = about;
+        }
+        if let Some(picture) = url("picture", &self.picture)? {
+            metadata.picture = picture.map(|u| u.to_string());
+        }
+        if let Some(banner) = url("banner", &self.banner)? {
+            metadata.banner = banner.map(|u| u.to_string());
+        }
+        if let Some(website) = url("website", &self.website)? {
+            metadata.website = website.map(|u| u.to_string());
+        }
+        if let Some(nip05) = identifier("NIP-05 identifier", &self.nip05)? {
+            metadata.nip05 = nip05;
+        }
+        if let Some(lud16) = identifier("lightning address", &self.lud16)? {
+            metadata.lud16 = lud16;
+        }
+
+        Ok(metadata)
+    }
+}
+
+/// What was removed when purging an account with [`Account::remove`].
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct AccountRemovalReport {
+    pub pubkey: String,
+    /// Number of groups, messages and invites deleted from the database
+    pub groups: u64,
+    pub messages: u64,
+    pub invites: u64,
+    /// Number of MLS export secrets removed from the secrets store
+    pub export_secrets: usize,
+    /// Number of events removed from the nostr database
+    pub nostr_events: usize,
+    pub mls_storage: bool,
+    pub private_key: bool,
+    pub remote_signer: bool,
+    pub mnemonic: bool,
+    pub nostr_wallet_connect_uri: bool,
+}
+
 /// This is an intermediate struct representing an account in the database
 #[derive(Serialize, Deserialize, Debug, Clone, sqlx::FromRow)]
 pub struct AccountRow {
@@ -88,14 +358,38 @@ pub struct Account {
     pub settings: AccountSettings,
     pub onboarding: AccountOnboarding,
     pub last_used: Timestamp,
+    /// No longer updated, catching up is tracked per kind and group by the sync cursors
     pub last_synced: Timestamp,
     pub active: bool,
 }
 
+impl TryFrom<AccountRow> for Account {
+    type Error = DatabaseError;
+
+    fn try_from(row: AccountRow) -> std::result::Result<Self, Self::Error> {
+        Ok(Account {
+            pubkey: PublicKey::parse(row.pubkey.as_str())
+                .map_err(|e| DatabaseError::corrupt_row("accounts", e))?,
+            metadata: serde_json::from_str(&row.metadata)
+                .map_err(|e| DatabaseError::corrupt_row("accounts", e))?,
+            settings: serde_json::from_str(&row.settings)
+                .map_err(|e| DatabaseError::corrupt_row("accounts", e))?,
+            onboarding: serde_json::from_str(&row.onboarding)
+                .map_err(|e| DatabaseError::corrupt_row("accounts", e))?,
+            last_used: Timestamp::from(row.last_used),
+            last_synced: Timestamp::from(row.last_synced),
+            active: row.active,
+        })
+    }
+}
+
 impl Account {
-    /// Generates a new keypair and saves the mostly blank account to the database
-    pub async fn new(wn: tauri::State<'_, Whitenoise>) -> Result<Account> {
-        let keys = Keys::generate();
+    /// Generates a new keypair from a NIP-06 mnemonic and saves the mostly blank account to the database
+    ///
+    /// The mnemonic is kept in the secrets store until it is shown to the user with [`Account::take_mnemonic`].
+    pub async fn new(wn: &Whitenoise) -> Result<Account> {
+        let mnemonic = generate_mnemonic()?;
+        let keys = keys_from_mnemonic(&mnemonic, None)?;
         let account = Account {
             pubkey: keys.public_key(),
             metadata: Metadata::default(),
@@ -105,22 +399,133 @@ impl Account {
             last_synced: Timestamp::zero(),
             active: false,
         };
-        let account = account.save(wn.clone()).await?;
+        let account = account.save(wn).await?;
 
-        // If the record saves, add the keys to the secret store
+        // If the record saves, add the keys and mnemonic to the secret store
         secrets_store::store_private_key(&keys, &wn.data_dir)?;
+        secrets_store::store_mnemonic(&keys.public_key().to_hex(), &mnemonic, &wn.data_dir)?;
 
         Ok(account)
     }
     /// Adds an account from an existing keypair
-    pub async fn add_from_keys(
-        keys: &Keys,
+    pub async fn add_from_keys(keys: &Keys, set_active: bool, wn: &Whitenoise) -> Result<Account> {
+        let account = Self::add_from_pubkey(keys.public_key(), wn).await?;
+
+        tracing::debug!(target: "whitenoise::accounts", "Storing private key");
+        secrets_store::store_private_key(keys, &wn.data_dir)?;
+
+        // Set active if requested
+        if set_active {
+            account.set_active(wn).await?;
+        }
+
+        Ok(account)
+    }
+
+    /// Adds an account that signs with a NIP-46 remote signer (`bunker://` or `nostrconnect://` URI).
+    ///
+    /// No private key is stored locally. Instead the bunker URI and the local app keys used to
+    /// talk to the remote signer are kept in the secrets store.
+    /// For `nostrconnect://` URIs, the app keys must have been created with [`crate::commands::accounts::create_nostr_connect_uri`].
+    ///
+    /// If the account already exists, its remote signer connection is replaced.
+    pub async fn add_from_remote_signer(
+        uri: &str,
         set_active: bool,
-        wn: tauri::State<'_, Whitenoise>,
-        app_handle: &tauri::AppHandle,
+        wn: &Whitenoise,
     ) -> Result<Account> {
-        let pubkey = keys.public_key();
+        let uri = remote_signer::parse_remote_signer_uri(uri)?;
+        // The pending app keys of a `nostrconnect://` URI are only removed once the signer has
+        // connected, so that the same URI can be retried after a timeout or a rejection.
+        let pending_app_pubkey = match &uri {
+            NostrConnectURI::Bunker { .. } => None,
+            NostrConnectURI::Client { public_key, .. } => Some(public_key.to_hex()),
+        };
+        let app_keys = match &pending_app_pubkey {
+            None => Keys::generate(),
+            Some(app_pubkey) => {
+                secrets_store::get_pending_remote_signer_app_keys(app_pubkey, &wn.data_dir)?
+            }
+        };
+
+        // Connecting waits for the remote signer to respond (and possibly for the user to approve)
+        let connection_mode = wn.nostr.connection_mode_for_uri(&uri.to_string()).await?;
+        let signer = remote_signer::connect_remote_signer(uri, app_keys.clone(), connection_mode)?;
+        let pubkey = signer
+            .get_public_key()
+            .await
+            .map_err(|e| nostr_manager::NostrManagerError::RemoteSigner(e.to_string()))?;
+        // Store the bunker URI so we can reconnect to the same signer later, even for `nostrconnect://` logins
+        let bunker_uri = signer
+            .bunker_uri()
+            .await
+            .map_err(|e| nostr_manager::NostrManagerError::RemoteSigner(e.to_string()))?;
+        signer.shutdown().await;
+
+        tracing::debug!(target: "whitenoise::accounts", "Remote signer connected for pubkey: {}", pubkey.to_hex());
+
+        let account = match Account::find_by_pubkey(&pubkey, wn).await {
+            Ok(account) => account,
+            Err(_) => Self::add_from_pubkey(pubkey, wn).await?,
+        };
 
+        tracing::debug!(target: "whitenoise::accounts", "Storing remote signer");
+        secrets_store::store_remote_signer(
+            &pubkey.to_hex(),
+            &bunker_uri.to_string(),
+            &app_keys,
+            &wn.data_dir,
+        )?;
+        if let Some(app_pubkey) = pending_app_pubkey {
+            secrets_store::remove_pending_remote_signer_app_keys(&app_pubkey, &wn.data_dir)?;
+        }
+
+        // Set active if requested
+        if set_active {
+            return account.set_active(wn).await;
+        }
+
+        Ok(account)
+    }
+
+    /// Logs in with a private key (nsec, hex or NIP-49 ncryptsec), a NIP-06 mnemonic or a NIP-46
+    /// remote signer URI, adding the account if it doesn't exist yet, and makes it the active account.
+    ///
+    /// The password decrypts an ncryptsec, or is the optional passphrase of a mnemonic. It's ignored for other formats.
+    pub async fn login(secret: &str, password: Option<&str>, wn: &Whitenoise) -> Result<Account> {
+        if remote_signer::is_remote_signer_uri(secret) {
+            tracing::debug!(target: "whitenoise::accounts", "Logging in with remote signer");
+            return Self::add_from_remote_signer(secret, true, wn).await;
+        }
+
+        let keys = if secret.trim().starts_with("ncryptsec1") {
+            let password = password.ok_or_else(|| {
+                AccountError::InvalidPassword(
+                    "A password is required for an encrypted private key".to_string(),
+                )
+            })?;
+            decrypt_ncryptsec(secret, password)?
+        } else if is_mnemonic(secret) {
+            keys_from_mnemonic(secret, password)?
+        } else {
+            Keys::parse(secret)?
+        };
+
+        match Account::find_by_pubkey(&keys.public_key(), wn).await {
+            Ok(account) => {
+                tracing::debug!(target: "whitenoise::accounts", "Account found, setting active");
+                account.set_active(wn).await
+            }
+            Err(_) => {
+                tracing::debug!(target: "whitenoise::accounts", "Account not found, adding from keys");
+                Self::add_from_keys(&keys, true, wn).await
+            }
+        }
+    }
+
+    /// Fetches the metadata and relays for a public key and saves it as a new account.
+    /// The caller is responsible for storing the account's secrets.
+    async fn add_from_pubkey(pubkey: PublicKey, wn: &Whitenoise) -> Result<Account> {
         tracing::debug!(target: "whitenoise::accounts", "Adding account for pubkey: {}", pubkey.to_hex());
 
         // Fetch metadata & relays from Nostr
@@ -189,43 +594,28 @@ impl Account {
         };
 
         tracing::debug!(target: "whitenoise::accounts", "Saving new account to database");
-        account.save(wn.clone()).await?;
+        account.save(wn).await?;
 
         tracing::debug!(target: "whitenoise::accounts", "Inserting nostr relays, {:?}", nostr_relays_unwrapped);
         account
-            .update_relays(RelayType::Nostr, &nostr_relays_unwrapped, wn.clone())
+            .update_relays(RelayType::Nostr, &nostr_relays_unwrapped, wn)
             .await?;
 
         tracing::debug!(target: "whitenoise::accounts", "Inserting inbox relays, {:?}", inbox_relays_unwrapped);
         account
-            .update_relays(RelayType::Inbox, &inbox_relays_unwrapped, wn.clone())
+            .update_relays(RelayType::Inbox, &inbox_relays_unwrapped, wn)
             .await?;
 
         tracing::debug!(target: "whitenoise::accounts", "Inserting key package relays, {:?}", key_package_relays_unwrapped);
         account
-            .update_relays(
-                RelayType::KeyPackage,
-                &key_package_relays_unwrapped,
-                wn.clone(),
-            )
+            .update_relays(RelayType::KeyPackage, &key_package_relays_unwrapped, wn)
             .await?;
 
-        tracing::debug!(target: "whitenoise::accounts", "Storing private key");
-        secrets_store::store_private_key(keys, &wn.data_dir)?;
-
-        // Set active if requested
-        if set_active {
-            account.set_active(wn.clone(), app_handle).await?;
-        }
-
         Ok(account)
     }
 
     /// Finds an account by its public key
-    pub async fn find_by_pubkey(
-        pubkey: &PublicKey,
-        wn: tauri::State<'_, Whitenoise>,
-    ) -> Result<Account> {
+    pub async fn find_by_pubkey(pubkey: &PublicKey, wn: &Whitenoise) -> Result<Account> {
         let mut txn = wn.database.pool.begin().await?;
 
         let row = sqlx::query_as::<_, AccountRow>("SELECT * FROM accounts WHERE pubkey = ?")
@@ -233,44 +623,24 @@ impl Account {
             .fetch_one(&mut *txn)
             .await?;
 
-        Ok(Account {
-            pubkey: PublicKey::parse(row.pubkey.as_str())?,
-            metadata: serde_json::from_str(&row.metadata)?,
-            settings: serde_json::from_str(&row.settings)?,
-            onboarding: serde_json::from_str(&row.onboarding)?,
-            last_used: Timestamp::from(row.last_used),
-            last_synced: Timestamp::from(row.last_synced),
-            active: row.active,
-        })
+        Ok(Account::try_from(row)?)
     }
 
-    /// Returns all accounts
-    pub async fn all(wn: tauri::State<'_, Whitenoise>) -> Result<Vec<Account>> {
+    /// Returns all accounts, skipping rows that can't be read
+    pub async fn all(wn: &Whitenoise) -> Result<Vec<Account>> {
         let mut txn = wn.database.pool.begin().await?;
 
-        let iter = sqlx::query_as::<_, AccountRow>("SELECT * FROM accounts")
+        let rows = sqlx::query_as::<_, AccountRow>("SELECT * FROM accounts")
             .fetch_all(&mut *txn)
             .await?;
 
-        iter.into_iter()
-            .map(|row| -> Result<Account> {
-                Ok(Account {
-                    pubkey: PublicKey::parse(row.pubkey.as_str())?,
-                    metadata: serde_json::from_str(&row.metadata)?,
-                    settings: serde_json::from_str(&row.settings)?,
-                    onboarding: serde_json::from_str(&row.onboarding)?,
-                    last_used: Timestamp::from(row.last_used),
-                    last_synced: Timestamp::from(row.last_synced),
-                    active: row.active,
-                })
-            })
-            .collect::<Result<Vec<_>>>()
+        Ok(convert_rows("accounts", rows, wn))
     }
 
     /// Returns the currently active account
-    pub async fn get_active(wn: tauri::State<'_, Whitenoise>) -> Result<Account> {
+    pub async fn get_active(wn: &Whitenoise) -> Result<Account> {
         // First validate/fix the active state
-        Self::validate_active_state(wn.clone()).await?;
+        Self::validate_active_state(wn).await?;
 
         let mut txn = wn.database.pool.begin().await?;
 
@@ -279,15 +649,7 @@ impl Account {
             .await?;
 
         match row {
-            Some(row) => Ok(Account {
-                pubkey: PublicKey::parse(row.pubkey.as_str())?,
-                metadata: serde_json::from_str(&row.metadata)?,
-                settings: serde_json::from_str(&row.settings)?,
-                onboarding: serde_json::from_str(&row.onboarding)?,
-                last_used: Timestamp::from(row.last_used),
-                last_synced: Timestamp::from(row.last_synced),
-                active: row.active,
-            }),
+            Some(row) => Ok(Account::try_from(row)?),
             None => Err(AccountError::NoActiveAccount),
         }
     }
@@ -305,9 +667,9 @@ impl Account {
     /// Returns error if:
     /// - No active account is found
     /// - Active account's public key is invalid
-    pub async fn get_active_pubkey(wn: tauri::State<'_, Whitenoise>) -> Result<PublicKey> {
+    pub async fn get_active_pubkey(wn: &Whitenoise) -> Result<PublicKey> {
         // First validate/fix the active state
-        Self::validate_active_state(wn.clone()).await?;
+        Self::validate_active_state(wn).await?;
 
         let mut txn = wn.database.pool.begin().await?;
 
@@ -323,11 +685,7 @@ impl Account {
     }
 
     /// Sets the active account in the database and updates nostr for the active identity
-    pub async fn set_active(
-        &self,
-        wn: tauri::State<'_, Whitenoise>,
-        app_handle: &tauri::AppHandle,
-    ) -> Result<Account> {
+    pub async fn set_active(&self, wn: &Whitenoise) -> Result<Account> {
         tracing::debug!(
             target: "whitenoise::accounts::set_active",
             "Starting set_active for pubkey: {}",
@@ -358,12 +716,10 @@ impl Account {
         txn.commit().await?;
 
         // Validate the active state as a safeguard
-        Self::validate_active_state(wn.clone()).await?;
+        Self::validate_active_state(wn).await?;
 
         // If the database operation is successful, update Nostr client
-        wn.nostr
-            .set_nostr_identity(self, wn.clone(), app_handle)
-            .await?;
+        wn.nostr.set_nostr_identity(self, wn).await?;
 
         tracing::debug!(
             target: "whitenoise::accounts::set_active",
@@ -371,21 +727,8 @@ impl Account {
             self.pubkey.to_hex()
         );
 
-        app_handle.emit("nostr_ready", ())?;
-
-        // Then update Nostr MLS instance
-        {
-            let mut nostr_mls = wn.nostr_mls.lock().await;
-            *nostr_mls = NostrMls::new(wn.data_dir.clone(), Some(self.pubkey.to_hex()));
-        }
-
-        tracing::debug!(
-            target: "whitenoise::accounts::set_active",
-            "Nostr MLS updated for: {}",
-            self.pubkey.to_hex()
-        );
-
-        app_handle.emit("account_changed", ())?;
+        wn.emit("nostr_ready", ());
+        wn.emit("account_changed", ());
 
         tracing::debug!(
             target: "whitenoise::accounts::set_active",
@@ -397,7 +740,7 @@ impl Account {
     }
 
     /// Returns the groups the account is a member of
-    pub async fn groups(&self, wn: tauri::State<'_, Whitenoise>) -> Result<Vec<Group>> {
+    pub async fn groups(&self, wn: &Whitenoise) -> Result<Vec<Group>> {
         let mut txn = wn.database.pool.begin().await?;
 
         let iter = sqlx::query_as::<_, GroupRow>("SELECT * FROM groups WHERE account_pubkey = ?")
@@ -405,28 +748,12 @@ impl Account {
             .fetch_all(&mut *txn)
             .await?;
 
-        iter.into_iter()
-            .map(|row| -> Result<Group> {
-                Ok(Group {
-                    mls_group_id: row.mls_group_id,
-                    account_pubkey: PublicKey::parse(row.account_pubkey.as_str())?,
-                    nostr_group_id: row.nostr_group_id,
-                    name: row.name,
-                    description: row.description,
-                    admin_pubkeys: serde_json::from_str(&row.admin_pubkeys)?,
-                    last_message_id: row.last_message_id,
-                    last_message_at: row.last_message_at.map(Timestamp::from),
-                    group_type: row.group_type.into(),
-                    epoch: row.epoch,
-                    state: row.state.into(),
-                })
-            })
-            .collect::<Result<Vec<_>>>()
+        Ok(convert_rows("groups", iter, wn))
     }
 
     /// Returns the invites the account has received
     #[allow(dead_code)]
-    pub async fn invites(&self, wn: tauri::State<'_, Whitenoise>) -> Result<Vec<Invite>> {
+    pub async fn invites(&self, wn: &Whitenoise) -> Result<Vec<Invite>> {
         let mut txn = wn.database.pool.begin().await?;
 
         let invite_rows =
@@ -435,29 +762,10 @@ impl Account {
                 .fetch_all(&mut *txn)
                 .await?;
 
-        invite_rows
-            .into_iter()
-            .map(|row| -> Result<Invite> {
-                Ok(Invite {
-                    event_id: row.event_id,
-                    account_pubkey: row.account_pubkey,
-                    event: serde_json::from_str(&row.event)?,
-                    mls_group_id: row.mls_group_id,
-                    nostr_group_id: row.nostr_group_id,
-                    group_name: row.group_name,
-                    group_description: row.group_description,
-                    group_admin_pubkeys: serde_json::from_str(&row.group_admin_pubkeys)?,
-                    group_relays: serde_json::from_str(&row.group_relays)?,
-                    inviter: row.inviter,
-                    member_count: row.member_count,
-                    state: row.state.into(),
-                    outer_event_id: row.outer_event_id,
-                })
-            })
-            .collect::<Result<Vec<_>>>()
+        Ok(convert_rows("invites", invite_rows, wn))
     }
 
-    pub async fn nostr_group_ids(&self, wn: tauri::State<'_, Whitenoise>) -> Result<Vec<String>> {
+    pub async fn nostr_group_ids(&self, wn: &Whitenoise) -> Result<Vec<String>> {
         Ok(self
             .groups(wn)
             .await?
@@ -467,7 +775,7 @@ impl Account {
     }
 
     #[allow(dead_code)]
-    pub async fn mls_group_ids(&self, wn: tauri::State<'_, Whitenoise>) -> Result<Vec<Vec<u8>>> {
+    pub async fn mls_group_ids(&self, wn: &Whitenoise) -> Result<Vec<Vec<u8>>> {
         Ok(self
             .groups(wn)
             .await?
@@ -476,18 +784,70 @@ impl Account {
             .collect())
     }
 
-    pub fn keys(&self, wn: tauri::State<'_, Whitenoise>) -> Result<Keys> {
+    pub fn keys(&self, wn: &Whitenoise) -> Result<Keys> {
         Ok(secrets_store::get_nostr_keys_for_pubkey(
             self.pubkey.to_hex().as_str(),
             &wn.data_dir,
         )?)
     }
 
-    pub async fn relays(
-        &self,
-        relay_type: RelayType,
-        wn: tauri::State<'_, Whitenoise>,
-    ) -> Result<Vec<String>> {
+    /// Returns the directory holding this account's `NostrMls` storage.
+    /// This is where `NostrMls::new` puts an identity's storage, which
+    /// `test_remove_account_removes_mls_storage` checks.
+    pub fn mls_storage_dir(&self, wn: &Whitenoise) -> std::path::PathBuf {
+        wn.data_dir.join("mls").join(self.pubkey.to_hex())
+    }
+
+    /// Returns the mnemonic this account was created from and removes it from the secrets store.
+    ///
+    /// The mnemonic is only available once, right after the identity was created.
+    /// Returns `None` if it has already been shown or the account wasn't created from a mnemonic.
+    pub fn take_mnemonic(&self, wn: &Whitenoise) -> Result<Option<String>> {
+        Ok(secrets_store::take_mnemonic(
+            &self.pubkey.to_hex(),
+            &wn.data_dir,
+        )?)
+    }
+
+    /// Checks that the given mnemonic derives this account's keys.
+    /// Used to verify the user wrote the mnemonic down correctly.
+    pub fn verify_mnemonic(&self, mnemonic: &str, passphrase: Option<&str>) -> bool {
+        keys_from_mnemonic(mnemonic, passphrase).is_ok_and(|keys| keys.public_key() == self.pubkey)
+    }
+
+    /// Exports the account's private key encrypted with a password as a NIP-49 `ncryptsec` string.
+    ///
+    /// `log_n` is the scrypt work factor; higher values are slower to brute force but also slower to decrypt.
+    pub fn export_ncryptsec(&self, password: &str, log_n: u8, wn: &Whitenoise) -> Result<String> {
+        if self.uses_remote_signer(wn)? {
+            return Err(AccountError::RemoteSignerAccount);
+        }
+        let keys = self.keys(wn)?;
+        encrypt_secret_key(keys.secret_key(), password, log_n)
+    }
+
+    /// Returns the signer for this account.
+    ///
+    /// This is a NIP-46 remote signer if the account was added with a bunker URI, otherwise the
+    /// locally stored keys.
+    pub async fn signer(&self, wn: &Whitenoise) -> Result<Arc<dyn NostrSigner>> {
+        match secrets_store::get_remote_signer(&self.pubkey.to_hex(), &wn.data_dir)? {
+            Some((bunker_uri, app_keys)) => {
+                let uri = remote_signer::parse_remote_signer_uri(&bunker_uri)?;
+                let connection_mode = wn.nostr.connection_mode_for_uri(&bunker_uri).await?;
+                let signer = remote_signer::connect_remote_signer(uri, app_keys, connection_mode)?;
+                Ok(signer.into_nostr_signer())
+            }
+            None => Ok(self.keys(wn)?.into_nostr_signer()),
+        }
+    }
+
+    /// Returns true if this account signs with a NIP-46 remote signer.
+    pub fn uses_remote_signer(&self, wn: &Whitenoise) -> Result<bool> {
+        Ok(secrets_store::get_remote_signer(&self.pubkey.to_hex(), &wn.data_dir)?.is_some())
+    }
+
+    pub async fn relays(&self, relay_type: RelayType, wn: &Whitenoise) -> Result<Vec<String>> {
         Ok(sqlx::query_scalar::<_, String>(
             "SELECT url FROM account_relays WHERE relay_type = ? AND account_pubkey = ?",
         )
@@ -501,7 +861,7 @@ impl Account {
         &self,
         relay_type: RelayType,
         relays: &Vec<String>,
-        wn: tauri::State<'_, Whitenoise>,
+        wn: &Whitenoise,
     ) -> Result<Account> {
         if relays.is_empty() {
             return Ok(self.clone());
@@ -527,8 +887,183 @@ impl Account {
         Ok(self.clone())
     }
 
+    /// Adds a relay to one of the account's relay lists.
+    ///
+    /// The updated list is published (kind 10002, 10050 or 10051) and the relay is added to the
+    /// live relay pool. Adding a relay that is already on the list does nothing.
+    ///
+    /// Returns the updated list.
+    pub async fn add_relay(
+        &self,
+        relay_type: RelayType,
+        url: &str,
+        wn: &Whitenoise,
+    ) -> Result<Vec<String>> {
+        let relay_url = validate_relay_url(relay_type, url)?;
+        self.active_signer(wn).await?;
+
+        let mut relays = self.relays(relay_type, wn).await?;
+        if relays.iter().any(|r| is_same_relay(r, &relay_url)) {
+            return Ok(relays);
+        }
+        let url = relay_url.to_string();
+        relays.push(url.clone());
+
+        let client = wn.nostr.client();
+        wn.nostr
+            .add_relay_to(&client, &url, relay_type == RelayType::Inbox)
+            .await?;
+        client
+            .connect_relay(&url)
+            .await
+            .map_err(nostr_manager::NostrManagerError::from)?;
+
+        self.publish_relay_list(relay_type, &relays, wn).await?;
+        self.update_relays(relay_type, &vec![url], wn).await?;
+
+        Ok(relays)
+    }
+
+    /// Removes a relay from one of the account's relay lists.
+    ///
+    /// The updated list is published (kind 10002, 10050 or 10051) and the relay is removed from the
+    /// live relay pool, unless it is still on another of the account's lists or a default relay.
+    /// Removing a relay that isn't on the list does nothing.
+    ///
+    /// Returns the updated list.
+    pub async fn remove_relay(
+        &self,
+        relay_type: RelayType,
+        url: &str,
+        wn: &Whitenoise,
+    ) -> Result<Vec<String>> {
+        let relay_url = validate_relay_url(relay_type, url)?;
+        self.active_signer(wn).await?;
+
+        let (removed, relays): (Vec<String>, Vec<String>) = self
+            .relays(relay_type, wn)
+            .await?
+            .into_iter()
+            .partition(|r| is_same_relay(r, &relay_url));
+        if removed.is_empty() {
+            return Ok(relays);
+        }
+
+        self.publish_relay_list(relay_type, &relays, wn).await?;
+
+        let mut txn = wn.database.pool.begin().await?;
+        for url in removed.iter() {
+            sqlx::query(
+                "DELETE FROM account_relays WHERE url = ? AND relay_type = ? AND account_pubkey = ?",
+            )
+            .bind(url)
+            .bind(String::from(relay_type))
+            .bind(self.pubkey.to_hex())
+            .execute(&mut *txn)
+            .await?;
+        }
+        txn.commit().await?;
+
+        let mut still_used = wn.nostr.relays().await?;
+        for other_type in [RelayType::Nostr, RelayType::Inbox, RelayType::KeyPackage] {
+            still_used.extend(self.relays(other_type, wn).await?);
+        }
+        if !still_used.iter().any(|r| is_same_relay(r, &relay_url)) {
+            wn.nostr
+                .client()
+                .remove_relay(relay_url)
+                .await
+                .map_err(nostr_manager::NostrManagerError::from)?;
+        }
+
+        Ok(relays)
+    }
+
+    /// Signs and publishes one of the account's relay lists to its outbox relays.
+    /// NIP-65 relay lists are also published to the relays on the list.
+    async fn publish_relay_list(
+        &self,
+        relay_type: RelayType,
+        relays: &[String],
+        wn: &Whitenoise,
+    ) -> Result<()> {
+        let relay_tags = || {
+            relays
+                .iter()
+                .map(|r| Tag::custom(TagKind::Relay, [r.clone()]))
+                .collect::<Vec<_>>()
+        };
+        let builder = match relay_type {
+            // Keep the read/write markers the user may have set in other clients
+            RelayType::Nostr => {
+                let metadata = wn.nostr.query_user_relay_metadata(self.pubkey).await?;
+                EventBuilder::relay_list(relays.iter().filter_map(|r| RelayUrl::parse(r).ok()).map(
+                    |url| {
+                        let marker = metadata.get(&url).cloned().flatten();
+                        (url, marker)
+                    },
+                ))
+            }
+            RelayType::Inbox => EventBuilder::new(Kind::InboxRelays, "").tags(relay_tags()),
+            RelayType::KeyPackage => {
+                EventBuilder::new(Kind::MlsKeyPackageRelays, "").tags(relay_tags())
+            }
+            RelayType::Group => {
+                return Err(AccountError::InvalidRelay(
+                    "Group relays are not an account relay list".to_string(),
+                ))
+            }
+        };
+
+        let mut targets = wn.nostr.query_user_write_relays(self.pubkey).await?;
+        if targets.is_empty() {
+            targets = self.relays(RelayType::Nostr, wn).await?;
+        }
+        if targets.is_empty() {
+            targets = wn.nostr.relays().await?;
+        }
+        if relay_type == RelayType::Nostr {
+            targets.extend(relays.iter().cloned());
+        }
+        targets.sort();
+        targets.dedup();
+
+        // Publishing only works to relays in the pool
+        let client = wn.nostr.client();
+        let targets = wn.nostr.add_relays_to(&client, &targets, false).await?;
+        client.connect().await;
+
+        tracing::debug!(
+            target: "whitenoise::accounts::publish_relay_list",
+            "Publishing {:?} relay list for {} to {:?}",
+            relay_type,
+            self.pubkey.to_hex(),
+            targets
+        );
+
+        wn.nostr
+            .publish_event_builder_to(PublishTarget::Outbox, targets, builder)
+            .await?;
+
+        Ok(())
+    }
+
+    /// Returns the signer of the client, making sure it belongs to this account.
+    async fn active_signer(&self, wn: &Whitenoise) -> Result<Arc<dyn NostrSigner>> {
+        let signer = wn
+            .nostr
+            .client()
+            .signer()
+            .await
+            .map_err(nostr_manager::NostrManagerError::from)?;
+        if signer.get_public_key().await? != self.pubkey {
+            return Err(AccountError::NotActiveAccount);
+        }
+        Ok(signer)
+    }
+
     /// Saves the account to the database
-    pub async fn save(&self, wn: tauri::State<'_, Whitenoise>) -> Result<Account> {
+    pub async fn save(&self, wn: &Whitenoise) -> Result<Account> {
         tracing::debug!(
             target: "whitenoise::accounts::save",
             "Beginning save transaction for pubkey: {}",
@@ -575,16 +1110,122 @@ impl Account {
         Ok(self.clone())
     }
 
-    /// Removes the account from the database
-    pub async fn remove(
-        &self,
-        wn: tauri::State<'_, Whitenoise>,
-        app_handle: tauri::AppHandle,
-    ) -> Result<()> {
+    /// Validates and saves new settings for this account.
+    ///
+    /// The settings are applied to the account's session immediately, and to the Nostr manager's
+    /// lockdown mode if the account is the active one. Emits `settings_changed` for the frontend.
+    pub async fn update_settings(
+        &mut self,
+        settings: AccountSettings,
+        wn: &Whitenoise,
+    ) -> Result<Account> {
+        settings.validate()?;
+
+        self.settings = settings;
+        self.save(wn).await?;
+
+        wn.nostr
+            .set_session_settings(&self.pubkey, &self.settings)
+            .await;
+
+        wn.emit(
+            "settings_changed",
+            SettingsChangedEvent {
+                pubkey: self.pubkey.to_hex(),
+                settings: self.settings.clone(),
+            },
+        );
+
+        Ok(self.clone())
+    }
+
+    /// Merges the update into the account's metadata, publishes a new kind 0 event to the
+    /// account's NIP-65 write relays and saves the account.
+    ///
+    /// Only the active account can be updated since events are signed by the Nostr client's signer.
+    pub async fn update_profile(
+        &mut self,
+        update: ProfileUpdate,
+        wn: &Whitenoise,
+    ) -> Result<Account> {
+        let signer = self.active_signer(wn).await?;
+
+        let metadata = update.merge_into(self.metadata.clone())?;
+
+        let mut relays = wn.nostr.query_user_write_relays(self.pubkey).await?;
+        if relays.is_empty() {
+            relays = self.relays(RelayType::Nostr, wn).await?;
+        }
+        if relays.is_empty() {
+            relays = wn.nostr.relays().await?;
+        }
+
+        let event = EventBuilder::metadata(&metadata).sign(&signer).await?;
+
+        tracing::debug!(
+            target: "whitenoise::accounts::update_profile",
+            "Publishing metadata for {} to {:?}",
+            self.pubkey.to_hex(),
+            relays
+        );
+
+        wn.nostr
+            .publish_event_to(PublishTarget::Outbox, relays, event)
+            .await?;
+
+        self.metadata = metadata;
+        self.save(wn).await
+    }
+
+    /// Removes the account and purges everything scoped to its pubkey:
+    /// - its database rows (groups, messages, invites and relays cascade from the account row)
+    /// - its `NostrMls` storage
+    /// - the export secrets of groups no other local account is a member of
+    /// - its private key or remote signer connection, mnemonic and NWC URI
+    /// - its events in the nostr database
+    ///
+    /// The next remaining account, if any, becomes the active account.
+    pub async fn remove(&self, wn: &Whitenoise) -> Result<AccountRemovalReport> {
         let hex_pubkey = self.pubkey.to_hex();
+        let mut report = AccountRemovalReport {
+            pubkey: hex_pubkey.clone(),
+            ..Default::default()
+        };
+
+        // Stop the account's session before its data goes away, this also releases its MLS storage
+        wn.nostr.stop_session(&self.pubkey).await?;
+
+        // Groups the account shares with another local account keep their export secrets and messages
+        let mut exclusive_groups = Vec::new();
+        for group in self.groups(wn).await? {
+            let shared = sqlx::query_scalar::<_, i64>(
+                "SELECT COUNT(*) FROM groups WHERE mls_group_id = ? AND account_pubkey != ?",
+            )
+            .bind(&group.mls_group_id)
+            .bind(hex_pubkey.as_str())
+            .fetch_one(&wn.database.pool)
+            .await?;
+            if shared == 0 {
+                exclusive_groups.push(group);
+            }
+        }
 
         let mut txn = wn.database.pool.begin().await?;
 
+        for (table, count) in [
+            ("groups", &mut report.groups),
+            ("messages", &mut report.messages),
+            ("invites", &mut report.invites),
+        ] {
+            *count = sqlx::query_scalar::<_, i64>(&format!(
+                "SELECT COUNT(*) FROM {} WHERE account_pubkey = ?",
+                table
+            ))
+            .bind(hex_pubkey.as_str())
+            .fetch_one(&mut *txn)
+            .await? as u64;
+        }
+
         // First remove the account from the database, this will cascade to other tables
         sqlx::query("DELETE FROM accounts WHERE pubkey = ?")
             .bind(hex_pubkey.as_str())
@@ -615,29 +1256,70 @@ impl Account {
 
         // If the database update succeeded, then we continue with other steps
 
-        // Remove the old account's private key from the secrets store
+        // Remove the old account's secrets from the secrets store
+        report.private_key = self.keys(wn).is_ok();
         secrets_store::remove_private_key_for_pubkey(&hex_pubkey, &wn.data_dir)?;
+        report.remote_signer = self.uses_remote_signer(wn)?;
+        secrets_store::remove_remote_signer(&hex_pubkey, &wn.data_dir)?;
+        report.mnemonic = secrets_store::take_mnemonic(&hex_pubkey, &wn.data_dir)?.is_some();
+        report.nostr_wallet_connect_uri = self.get_nostr_wallet_connect_uri(wn)?.is_some();
+        self.remove_nostr_wallet_connect_uri(wn)?;
+        for group in exclusive_groups.iter() {
+            report.export_secrets +=
+                secrets_store::remove_export_secrets_for_group(&group.mls_group_id, &wn.data_dir)?;
+        }
 
-        // Update Nostr client & Nostr MLS
-        let account = Account::get_active(wn.clone()).await?;
-        wn.nostr
-            .set_nostr_identity(&account, wn.clone(), &app_handle)
-            .await?;
-
-        app_handle.emit("nostr_ready", ())?;
+        // Switch to the next account's session, the removed account's session was already stopped
+        match remaining_account_pubkey {
+            Some(_) => {
+                let account = Account::get_active(wn).await?;
+                wn.nostr.set_nostr_identity(&account, wn).await?;
+                wn.emit("nostr_ready", ());
+            }
+            None => {
+                wn.nostr
+                    .client()
+                    .reset()
+                    .await
+                    .map_err(nostr_manager::NostrManagerError::from)?;
+            }
+        }
 
-        // Then update Nostr MLS instance
-        {
-            let mut nostr_mls = wn.nostr_mls.lock().await;
-            *nostr_mls = NostrMls::new(wn.data_dir.clone(), Some(hex_pubkey));
+        let mls_storage_dir = self.mls_storage_dir(wn);
+        if mls_storage_dir.exists() {
+            std::fs::remove_dir_all(&mls_storage_dir)?;
+            report.mls_storage = true;
         }
 
-        app_handle.emit("account_changed", ())?;
-        Ok(())
+        let local_pubkeys: Vec<PublicKey> = Account::all(wn)
+            .await?
+            .into_iter()
+            .map(|account| account.pubkey)
+            .collect();
+        report.nostr_events = wn
+            .nostr
+            .delete_events_for_pubkey(
+                self.pubkey,
+                exclusive_groups
+                    .into_iter()
+                    .map(|group| group.nostr_group_id)
+                    .collect(),
+                &local_pubkeys,
+            )
+            .await?;
+
+        tracing::debug!(
+            target: "whitenoise::accounts::remove",
+            "Removed account: {:?}",
+            report
+        );
+
+        wn.emit("account_changed", ());
+        Ok(report)
     }
 
     // Add a validation method
-    async fn validate_active_state(wn: tauri::State<'_, Whitenoise>) -> Result<()> {
+    async fn validate_active_state(wn: &Whitenoise) -> Result<()> {
         let mut txn = wn.database.pool.begin().await?;
 
         // Check if we have multiple active accounts
@@ -686,9 +1368,17 @@ impl Account {
     }
 
     /// Stores a Nostr Wallet Connect URI for this account
-    pub fn store_nostr_wallet_connect_uri(&self, nostr_wallet_connect_uri: &str, wn: tauri::State<'_, Whitenoise>) -> Result<()> {
-        secrets_store::store_nostr_wallet_connect_uri(&self.pubkey.to_hex(), nostr_wallet_connect_uri, &wn.data_dir)
-            .map_err(AccountError::SecretsStoreError)
+    pub fn store_nostr_wallet_connect_uri(
+        &self,
+        nostr_wallet_connect_uri: &str,
+        wn: &Whitenoise,
+    ) -> Result<()> {
+        secrets_store::store_nostr_wallet_connect_uri(
+            &self.pubkey.to_hex(),
+            nostr_wallet_connect_uri,
+            &wn.data_dir,
+        )
+        .map_err(AccountError::SecretsStoreError)
     }
 
     /// Retrieves the Nostr Wallet Connect URI for this account
@@ -696,14 +1386,246 @@ impl Account {
     /// # Returns
     /// * `Result<Option<String>>` - Some(uri) if a URI is stored, None if no URI is stored,
     ///   or an error if the operation fails
-    pub fn get_nostr_wallet_connect_uri(&self, wn: tauri::State<'_, Whitenoise>) -> Result<Option<String>> {
+    pub fn get_nostr_wallet_connect_uri(&self, wn: &Whitenoise) -> Result<Option<String>> {
         secrets_store::get_nostr_wallet_connect_uri(&self.pubkey.to_hex(), &wn.data_dir)
             .map_err(AccountError::SecretsStoreError)
     }
 
     /// Removes the Nostr Wallet Connect URI for this account
-    pub fn remove_nostr_wallet_connect_uri(&self, wn: tauri::State<'_, Whitenoise>) -> Result<()> {
+    pub fn remove_nostr_wallet_connect_uri(&self, wn: &Whitenoise) -> Result<()> {
         secrets_store::remove_nostr_wallet_connect_uri(&self.pubkey.to_hex(), &wn.data_dir)
             .map_err(AccountError::SecretsStoreError)
     }
 }
+
+/// Validates a relay URL for one of the account's relay lists.
+fn validate_relay_url(relay_type: RelayType, url: &str) -> Result<RelayUrl> {
+    if relay_type == RelayType::Group {
+        return Err(AccountError::InvalidRelay(
+            "Group relays are not an account relay list".to_string(),
+        ));
+    }
+    RelayUrl::parse(url.trim())
+        .map_err(|e| AccountError::InvalidRelay(format!("Invalid relay URL {}: {}", url, e)))
+}
+
+/// Returns true if the stored relay URL points to the same relay as `relay_url`.
+fn is_same_relay(stored: &str, relay_url: &RelayUrl) -> bool {
+    RelayUrl::parse(stored).is_ok_and(|url| &url == relay_url)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_account_settings_missing_fields_use_defaults() {
+        let settings: AccountSettings = serde_json::from_str(r#"{"dark_theme":false}"#).unwrap();
+        assert!(!settings.dark_theme);
+        assert!(!settings.dev_mode);
+        assert!(!settings.lockdown_mode);
+
+        let settings: AccountSettings = serde_json::from_str("{}").unwrap();
+        assert_eq!(settings, AccountSettings::default());
+    }
+
+    #[test]
+    fn test_account_settings_unknown_fields_are_ignored() {
+        let settings: AccountSettings = serde_json::from_str(
+            r#"{"dark_theme":true,"dev_mode":true,"lockdown_mode":false,"from_the_future":1}"#,
+        )
+        .unwrap();
+        assert!(settings.dev_mode);
+    }
+
+    #[test]
+    fn test_profile_update_merges_fields() {
+        let metadata = Metadata::new().name("alice").about("hello");
+        let update = ProfileUpdate {
+            display_name: Some("Alice".to_string()),
+            about: Some("".to_string()),
+            lud16: Some("alice@getalby.com".to_string()),
+            ..Default::default()
+        };
+
+        let merged = update.merge_into(metadata).unwrap();
+        assert_eq!(merged.name, Some("alice".to_string()));
+        assert_eq!(merged.display_name, Some("Alice".to_string()));
+        assert_eq!(merged.about, None);
+        assert_eq!(merged.lud16, Some("alice@getalby.com".to_string()));
+    }
+
+    #[test]
+    fn test_profile_update_rejects_invalid_fields() {
+        let update = ProfileUpdate {
+            picture: Some("not a url".to_string()),
+            ..Default::default()
+        };
+        assert!(matches!(
+            update.merge_into(Metadata::new()),
+            Err(AccountError::InvalidProfile(_))
+        ));
+
+        let update = ProfileUpdate {
+            nip05: Some("alice".to_string()),
+            ..Default::default()
+        };
+        assert!(matches!(
+            update.merge_into(Metadata::new()),
+            Err(AccountError::InvalidProfile(_))
+        ));
+    }
+
+    #[test]
+    fn test_keys_from_mnemonic_nip06_vector() {
+        let keys = keys_from_mnemonic(
+            "leader monkey parrot ring guide accident before fence cannon height naive bean",
+            None,
+        )
+        .unwrap();
+        assert_eq!(
+            keys.secret_key().to_secret_hex(),
+            "7f7ff03d123792d6ac594bfa67bf6d0c0ab55b6b1fdb6249303fe861f1ccba9a"
+        );
+        assert_eq!(
+            keys.public_key().to_hex(),
+            "17162c921dc4d2518f9a101db33695df1afb56ab82f5ff3e5da6eec3ca5cd917"
+        );
+
+        // Whitespace and case are normalized, a passphrase derives different keys
+        let normalized = keys_from_mnemonic(
+            "  Leader monkey parrot ring guide accident\nbefore fence cannon height naive bean ",
+            Some(""),
+        )
+        .unwrap();
+        assert_eq!(normalized.public_key(), keys.public_key());
+        let with_passphrase = keys_from_mnemonic(
+            "leader monkey parrot ring guide accident before fence cannon height naive bean",
+            Some("passphrase"),
+        )
+        .unwrap();
+        assert_ne!(with_passphrase.public_key(), keys.public_key());
+    }
+
+    #[test]
+    fn test_generate_mnemonic() {
+        let mnemonic = generate_mnemonic().unwrap();
+        assert!(is_mnemonic(&mnemonic));
+        assert_eq!(mnemonic.split_whitespace().count(), MNEMONIC_WORD_COUNT);
+        assert!(keys_from_mnemonic(&mnemonic, None).is_ok());
+
+        assert!(!is_mnemonic(
+            "nsec1d4ed5x49d7p24xn63flj4985dc4gpfngdhtqcxpth0ywhm6czxcs5l2exj"
+        ));
+        assert!(matches!(
+            keys_from_mnemonic(
+                "leader leader leader leader leader leader leader leader leader leader leader leader",
+                None
+            ),
+            Err(AccountError::InvalidMnemonic(_))
+        ));
+    }
+
+    #[test]
+    fn test_ncryptsec_round_trip() {
+        let keys = Keys::generate();
+        let ncryptsec =
+            encrypt_secret_key(keys.secret_key(), "correct horse", NCRYPTSEC_MIN_LOG_N).unwrap();
+        assert!(ncryptsec.starts_with("ncryptsec1"));
+
+        let decrypted = decrypt_ncryptsec(&ncryptsec, "correct horse").unwrap();
+        assert_eq!(decrypted.public_key(), keys.public_key());
+    }
+
+    #[test]
+    fn test_ncryptsec_wrong_password() {
+        let keys = Keys::generate();
+        let ncryptsec =
+            encrypt_secret_key(keys.secret_key(), "correct horse", NCRYPTSEC_MIN_LOG_N).unwrap();
+
+        assert!(matches!(
+            decrypt_ncryptsec(&ncryptsec, "battery staple"),
+            Err(AccountError::WrongPassword)
+        ));
+        assert!(matches!(
+            decrypt_ncryptsec("ncryptsec1invalid", "correct horse"),
+            Err(AccountError::InvalidEncryptedKey(_))
+        ));
+    }
+
+    #[test]
+    fn test_ncryptsec_rejects_invalid_parameters() {
+        let keys = Keys::generate();
+        assert!(matches!(
+            encrypt_secret_key(keys.secret_key(), "", NCRYPTSEC_MIN_LOG_N),
+            Err(AccountError::InvalidPassword(_))
+        ));
+        assert!(matches!(
+            encrypt_secret_key(keys.secret_key(), "password", NCRYPTSEC_MAX_LOG_N + 1),
+            Err(AccountError::InvalidEncryptedKey(_))
+        ));
+    }
+
+    #[test]
+    fn test_account_settings_validate() {
+        assert!(AccountSettings::default().validate().is_ok());
+
+        let settings = AccountSettings {
+            dev_mode: true,
+            lockdown_mode: true,
+            ..Default::default()
+        };
+        assert!(matches!(
+            settings.validate(),
+            Err(AccountError::InvalidSettings(_))
+        ));
+
+        let settings = AccountSettings {
+            lockdown_mode: true,
+            relay_auth: RelayAuthPolicy::Always,
+            ..Default::default()
+        };
+        assert!(matches!(
+            settings.validate(),
+            Err(AccountError::InvalidSettings(_))
+        ));
+    }
+
+    #[test]
+    fn test_validate_relay_url() {
+        let url = validate_relay_url(RelayType::Inbox, " wss://relay.example.com ").unwrap();
+        assert!(is_same_relay("wss://relay.example.com", &url));
+        assert!(is_same_relay("wss://relay.example.com/", &url));
+        assert!(!is_same_relay("wss://other.example.com", &url));
+
+        assert!(matches!(
+            validate_relay_url(RelayType::Nostr, "not a relay"),
+            Err(AccountError::InvalidRelay(_))
+        ));
+        assert!(matches!(
+            validate_relay_url(RelayType::Group, "wss://relay.example.com"),
+            Err(AccountError::InvalidRelay(_))
+        ));
+    }
+
+    #[test]
+    fn test_account_from_corrupt_row() {
+        let row = AccountRow {
+            pubkey: Keys::generate().public_key().to_hex(),
+            metadata: "{}".to_string(),
+            settings: "not json".to_string(),
+            onboarding: serde_json::to_string(&AccountOnboarding::default()).unwrap(),
+            last_used: 0,
+            last_synced: 0,
+            active: false,
+        };
+
+        assert!(matches!(
+            Account::try_from(row),
+            Err(DatabaseError::CorruptRow {
+                table: "accounts",
+                ..
+            })
+        ));
+    }
+}
@@ -0,0 +1,95 @@
+//! The WebSocket stream of the events the core emits.
+
+use crate::api::{ApiError, ApiState, Result};
+use futures_util::{SinkExt, StreamExt};
+use http_body_util::Full;
+use hyper::body::{Bytes, Incoming};
+use hyper::header::{CONNECTION, SEC_WEBSOCKET_ACCEPT, SEC_WEBSOCKET_KEY, UPGRADE};
+use hyper::upgrade::Upgraded;
+use hyper::{Request, Response, StatusCode};
+use hyper_util::rt::TokioIo;
+use serde_json::Value;
+use std::sync::Arc;
+use tokio::sync::{broadcast, watch};
+use tokio_tungstenite::tungstenite::handshake::derive_accept_key;
+use tokio_tungstenite::tungstenite::protocol::Role;
+use tokio_tungstenite::tungstenite::Message;
+use tokio_tungstenite::WebSocketStream;
+
+/// Accepts the WebSocket upgrade and streams events on the upgraded connection.
+pub(super) fn upgrade(
+    mut request: Request<Incoming>,
+    state: Arc<ApiState>,
+) -> Result<Response<Full<Bytes>>> {
+    let is_websocket = request
+        .headers()
+        .get(UPGRADE)
+        .is_some_and(|upgrade| upgrade.as_bytes().eq_ignore_ascii_case(b"websocket"));
+    let key = request
+        .headers()
+        .get(SEC_WEBSOCKET_KEY)
+        .filter(|_| is_websocket)
+        .ok_or_else(|| ApiError::BadRequest("Expected a WebSocket upgrade".to_string()))?;
+    let accept = derive_accept_key(key.as_bytes());
+
+    // Subscribe before answering, so the client doesn't miss events emitted right after it connected
+    let events = state.wn.subscribe_events();
+    let shutdown = state.shutdown.clone();
+    tokio::spawn(async move {
+        match hyper::upgrade::on(&mut request).await {
+            Ok(upgraded) => {
+                let socket =
+                    WebSocketStream::from_raw_socket(TokioIo::new(upgraded), Role::Server, None)
+                        .await;
+                stream_events(socket, events, shutdown).await;
+            }
+            Err(e) => {
+                tracing::warn!(target: "whitenoise::api::events", "WebSocket upgrade failed: {}", e)
+            }
+        }
+    });
+
+    Ok(Response::builder()
+        .status(StatusCode::SWITCHING_PROTOCOLS)
+        .header(CONNECTION, "upgrade")
+        .header(UPGRADE, "websocket")
+        .header(SEC_WEBSOCKET_ACCEPT, accept)
+        .body(Full::default())?)
+}
+
+/// Sends every event as `{"event": <name>, "payload": <payload>}` until the client goes away or the server stops.
+async fn stream_events(
+    mut socket: WebSocketStream<TokioIo<Upgraded>>,
+    mut events: broadcast::Receiver<(String, Value)>,
+    mut shutdown: watch::Receiver<bool>,
+) {
+    loop {
+        tokio::select! {
+            event = events.recv() => match event {
+                Ok((event, payload)) => {
+                    let message = serde_json::json!({ "event": event, "payload": payload });
+                    if socket.send(Message::Text(message.to_string())).await.is_err() {
+                        break;
+                    }
+                }
+                Err(broadcast::error::RecvError::Lagged(missed)) => {
+                    tracing::warn!(
+                        target: "whitenoise::api::events",
+                        "WebSocket client fell behind and missed {} events",
+                        missed
+                    );
+                }
+                Err(broadcast::error::RecvError::Closed) => break,
+            },
+            // Clients only ever close the stream, pings are answered while reading
+            message = socket.next() => match message {
+                Some(Ok(Message::Close(_))) | Some(Err(_)) | None => break,
+                Some(Ok(_)) => {}
+            },
+            _ = shutdown.changed() => {
+                let _ = socket.close(None).await;
+                break;
+            }
+        }
+    }
+}
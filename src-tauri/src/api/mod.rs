@@ -0,0 +1,385 @@
+//! An opt-in HTTP and WebSocket API on localhost, so other local tools can post into groups and
+//! react to messages. It is off until enabled in `api.json` in the data directory, only listens
+//! on 127.0.0.1, and every request needs the token from the settings, either as
+//! `Authorization: Bearer <token>` or, for WebSocket clients that can't set headers, as `?token=`.
+//!
+//! Endpoints:
+//! * `GET /groups` - The active account's groups
+//! * `POST /groups/<nostr group id>/messages` - Sends `{"message": ..., "kind": ..., "tags": ...}`
+//!   to a group. `kind` defaults to a chat message and `tags` to none. Returns the sent event.
+//! * `GET /search?q=<query>` - Searches for users, like the contact search in the app
+//! * `GET /events` - A WebSocket stream of every event the core emits to the frontend, as
+//!   `{"event": <name>, "payload": <payload>}` text messages
+
+mod events;
+mod routes;
+
+use crate::accounts::AccountError;
+use crate::groups::GroupError;
+use crate::nostr_manager::NostrManagerError;
+use crate::Whitenoise;
+use hyper::server::conn::http1;
+use hyper::service::service_fn;
+use hyper::StatusCode;
+use hyper_util::rt::TokioIo;
+use serde::{Deserialize, Serialize};
+use std::convert::Infallible;
+use std::net::{Ipv4Addr, SocketAddr};
+use std::path::Path;
+use std::sync::Arc;
+use thiserror::Error;
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::{watch, Mutex};
+use tokio::task::{JoinHandle, JoinSet};
+use uuid::Uuid;
+
+const API_SETTINGS_FILE: &str = "api.json";
+pub const DEFAULT_API_PORT: u16 = 7474;
+
+#[derive(Error, Debug)]
+pub enum ApiError {
+    #[error("Invalid API settings: {0}")]
+    InvalidSettings(String),
+
+    #[error("Missing or invalid token")]
+    Unauthorized,
+
+    #[error("Not found: {0}")]
+    NotFound(String),
+
+    #[error("Bad request: {0}")]
+    BadRequest(String),
+
+    #[error("IO error: {0}")]
+    IoError(#[from] std::io::Error),
+
+    #[error("JSON error: {0}")]
+    JsonError(#[from] serde_json::Error),
+
+    #[error("HTTP error: {0}")]
+    HttpError(#[from] hyper::http::Error),
+
+    #[error("Account error: {0}")]
+    AccountError(#[from] AccountError),
+
+    #[error("Group error: {0}")]
+    GroupError(#[from] GroupError),
+
+    #[error("Nostr Manager error: {0}")]
+    NostrManagerError(#[from] NostrManagerError),
+}
+
+pub type Result<T> = std::result::Result<T, ApiError>;
+
+impl ApiError {
+    fn status(&self) -> StatusCode {
+        match self {
+            ApiError::Unauthorized => StatusCode::UNAUTHORIZED,
+            ApiError::NotFound(_) | ApiError::GroupError(GroupError::GroupNotFound) => {
+                StatusCode::NOT_FOUND
+            }
+            ApiError::BadRequest(_) | ApiError::JsonError(_) => StatusCode::BAD_REQUEST,
+            ApiError::AccountError(AccountError::NoActiveAccount) => StatusCode::CONFLICT,
+            ApiError::GroupError(GroupError::PermissionDenied(_)) => StatusCode::FORBIDDEN,
+            _ => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ApiSettings {
+    pub enabled: bool,
+    /// The port on 127.0.0.1 to listen on.
+    pub port: u16,
+    /// The token clients have to send with every request.
+    pub token: String,
+}
+
+impl Default for ApiSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            port: DEFAULT_API_PORT,
+            token: String::new(),
+        }
+    }
+}
+
+impl ApiSettings {
+    /// Loads the settings from the data directory. The API is disabled if there are none.
+    pub fn load(data_dir: &Path) -> Result<Self> {
+        let path = data_dir.join(API_SETTINGS_FILE);
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let settings: Self = serde_json::from_str(&std::fs::read_to_string(path)?)?;
+        settings.validate()?;
+        Ok(settings)
+    }
+
+    pub fn save(&self, data_dir: &Path) -> Result<()> {
+        std::fs::write(
+            data_dir.join(API_SETTINGS_FILE),
+            serde_json::to_string_pretty(self)?,
+        )?;
+        Ok(())
+    }
+
+    pub fn validate(&self) -> Result<()> {
+        if self.enabled && self.token.len() < 32 {
+            return Err(ApiError::InvalidSettings(
+                "The token must be at least 32 characters".to_string(),
+            ));
+        }
+        Ok(())
+    }
+
+    /// Generates a random token.
+    pub fn generate_token() -> String {
+        format!("{}{}", Uuid::new_v4().simple(), Uuid::new_v4().simple())
+    }
+}
+
+/// What request handlers share.
+struct ApiState {
+    wn: Whitenoise,
+    token: String,
+    /// Changes when the server stops, so open WebSocket streams close too
+    shutdown: watch::Receiver<bool>,
+}
+
+struct RunningServer {
+    local_addr: SocketAddr,
+    shutdown: watch::Sender<bool>,
+    task: JoinHandle<()>,
+}
+
+/// Runs the API server while it is enabled.
+#[derive(Default)]
+pub struct ApiServer {
+    running: Mutex<Option<RunningServer>>,
+}
+
+impl ApiServer {
+    /// Starts, restarts or stops the server to match the settings.
+    pub async fn apply(&self, settings: &ApiSettings, wn: &Whitenoise) -> Result<()> {
+        settings.validate()?;
+
+        let mut running = self.running.lock().await;
+        if let Some(server) = running.take() {
+            let _ = server.shutdown.send(true);
+            // Wait for the listener to be dropped and the open connections to close, so the port
+            // can be bound again right away and no request is served with the old token
+            let _ = server.task.await;
+            tracing::info!(target: "whitenoise::api", "Stopped API server on {}", server.local_addr);
+        }
+
+        if !settings.enabled {
+            return Ok(());
+        }
+
+        let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, settings.port)).await?;
+        let local_addr = listener.local_addr()?;
+        let (shutdown, shutdown_rx) = watch::channel(false);
+        let state = Arc::new(ApiState {
+            wn: wn.clone(),
+            token: settings.token.clone(),
+            shutdown: shutdown_rx,
+        });
+        let task = tokio::spawn(serve(listener, state));
+        tracing::info!(target: "whitenoise::api", "Started API server on {}", local_addr);
+
+        *running = Some(RunningServer {
+            local_addr,
+            shutdown,
+            task,
+        });
+        Ok(())
+    }
+
+    /// The address the server listens on, if it is running.
+    pub async fn local_addr(&self) -> Option<SocketAddr> {
+        self.running
+            .lock()
+            .await
+            .as_ref()
+            .map(|server| server.local_addr)
+    }
+}
+
+/// Accepts connections until `shutdown` changes, then shuts down the open connections gracefully
+/// and waits for them, so no keep-alive connection goes on serving requests with the old state.
+async fn serve(listener: TcpListener, state: Arc<ApiState>) {
+    let mut shutdown = state.shutdown.clone();
+    let mut connections = JoinSet::new();
+    loop {
+        tokio::select! {
+            accepted = listener.accept() => {
+                let stream = match accepted {
+                    Ok((stream, _)) => stream,
+                    Err(e) => {
+                        tracing::warn!(target: "whitenoise::api", "Error accepting connection: {}", e);
+                        continue;
+                    }
+                };
+                connections.spawn(serve_connection(stream, state.clone()));
+            }
+            Some(_) = connections.join_next() => {}
+            _ = shutdown.changed() => break,
+        }
+    }
+    drop(listener);
+    while connections.join_next().await.is_some() {}
+}
+
+async fn serve_connection(stream: TcpStream, state: Arc<ApiState>) {
+    let mut shutdown = state.shutdown.clone();
+    let service = service_fn(move |request| {
+        let state = state.clone();
+        async move { Ok::<_, Infallible>(routes::handle(request, state).await) }
+    });
+    let connection = http1::Builder::new()
+        .serve_connection(TokioIo::new(stream), service)
+        .with_upgrades();
+    tokio::pin!(connection);
+
+    let result = tokio::select! {
+        result = connection.as_mut() => result,
+        _ = shutdown.changed() => {
+            connection.as_mut().graceful_shutdown();
+            connection.await
+        }
+    };
+    if let Err(e) = result {
+        tracing::debug!(target: "whitenoise::api", "Connection error: {}", e);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_harness::TestNetwork;
+    use futures_util::StreamExt;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio_tungstenite::tungstenite::Message;
+
+    /// Sends a request without a body and returns the status code and the body.
+    async fn request(addr: SocketAddr, path: &str, token: Option<&str>) -> (u16, String) {
+        let mut stream = TcpStream::connect(addr).await.unwrap();
+        let auth = token
+            .map(|token| format!("Authorization: Bearer {}\r\n", token))
+            .unwrap_or_default();
+        let request = format!(
+            "GET {} HTTP/1.1\r\nHost: localhost\r\n{}Connection: close\r\n\r\n",
+            path, auth
+        );
+        stream.write_all(request.as_bytes()).await.unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).await.unwrap();
+
+        let status = response[9..12].parse().unwrap();
+        let body = response
+            .split_once("\r\n\r\n")
+            .map(|(_, body)| body.to_string())
+            .unwrap_or_default();
+        (status, body)
+    }
+
+    fn enabled_settings() -> ApiSettings {
+        ApiSettings {
+            enabled: true,
+            port: 0,
+            token: ApiSettings::generate_token(),
+        }
+    }
+
+    #[test]
+    fn test_settings_validation() {
+        assert!(ApiSettings::default().validate().is_ok());
+        assert!(enabled_settings().validate().is_ok());
+
+        let short_token = ApiSettings {
+            token: "secret".to_string(),
+            ..enabled_settings()
+        };
+        assert!(matches!(
+            short_token.validate(),
+            Err(ApiError::InvalidSettings(_))
+        ));
+    }
+
+    #[test]
+    fn test_settings_roundtrip() {
+        let dir = tempfile::TempDir::new().unwrap();
+        assert_eq!(
+            ApiSettings::load(dir.path()).unwrap(),
+            ApiSettings::default()
+        );
+
+        let settings = enabled_settings();
+        settings.save(dir.path()).unwrap();
+        assert_eq!(ApiSettings::load(dir.path()).unwrap(), settings);
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_requests_need_the_token() {
+        let network = TestNetwork::start().await;
+        let instance = network.instance().await;
+        instance.new_account().await;
+
+        let settings = enabled_settings();
+        let server = ApiServer::default();
+        server.apply(&settings, instance.wn()).await.unwrap();
+        let addr = server.local_addr().await.unwrap();
+
+        assert_eq!(request(addr, "/groups", None).await.0, 401);
+        assert_eq!(request(addr, "/groups", Some("wrong")).await.0, 401);
+
+        let (status, body) = request(addr, "/groups", Some(&settings.token)).await;
+        assert_eq!(status, 200);
+        assert_eq!(body, "[]");
+
+        assert_eq!(request(addr, "/nope", Some(&settings.token)).await.0, 404);
+
+        // Disabling stops the server
+        server
+            .apply(&ApiSettings::default(), instance.wn())
+            .await
+            .unwrap();
+        assert!(server.local_addr().await.is_none());
+        assert!(TcpStream::connect(addr).await.is_err());
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_event_stream() {
+        let network = TestNetwork::start().await;
+        let instance = network.instance().await;
+
+        let settings = enabled_settings();
+        let server = ApiServer::default();
+        server.apply(&settings, instance.wn()).await.unwrap();
+        let addr = server.local_addr().await.unwrap();
+
+        let url = format!("ws://{}/events?token={}", addr, settings.token);
+        let (mut events, _) = tokio_tungstenite::connect_async(url).await.unwrap();
+
+        instance.wn().emit("account_changed", ());
+        // Other events, like relay status updates, can come first
+        let received = tokio::time::timeout(std::time::Duration::from_secs(5), async {
+            while let Some(Ok(Message::Text(text))) = events.next().await {
+                let event: serde_json::Value = serde_json::from_str(&text).unwrap();
+                if event["event"] == "account_changed" {
+                    return true;
+                }
+            }
+            false
+        })
+        .await
+        .expect("Timed out waiting for event");
+        assert!(received);
+
+        let url = format!("ws://{}/events?token=wrong", addr);
+        assert!(tokio_tungstenite::connect_async(url).await.is_err());
+    }
+}
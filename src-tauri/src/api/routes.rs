@@ -0,0 +1,187 @@
+//! Request handling for the API server.
+
+use crate::accounts::Account;
+use crate::api::{events, ApiError, ApiState, Result};
+use crate::groups::{Group, CHAT_MESSAGE_KIND};
+use http_body_util::{BodyExt, Full, Limited};
+use hyper::body::{Bytes, Incoming};
+use hyper::header::{AUTHORIZATION, CONTENT_TYPE};
+use hyper::{Method, Request, Response, StatusCode};
+use nostr_sdk::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+
+/// The largest request body we accept.
+const MAX_BODY_SIZE: usize = 64 * 1024;
+
+#[derive(Debug, Deserialize)]
+struct SendMessageRequest {
+    message: String,
+    kind: Option<u16>,
+    tags: Option<Vec<Tag>>,
+}
+
+pub(super) async fn handle(
+    request: Request<Incoming>,
+    state: Arc<ApiState>,
+) -> Response<Full<Bytes>> {
+    let method = request.method().clone();
+    let path = request.uri().path().to_string();
+
+    match route(request, &state).await {
+        Ok(response) => response,
+        Err(e) => {
+            if e.status().is_server_error() {
+                tracing::error!(target: "whitenoise::api", "{} {} failed: {}", method, path, e);
+            }
+            error_response(&e)
+        }
+    }
+}
+
+async fn route(request: Request<Incoming>, state: &Arc<ApiState>) -> Result<Response<Full<Bytes>>> {
+    if !authorized(&request, &state.token) {
+        return Err(ApiError::Unauthorized);
+    }
+
+    let method = request.method().clone();
+    let path = request.uri().path().to_string();
+    let segments: Vec<&str> = path.trim_matches('/').split('/').collect();
+    match (&method, segments.as_slice()) {
+        (&Method::GET, ["groups"]) => {
+            let groups = Account::get_active(&state.wn)
+                .await?
+                .groups(&state.wn)
+                .await?;
+            json_response(StatusCode::OK, &groups)
+        }
+        (&Method::POST, ["groups", nostr_group_id, "messages"]) => {
+            let nostr_group_id = nostr_group_id.to_string();
+            send_message(request, &nostr_group_id, state).await
+        }
+        (&Method::GET, ["search"]) => {
+            let query = query_param(&request, "q")
+                .ok_or_else(|| ApiError::BadRequest("Missing query parameter q".to_string()))?;
+            let users = state.wn.nostr.search_users(query, &state.wn).await?;
+            json_response(StatusCode::OK, &users)
+        }
+        (&Method::GET, ["events"]) => events::upgrade(request, state.clone()),
+        _ => Err(ApiError::NotFound(path.clone())),
+    }
+}
+
+async fn send_message(
+    request: Request<Incoming>,
+    nostr_group_id: &str,
+    state: &ApiState,
+) -> Result<Response<Full<Bytes>>> {
+    let account = Account::get_active(&state.wn).await?;
+    let group = Group::get_by_nostr_group_id(nostr_group_id, &account.pubkey, &state.wn).await?;
+
+    let body = Limited::new(request.into_body(), MAX_BODY_SIZE)
+        .collect()
+        .await
+        .map_err(|e| ApiError::BadRequest(format!("Error reading body: {}", e)))?
+        .to_bytes();
+    let body: SendMessageRequest = serde_json::from_slice(&body)?;
+
+    let event = group
+        .send_message(
+            body.message,
+            body.kind.unwrap_or(CHAT_MESSAGE_KIND),
+            body.tags,
+            &state.wn,
+        )
+        .await?;
+    json_response(StatusCode::CREATED, &event)
+}
+
+/// Checks the token of the `Authorization` header, or of the `token` query parameter.
+fn authorized<B>(request: &Request<B>, token: &str) -> bool {
+    let bearer = request
+        .headers()
+        .get(AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .map(str::to_string);
+
+    bearer
+        .or_else(|| query_param(request, "token"))
+        .is_some_and(|given| constant_time_eq(given.as_bytes(), token.as_bytes()))
+}
+
+fn query_param<B>(request: &Request<B>, name: &str) -> Option<String> {
+    let url = Url::parse(&format!("http://localhost{}", request.uri())).ok()?;
+    url.query_pairs()
+        .find(|(key, _)| key == name)
+        .map(|(_, value)| value.into_owned())
+}
+
+/// Compares without returning early, so the time taken doesn't give away how much of a token matched.
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    if a.len() != b.len() {
+        return false;
+    }
+    a.iter().zip(b).fold(0, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+pub(super) fn json_response<T: Serialize>(
+    status: StatusCode,
+    body: &T,
+) -> Result<Response<Full<Bytes>>> {
+    Ok(Response::builder()
+        .status(status)
+        .header(CONTENT_TYPE, "application/json")
+        .body(Full::new(Bytes::from(serde_json::to_vec(body)?)))?)
+}
+
+fn error_response(error: &ApiError) -> Response<Full<Bytes>> {
+    let body = serde_json::json!({ "error": error.to_string() }).to_string();
+    let mut response = Response::new(Full::new(Bytes::from(body)));
+    *response.status_mut() = error.status();
+    response
+        .headers_mut()
+        .insert(CONTENT_TYPE, "application/json".parse().unwrap());
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request(uri: &str, authorization: Option<&str>) -> Request<()> {
+        let mut builder = Request::builder().uri(uri);
+        if let Some(authorization) = authorization {
+            builder = builder.header(AUTHORIZATION, authorization);
+        }
+        builder.body(()).unwrap()
+    }
+
+    #[test]
+    fn test_authorized() {
+        let token = "0123456789abcdef0123456789abcdef";
+        assert!(authorized(
+            &request("/groups", Some(&format!("Bearer {}", token))),
+            token
+        ));
+        assert!(authorized(
+            &request(&format!("/events?token={}", token), None),
+            token
+        ));
+
+        assert!(!authorized(&request("/groups", None), token));
+        assert!(!authorized(&request("/groups", Some(token)), token));
+        assert!(!authorized(
+            &request("/groups", Some("Bearer 0123456789abcdef")),
+            token
+        ));
+        assert!(!authorized(&request("/events?token=", None), token));
+    }
+
+    #[test]
+    fn test_query_param() {
+        let request = request("/search?q=alice%20smith&limit=5", None);
+        assert_eq!(query_param(&request, "q"), Some("alice smith".to_string()));
+        assert_eq!(query_param(&request, "token"), None);
+    }
+}
@@ -0,0 +1,824 @@
+//! Encrypted account backups.
+//!
+//! A backup is a single file holding everything needed to restore one account on a fresh install:
+//! the account's SQLite rows, its `NostrMls` storage, the MLS exporter secrets and its private key
+//! (or remote signer connection). The contents are serialized as JSON and encrypted with
+//! XChaCha20-Poly1305, using a key derived from the user's passphrase with scrypt.
+
+use crate::accounts::{Account, AccountError};
+use crate::groups::{Group, GroupError, GroupState};
+use crate::key_packages::{self, KeyPackageError};
+use crate::nostr_manager::NostrManagerError;
+use crate::secrets_store::{self, SecretsStoreError};
+use crate::Whitenoise;
+use base64::{engine::general_purpose, Engine as _};
+use chacha20poly1305::aead::rand_core::RngCore;
+use chacha20poly1305::aead::{Aead, AeadCore, KeyInit, OsRng, Payload};
+use chacha20poly1305::{Key, XChaCha20Poly1305, XNonce};
+use nostr_sdk::prelude::*;
+use nostr_sdk::util::hex;
+use serde::{Deserialize, Serialize};
+use sqlx::{Connection, SqliteConnection};
+use std::path::{Component, Path, PathBuf};
+use thiserror::Error;
+
+const MAGIC: &[u8; 8] = b"WNBACKUP";
+const VERSION: u8 = 1;
+/// The scrypt work factor used for new backups
+const BACKUP_LOG_N: u8 = 16;
+/// The largest work factor accepted when reading a backup, to bound memory use
+const MAX_LOG_N: u8 = 22;
+const SALT_LEN: usize = 16;
+const NONCE_LEN: usize = 24;
+/// `MAGIC | version | log_n | salt | nonce`
+const HEADER_LEN: usize = MAGIC.len() + 2 + SALT_LEN + NONCE_LEN;
+
+/// How many of a group's messages since the backup are checked for a later epoch.
+const STALE_CHECK_LIMIT: usize = 50;
+
+/// Tables copied into a backup, in foreign key order, with the column holding the account pubkey.
+const TABLES: &[(&str, &str)] = &[
+    ("accounts", "pubkey"),
+    ("account_relays", "account_pubkey"),
+    ("groups", "account_pubkey"),
+    ("group_relays", "account_pubkey"),
+    ("invites", "account_pubkey"),
+    ("processed_invites", "account_pubkey"),
+    ("messages", "account_pubkey"),
+    ("processed_messages", "account_pubkey"),
+    ("sync_cursors", "account_pubkey"),
+    ("sync_gaps", "account_pubkey"),
+    ("bot_state", "account_pubkey"),
+];
+
+#[derive(Error, Debug)]
+pub enum BackupError {
+    #[error("Wrong passphrase or corrupted backup")]
+    WrongPassphrase,
+
+    #[error("Passphrase must not be empty")]
+    EmptyPassphrase,
+
+    #[error("Not a valid White Noise backup: {0}")]
+    InvalidArchive(String),
+
+    #[error("Unsupported backup version: {0}")]
+    UnsupportedVersion(u8),
+
+    #[error("Account already exists on this device")]
+    AccountExists,
+
+    #[error("Encryption error: {0}")]
+    EncryptionError(String),
+
+    #[error("File error: {0}")]
+    FileError(#[from] std::io::Error),
+
+    #[error("JSON error: {0}")]
+    JsonError(#[from] serde_json::Error),
+
+    #[error("Database error: {0}")]
+    DatabaseError(#[from] sqlx::Error),
+
+    #[error("Base64 error: {0}")]
+    Base64Error(#[from] base64::DecodeError),
+
+    #[error("Hex error: {0}")]
+    HexError(#[from] hex::Error),
+
+    #[error("Key error: {0}")]
+    KeyError(#[from] nostr_sdk::key::Error),
+
+    #[error("Account error: {0}")]
+    AccountError(#[from] AccountError),
+
+    #[error("Group error: {0}")]
+    GroupError(#[from] GroupError),
+
+    #[error("Key package error: {0}")]
+    KeyPackageError(#[from] KeyPackageError),
+
+    #[error("Nostr manager error: {0}")]
+    NostrManagerError(#[from] NostrManagerError),
+
+    #[error("Secrets store error: {0}")]
+    SecretsStoreError(#[from] SecretsStoreError),
+}
+
+pub type Result<T> = std::result::Result<T, BackupError>;
+
+/// A group that couldn't be restored because it moved on to a later epoch after the backup was made.
+/// The user needs to ask one of the group's admins to add them again.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct StaleGroup {
+    pub nostr_group_id: String,
+    pub name: String,
+    pub admin_pubkeys: Vec<String>,
+}
+
+/// The result of restoring a backup.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct RestoreReport {
+    pub account: Account,
+    /// Nostr group ids of the groups that were restored and can be used right away
+    pub restored_groups: Vec<String>,
+    /// Groups that were marked inactive and need a rejoin
+    pub stale_groups: Vec<StaleGroup>,
+    /// Nostr group ids of restored groups that couldn't be checked for a later epoch, e.g. because
+    /// their relays were unreachable. They may turn out to be stale.
+    pub unchecked_groups: Vec<String>,
+}
+
+/// How the backed up account signs events.
+#[derive(Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum BackupSigner {
+    PrivateKey {
+        secret_key: String,
+    },
+    RemoteSigner {
+        bunker_uri: String,
+        app_secret_key: String,
+    },
+}
+
+#[derive(Serialize, Deserialize)]
+struct BackupFile {
+    /// Path relative to the account's MLS storage directory, using `/` as separator
+    path: String,
+    /// Base64 encoded file contents
+    contents: String,
+}
+
+#[derive(Serialize, Deserialize)]
+struct BackupExportSecret {
+    mls_group_id: String,
+    epoch: u64,
+    secret: String,
+}
+
+#[derive(Serialize, Deserialize)]
+struct AccountBackup {
+    pubkey: String,
+    created_at: Timestamp,
+    signer: BackupSigner,
+    /// Base64 encoded SQLite database holding only this account's rows
+    database: String,
+    mls_storage: Vec<BackupFile>,
+    export_secrets: Vec<BackupExportSecret>,
+}
+
+/// Creates a passphrase-encrypted backup of an account.
+pub async fn export_account_backup(
+    account: &Account,
+    passphrase: &str,
+    wn: &Whitenoise,
+) -> Result<Vec<u8>> {
+    if passphrase.is_empty() {
+        return Err(BackupError::EmptyPassphrase);
+    }
+
+    let pubkey = account.pubkey.to_hex();
+
+    tracing::debug!(
+        target: "whitenoise::backup::export_account_backup",
+        "Creating backup for {}",
+        pubkey
+    );
+
+    let signer = match secrets_store::get_remote_signer(&pubkey, &wn.data_dir)? {
+        Some((bunker_uri, app_keys)) => BackupSigner::RemoteSigner {
+            bunker_uri,
+            app_secret_key: app_keys.secret_key().to_secret_hex(),
+        },
+        None => BackupSigner::PrivateKey {
+            secret_key: account.keys(wn)?.secret_key().to_secret_hex(),
+        },
+    };
+
+    let mut export_secrets = Vec::new();
+    for group in account.groups(wn).await? {
+        for (epoch, secret) in
+            secrets_store::get_all_export_secrets_for_group(&group.mls_group_id, &wn.data_dir)?
+        {
+            export_secrets.push(BackupExportSecret {
+                mls_group_id: hex::encode(&group.mls_group_id),
+                epoch,
+                secret,
+            });
+        }
+    }
+
+    let database = export_database(&pubkey, wn).await?;
+
+    // Hold the lock of the account's own MLS instance so the storage isn't written to while it's
+    // copied. Without a session nothing writes to the account's storage.
+    let mls_storage = match wn.nostr.session_nostr_mls(&account.pubkey).await {
+        Some(nostr_mls) => {
+            let _nostr_mls = nostr_mls.lock_owned().await;
+            read_dir_files(&account.mls_storage_dir(wn))?
+        }
+        None => read_dir_files(&account.mls_storage_dir(wn))?,
+    };
+
+    let backup = AccountBackup {
+        pubkey,
+        created_at: Timestamp::now(),
+        signer,
+        database: general_purpose::STANDARD.encode(database),
+        mls_storage,
+        export_secrets,
+    };
+
+    encrypt_archive(&serde_json::to_vec(&backup)?, passphrase, BACKUP_LOG_N)
+}
+
+/// Restores an account from a backup and makes it the active account.
+///
+/// Groups that have moved on to a later epoch since the backup was made are marked inactive
+/// and returned as stale, and a fresh key package is published so their admins can add the
+/// user back. For every other group our own keys are rotated, so that nothing sent from the
+/// restored state reuses keys the old device may have used after the backup was made.
+pub async fn restore_account_backup(
+    archive: &[u8],
+    passphrase: &str,
+    wn: &Whitenoise,
+) -> Result<RestoreReport> {
+    let backup: AccountBackup = serde_json::from_slice(&decrypt_archive(archive, passphrase)?)?;
+    let pubkey = PublicKey::from_hex(&backup.pubkey)?;
+
+    tracing::debug!(
+        target: "whitenoise::backup::restore_account_backup",
+        "Restoring backup for {} made at {}",
+        backup.pubkey,
+        backup.created_at
+    );
+
+    if Account::find_by_pubkey(&pubkey, wn).await.is_ok() {
+        return Err(BackupError::AccountExists);
+    }
+
+    let previous_active = Account::get_active(wn).await.ok();
+    restore_database(&general_purpose::STANDARD.decode(&backup.database)?, wn).await?;
+
+    // Without the rest of the restore the account is unusable, and its rows would make every retry
+    // fail with `AccountExists`
+    let result = restore_account(&pubkey, backup, wn).await;
+    if let Err(e) = &result {
+        tracing::warn!(
+            target: "whitenoise::backup::restore_account_backup",
+            "Restore failed, removing the partially restored account: {}",
+            e
+        );
+        discard_restored_account(&pubkey, previous_active, wn).await;
+    }
+    result
+}
+
+/// Restores everything of the backup but the database rows, which must already be restored.
+async fn restore_account(
+    pubkey: &PublicKey,
+    backup: AccountBackup,
+    wn: &Whitenoise,
+) -> Result<RestoreReport> {
+    let account = Account::find_by_pubkey(pubkey, wn).await?;
+
+    // Leftover storage from an earlier install of this account would be mixed with the restored state
+    let mls_storage_dir = account.mls_storage_dir(wn);
+    if mls_storage_dir.exists() {
+        std::fs::remove_dir_all(&mls_storage_dir)?;
+    }
+    write_dir_files(&mls_storage_dir, &backup.mls_storage)?;
+
+    for export_secret in backup.export_secrets {
+        secrets_store::store_mls_export_secret(
+            hex::decode(&export_secret.mls_group_id)?,
+            export_secret.epoch,
+            export_secret.secret,
+            &wn.data_dir,
+        )?;
+    }
+
+    match backup.signer {
+        BackupSigner::PrivateKey { secret_key } => {
+            secrets_store::store_private_key(&Keys::parse(&secret_key)?, &wn.data_dir)?
+        }
+        BackupSigner::RemoteSigner {
+            bunker_uri,
+            app_secret_key,
+        } => secrets_store::store_remote_signer(
+            &backup.pubkey,
+            &bunker_uri,
+            &Keys::parse(&app_secret_key)?,
+            &wn.data_dir,
+        )?,
+    }
+
+    let account = account.set_active(wn).await?;
+
+    let mut restored_groups = Vec::new();
+    let mut stale_groups = Vec::new();
+    let mut unchecked_groups = Vec::new();
+    for group in account.groups(wn).await? {
+        if !matches!(group.state, GroupState::Active) {
+            continue;
+        }
+
+        let checked = is_group_stale(&group, backup.created_at, wn).await;
+        if let Err(e) = &checked {
+            tracing::warn!(
+                target: "whitenoise::backup::restore_account_backup",
+                "Couldn't check group {} for a later epoch: {}",
+                group.nostr_group_id,
+                e
+            );
+        }
+        let stale = matches!(checked, Ok(true))
+            || match group.self_update_keys(wn).await {
+                Ok(()) => false,
+                Err(e) => {
+                    // Without fresh keys we can't safely send to the group
+                    tracing::warn!(
+                        target: "whitenoise::backup::restore_account_backup",
+                        "Failed to rotate keys for group {}: {}",
+                        group.nostr_group_id,
+                        e
+                    );
+                    true
+                }
+            };
+
+        if stale {
+            sqlx::query(
+                "UPDATE groups SET state = ? WHERE mls_group_id = ? AND account_pubkey = ?",
+            )
+            .bind(String::from(GroupState::Inactive))
+            .bind(&group.mls_group_id)
+            .bind(&backup.pubkey)
+            .execute(&wn.database.pool)
+            .await?;
+
+            stale_groups.push(StaleGroup {
+                nostr_group_id: group.nostr_group_id,
+                name: group.name,
+                admin_pubkeys: group.admin_pubkeys,
+            });
+        } else {
+            if checked.is_err() {
+                unchecked_groups.push(group.nostr_group_id.clone());
+            }
+            restored_groups.push(group.nostr_group_id);
+        }
+    }
+
+    if !stale_groups.is_empty() {
+        // Admins need a fresh key package to add this device back to the stale groups
+        key_packages::publish_key_package(wn).await?;
+    }
+
+    tracing::debug!(
+        target: "whitenoise::backup::restore_account_backup",
+        "Restored {} groups, {} stale, {} unchecked",
+        restored_groups.len(),
+        stale_groups.len(),
+        unchecked_groups.len()
+    );
+
+    Ok(RestoreReport {
+        account,
+        restored_groups,
+        stale_groups,
+        unchecked_groups,
+    })
+}
+
+/// Removes the rows, MLS storage and secrets of a failed restore and makes the previously active
+/// account active again. Errors are logged, the restore error is what gets reported.
+async fn discard_restored_account(
+    pubkey: &PublicKey,
+    previous_active: Option<Account>,
+    wn: &Whitenoise,
+) {
+    let removed = match Account::find_by_pubkey(pubkey, wn).await {
+        Ok(account) => account
+            .remove(wn)
+            .await
+            .map(|_| ())
+            .map_err(BackupError::from),
+        Err(_) => sqlx::query("DELETE FROM accounts WHERE pubkey = ?")
+            .bind(pubkey.to_hex())
+            .execute(&wn.database.pool)
+            .await
+            .map(|_| ())
+            .map_err(BackupError::from),
+    };
+    if let Err(e) = removed {
+        tracing::error!(
+            target: "whitenoise::backup::discard_restored_account",
+            "Failed to remove partially restored account {}: {}",
+            pubkey.to_hex(),
+            e
+        );
+    }
+
+    // Removing an account activates whichever account is left first, not necessarily the one
+    // that was active before the restore
+    let Some(previous_active) = previous_active else {
+        return;
+    };
+    let active = Account::get_active(wn)
+        .await
+        .ok()
+        .map(|account| account.pubkey);
+    if active != Some(previous_active.pubkey) {
+        if let Err(e) = previous_active.set_active(wn).await {
+            tracing::error!(
+                target: "whitenoise::backup::discard_restored_account",
+                "Failed to reactivate {}: {}",
+                previous_active.pubkey.to_hex(),
+                e
+            );
+        }
+    }
+}
+
+/// A group is stale if relays have messages for it, published after the backup was made,
+/// that can't be decrypted with the exporter secret of the backed up epoch.
+/// That means the group has moved on to a later epoch we can no longer follow.
+///
+/// Only the newest `STALE_CHECK_LIMIT` messages are checked. The group's relays are only connected
+/// to for the check.
+async fn is_group_stale(group: &Group, since: Timestamp, wn: &Whitenoise) -> Result<bool> {
+    let export_keys = match secrets_store::get_export_secret_keys_for_group(
+        group.mls_group_id.clone(),
+        group.epoch,
+        &wn.data_dir,
+    ) {
+        Ok(keys) => keys,
+        Err(_) => {
+            // The restored account's own MLS instance, whichever account is active
+            let nostr_mls = wn
+                .nostr
+                .session_nostr_mls(&group.account_pubkey)
+                .await
+                .ok_or_else(|| {
+                    NostrManagerError::AccountError(format!(
+                        "No session for {}",
+                        group.account_pubkey
+                    ))
+                })?;
+            let nostr_mls = nostr_mls.lock().await;
+            let (export_secret_hex, _) = nostr_mls
+                .export_secret_as_hex_secret_key_and_epoch(group.mls_group_id.clone())
+                .map_err(GroupError::from)?;
+            Keys::parse(&export_secret_hex)?
+        }
+    };
+
+    let filter = Filter::new()
+        .kind(Kind::MlsGroupMessage)
+        .custom_tag(
+            SingleLetterTag::lowercase(Alphabet::H),
+            vec![group.nostr_group_id.clone()],
+        )
+        .since(since)
+        .limit(STALE_CHECK_LIMIT);
+    let events = wn
+        .nostr
+        .fetch_events_from_temporary_relays(&group.relays(wn).await?, vec![filter])
+        .await?;
+
+    Ok(events.iter().any(|event| {
+        nip44::decrypt_to_bytes(
+            export_keys.secret_key(),
+            &export_keys.public_key(),
+            &event.content,
+        )
+        .is_err()
+    }))
+}
+
+fn temp_database_path(data_dir: &Path) -> PathBuf {
+    data_dir.join(format!("backup-{}.sqlite", uuid::Uuid::new_v4()))
+}
+
+async fn attach_backup_database(conn: &mut SqliteConnection, path: &Path) -> Result<()> {
+    sqlx::query("ATTACH DATABASE ? AS backup")
+        .bind(path.to_string_lossy().to_string())
+        .execute(conn)
+        .await?;
+    Ok(())
+}
+
+/// Copies the account's rows into a standalone SQLite database and returns the file contents.
+async fn export_database(pubkey: &str, wn: &Whitenoise) -> Result<Vec<u8>> {
+    let path = temp_database_path(&wn.data_dir);
+
+    let result: Result<Vec<u8>> = async {
+        let mut conn = wn.database.pool.acquire().await?;
+        attach_backup_database(&mut conn, &path).await?;
+
+        let copied: Result<()> = async {
+            for (table, column) in TABLES {
+                sqlx::query(&format!(
+                    "CREATE TABLE backup.{table} AS SELECT * FROM main.{table} WHERE {column} = ?"
+                ))
+                .bind(pubkey)
+                .execute(&mut *conn)
+                .await?;
+            }
+            Ok(())
+        }
+        .await;
+
+        sqlx::query("DETACH DATABASE backup")
+            .execute(&mut *conn)
+            .await?;
+        copied?;
+
+        Ok(std::fs::read(&path)?)
+    }
+    .await;
+
+    let _ = std::fs::remove_file(&path);
+    result
+}
+
+/// Inserts the rows of a backed up SQLite database into the main database in one transaction.
+async fn restore_database(database: &[u8], wn: &Whitenoise) -> Result<()> {
+    let path = temp_database_path(&wn.data_dir);
+    std::fs::write(&path, database)?;
+
+    let result: Result<()> = async {
+        let mut conn = wn.database.pool.acquire().await?;
+        attach_backup_database(&mut conn, &path).await?;
+
+        let copied: Result<()> = async {
+            // The account is activated after the restore, inserting it as active would
+            // conflict with the currently active account
+            sqlx::query("UPDATE backup.accounts SET active = FALSE")
+                .execute(&mut *conn)
+                .await?;
+
+            let mut txn = conn.begin().await?;
+            for (table, _) in TABLES {
+                // Backups made before a table was added don't have it
+                let backed_up = sqlx::query_scalar::<_, i64>(
+                    "SELECT COUNT(*) FROM backup.sqlite_master WHERE type = 'table' AND name = ?",
+                )
+                .bind(*table)
+                .fetch_one(&mut *txn)
+                .await?
+                    > 0;
+                if !backed_up {
+                    continue;
+                }
+
+                // Autoincrement ids are left out so they can't collide with existing rows
+                let columns = sqlx::query_scalar::<_, String>(
+                    "SELECT name FROM pragma_table_info(?, 'main') WHERE name != 'id'",
+                )
+                .bind(*table)
+                .fetch_all(&mut *txn)
+                .await?
+                .join(", ");
+
+                sqlx::query(&format!(
+                    "INSERT INTO main.{table} ({columns}) SELECT {columns} FROM backup.{table}"
+                ))
+                .execute(&mut *txn)
+                .await?;
+            }
+            txn.commit().await?;
+            Ok(())
+        }
+        .await;
+
+        sqlx::query("DETACH DATABASE backup")
+            .execute(&mut *conn)
+            .await?;
+        copied
+    }
+    .await;
+
+    let _ = std::fs::remove_file(&path);
+    result
+}
+
+/// Reads every file below `root`. Returns an empty list if `root` doesn't exist.
+fn read_dir_files(root: &Path) -> Result<Vec<BackupFile>> {
+    let mut files = Vec::new();
+    if !root.exists() {
+        return Ok(files);
+    }
+
+    let mut dirs = vec![root.to_path_buf()];
+    while let Some(dir) = dirs.pop() {
+        for entry in std::fs::read_dir(&dir)? {
+            let path = entry?.path();
+            if path.is_dir() {
+                dirs.push(path);
+                continue;
+            }
+            let relative = path
+                .strip_prefix(root)
+                .map_err(|e| BackupError::InvalidArchive(e.to_string()))?;
+            files.push(BackupFile {
+                path: relative.to_string_lossy().replace('\\', "/"),
+                contents: general_purpose::STANDARD.encode(std::fs::read(&path)?),
+            });
+        }
+    }
+
+    Ok(files)
+}
+
+/// Writes backed up files below `root`, refusing paths that would escape it.
+fn write_dir_files(root: &Path, files: &[BackupFile]) -> Result<()> {
+    for file in files {
+        let relative = Path::new(&file.path);
+        if relative
+            .components()
+            .any(|c| !matches!(c, Component::Normal(_)))
+        {
+            return Err(BackupError::InvalidArchive(format!(
+                "Invalid file path: {}",
+                file.path
+            )));
+        }
+
+        let path = root.join(relative);
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(path, general_purpose::STANDARD.decode(&file.contents)?)?;
+    }
+    Ok(())
+}
+
+fn derive_key(passphrase: &str, salt: &[u8], log_n: u8) -> Result<[u8; 32]> {
+    let params = scrypt::Params::new(log_n, 8, 1, 32)
+        .map_err(|e| BackupError::EncryptionError(e.to_string()))?;
+    let mut key = [0u8; 32];
+    scrypt::scrypt(passphrase.as_bytes(), salt, &params, &mut key)
+        .map_err(|e| BackupError::EncryptionError(e.to_string()))?;
+    Ok(key)
+}
+
+/// Encrypts `plaintext` into the backup file format. The header is authenticated as associated data.
+fn encrypt_archive(plaintext: &[u8], passphrase: &str, log_n: u8) -> Result<Vec<u8>> {
+    if passphrase.is_empty() {
+        return Err(BackupError::EmptyPassphrase);
+    }
+
+    let mut salt = [0u8; SALT_LEN];
+    OsRng.fill_bytes(&mut salt);
+    let nonce = XChaCha20Poly1305::generate_nonce(&mut OsRng);
+
+    let mut archive = Vec::with_capacity(HEADER_LEN + plaintext.len() + 16);
+    archive.extend_from_slice(MAGIC);
+    archive.push(VERSION);
+    archive.push(log_n);
+    archive.extend_from_slice(&salt);
+    archive.extend_from_slice(&nonce);
+
+    let key = derive_key(passphrase, &salt, log_n)?;
+    let cipher = XChaCha20Poly1305::new(Key::from_slice(&key));
+    let ciphertext = cipher
+        .encrypt(
+            &nonce,
+            Payload {
+                msg: plaintext,
+                aad: &archive,
+            },
+        )
+        .map_err(|e| BackupError::EncryptionError(e.to_string()))?;
+
+    archive.extend(ciphertext);
+    Ok(archive)
+}
+
+/// Decrypts a backup file created with [`encrypt_archive`].
+fn decrypt_archive(archive: &[u8], passphrase: &str) -> Result<Vec<u8>> {
+    if archive.len() < HEADER_LEN || &archive[..MAGIC.len()] != MAGIC {
+        return Err(BackupError::InvalidArchive("Missing header".to_string()));
+    }
+
+    let version = archive[MAGIC.len()];
+    if version != VERSION {
+        return Err(BackupError::UnsupportedVersion(version));
+    }
+    let log_n = archive[MAGIC.len() + 1];
+    if log_n > MAX_LOG_N {
+        return Err(BackupError::InvalidArchive(format!(
+            "Work factor {} is too large",
+            log_n
+        )));
+    }
+
+    let (header, ciphertext) = archive.split_at(HEADER_LEN);
+    let salt = &header[MAGIC.len() + 2..HEADER_LEN - NONCE_LEN];
+    let nonce = XNonce::from_slice(&header[HEADER_LEN - NONCE_LEN..]);
+
+    let key = derive_key(passphrase, salt, log_n)?;
+    let cipher = XChaCha20Poly1305::new(Key::from_slice(&key));
+    cipher
+        .decrypt(
+            nonce,
+            Payload {
+                msg: ciphertext,
+                aad: header,
+            },
+        )
+        .map_err(|_| BackupError::WrongPassphrase)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    // Keep the tests fast, the work factor doesn't affect the format
+    const TEST_LOG_N: u8 = 4;
+
+    #[test]
+    fn test_archive_round_trip() {
+        let archive = encrypt_archive(b"backup contents", "passphrase", TEST_LOG_N).unwrap();
+        assert!(archive.starts_with(MAGIC));
+
+        let plaintext = decrypt_archive(&archive, "passphrase").unwrap();
+        assert_eq!(plaintext, b"backup contents");
+    }
+
+    #[test]
+    fn test_archive_wrong_passphrase() {
+        let archive = encrypt_archive(b"backup contents", "passphrase", TEST_LOG_N).unwrap();
+        assert!(matches!(
+            decrypt_archive(&archive, "wrong passphrase"),
+            Err(BackupError::WrongPassphrase)
+        ));
+    }
+
+    #[test]
+    fn test_archive_rejects_tampering() {
+        let mut archive = encrypt_archive(b"backup contents", "passphrase", TEST_LOG_N).unwrap();
+
+        // The header is authenticated, changing the work factor breaks decryption
+        archive[MAGIC.len() + 1] = TEST_LOG_N + 1;
+        assert!(matches!(
+            decrypt_archive(&archive, "passphrase"),
+            Err(BackupError::WrongPassphrase)
+        ));
+
+        assert!(matches!(
+            decrypt_archive(b"not a backup", "passphrase"),
+            Err(BackupError::InvalidArchive(_))
+        ));
+        assert!(matches!(
+            encrypt_archive(b"backup contents", "", TEST_LOG_N),
+            Err(BackupError::EmptyPassphrase)
+        ));
+    }
+
+    #[test]
+    fn test_dir_files_round_trip() {
+        let source = TempDir::new().unwrap();
+        std::fs::create_dir_all(source.path().join("nested")).unwrap();
+        std::fs::write(source.path().join("mls.db"), b"storage").unwrap();
+        std::fs::write(source.path().join("nested").join("wal"), b"log").unwrap();
+
+        let files = read_dir_files(source.path()).unwrap();
+        assert_eq!(files.len(), 2);
+
+        let target = TempDir::new().unwrap();
+        write_dir_files(target.path(), &files).unwrap();
+        assert_eq!(
+            std::fs::read(target.path().join("mls.db")).unwrap(),
+            b"storage"
+        );
+        assert_eq!(
+            std::fs::read(target.path().join("nested").join("wal")).unwrap(),
+            b"log"
+        );
+
+        // A missing directory is an empty backup
+        assert!(read_dir_files(&target.path().join("missing"))
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn test_write_dir_files_rejects_escaping_paths() {
+        let target = TempDir::new().unwrap();
+        for path in ["../escape", "/etc/passwd", "nested/../../escape"] {
+            let files = vec![BackupFile {
+                path: path.to_string(),
+                contents: general_purpose::STANDARD.encode(b"x"),
+            }];
+            assert!(matches!(
+                write_dir_files(target.path(), &files),
+                Err(BackupError::InvalidArchive(_))
+            ));
+        }
+    }
+}
@@ -0,0 +1,232 @@
+//! Command line parsing for `whitenoise-cli`.
+
+use std::path::PathBuf;
+
+pub const USAGE: &str = "\
+Usage: whitenoise-cli [--data-dir <dir>] <command> [args]
+
+Options:
+  --data-dir <dir>    Where accounts, groups and keys are stored.
+                      Defaults to $WHITENOISE_DATA_DIR or ./whitenoise-data
+
+Commands:
+  create-identity                 Create a new identity and make it the active account
+  login                           Log in with a secret read from stdin (nsec, hex, ncryptsec,
+                                  mnemonic or bunker URI). The password, if any, is read from
+                                  $WHITENOISE_PASSWORD
+  accounts                        List accounts
+  use <pubkey>                    Make an account the active account
+  publish-key-package             Publish a new key package for the active account
+  groups                          List the active account's groups
+  create-group [--name <name>] [--description <text>] [--admin <pubkey>]... <member>...
+                                  Create a group with the given members
+  invites                         List pending invites
+  accept-invite <invite id>       Accept an invite and join its group
+  send <group id> <message>       Send a message to a group. Use - to read the message from stdin
+  tail <group id>                 Print the group's new messages until interrupted
+
+Every command prints JSON to stdout, one object per line. Errors are printed to stderr and
+exit with a non-zero status. Group ids are hex encoded MLS group ids.";
+
+#[derive(Debug, PartialEq)]
+pub struct Args {
+    pub data_dir: PathBuf,
+    pub command: Command,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum Command {
+    CreateIdentity,
+    Login,
+    Accounts,
+    Use {
+        pubkey: String,
+    },
+    PublishKeyPackage,
+    Groups,
+    CreateGroup {
+        name: String,
+        description: String,
+        admins: Vec<String>,
+        members: Vec<String>,
+    },
+    Invites,
+    AcceptInvite {
+        invite_id: String,
+    },
+    Send {
+        group_id: String,
+        message: String,
+    },
+    Tail {
+        group_id: String,
+    },
+    Help,
+}
+
+impl Command {
+    /// Whether the command needs the active account to be connected to its relays.
+    pub fn needs_nostr(&self) -> bool {
+        !matches!(
+            self,
+            Command::Accounts | Command::Groups | Command::Invites | Command::Help
+        )
+    }
+}
+
+/// Parses the arguments, without the program name.
+pub fn parse<I>(args: I, default_data_dir: PathBuf) -> Result<Args, String>
+where
+    I: IntoIterator<Item = String>,
+{
+    let mut args = args.into_iter().peekable();
+    let mut data_dir = default_data_dir;
+
+    while let Some(arg) = args.next_if(|arg| arg.starts_with("--")) {
+        match arg.as_str() {
+            "--data-dir" => data_dir = PathBuf::from(value(&mut args, "--data-dir")?),
+            "--help" => {
+                return Ok(Args {
+                    data_dir,
+                    command: Command::Help,
+                })
+            }
+            _ => return Err(format!("Unknown option: {}", arg)),
+        }
+    }
+
+    let name = args.next().ok_or("Missing command")?;
+    let command = match name.as_str() {
+        "create-identity" => Command::CreateIdentity,
+        "login" => Command::Login,
+        "accounts" => Command::Accounts,
+        "use" => Command::Use {
+            pubkey: value(&mut args, "use")?,
+        },
+        "publish-key-package" => Command::PublishKeyPackage,
+        "groups" => Command::Groups,
+        "create-group" => {
+            let mut name = String::new();
+            let mut description = String::new();
+            let mut admins = Vec::new();
+            let mut members = Vec::new();
+            while let Some(arg) = args.next() {
+                match arg.as_str() {
+                    "--name" => name = value(&mut args, "--name")?,
+                    "--description" => description = value(&mut args, "--description")?,
+                    "--admin" => admins.push(value(&mut args, "--admin")?),
+                    _ if arg.starts_with("--") => return Err(format!("Unknown option: {}", arg)),
+                    _ => members.push(arg),
+                }
+            }
+            if members.is_empty() {
+                return Err("create-group needs at least one member".to_string());
+            }
+            Command::CreateGroup {
+                name,
+                description,
+                admins,
+                members,
+            }
+        }
+        "invites" => Command::Invites,
+        "accept-invite" => Command::AcceptInvite {
+            invite_id: value(&mut args, "accept-invite")?,
+        },
+        "send" => Command::Send {
+            group_id: value(&mut args, "send")?,
+            message: value(&mut args, "send")?,
+        },
+        "tail" => Command::Tail {
+            group_id: value(&mut args, "tail")?,
+        },
+        "help" => Command::Help,
+        _ => return Err(format!("Unknown command: {}", name)),
+    };
+
+    if let Some(arg) = args.next() {
+        return Err(format!("Unexpected argument: {}", arg));
+    }
+
+    Ok(Args { data_dir, command })
+}
+
+/// Takes the next argument as the value of an option or command.
+fn value(args: &mut impl Iterator<Item = String>, name: &str) -> Result<String, String> {
+    args.next()
+        .ok_or_else(|| format!("Missing value for {}", name))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse_args(args: &[&str]) -> Result<Args, String> {
+        parse(
+            args.iter().map(|arg| arg.to_string()),
+            PathBuf::from("default"),
+        )
+    }
+
+    #[test]
+    fn test_parse_data_dir() {
+        let args = parse_args(&["--data-dir", "/tmp/wn", "groups"]).unwrap();
+        assert_eq!(args.data_dir, PathBuf::from("/tmp/wn"));
+        assert_eq!(args.command, Command::Groups);
+
+        let args = parse_args(&["accounts"]).unwrap();
+        assert_eq!(args.data_dir, PathBuf::from("default"));
+
+        assert!(parse_args(&["--data-dir"]).is_err());
+    }
+
+    #[test]
+    fn test_parse_create_group() {
+        let args = parse_args(&[
+            "create-group",
+            "--name",
+            "Ops",
+            "--admin",
+            "aa",
+            "bb",
+            "--admin",
+            "cc",
+            "dd",
+        ])
+        .unwrap();
+        assert_eq!(
+            args.command,
+            Command::CreateGroup {
+                name: "Ops".to_string(),
+                description: String::new(),
+                admins: vec!["aa".to_string(), "cc".to_string()],
+                members: vec!["bb".to_string(), "dd".to_string()],
+            }
+        );
+
+        assert!(parse_args(&["create-group", "--name", "Ops"]).is_err());
+        assert!(parse_args(&["create-group", "--color", "red", "bb"]).is_err());
+    }
+
+    #[test]
+    fn test_parse_send_and_tail() {
+        let args = parse_args(&["send", "abcd", "Deploy finished"]).unwrap();
+        assert_eq!(
+            args.command,
+            Command::Send {
+                group_id: "abcd".to_string(),
+                message: "Deploy finished".to_string(),
+            }
+        );
+
+        assert!(parse_args(&["send", "abcd"]).is_err());
+        assert!(parse_args(&["tail", "abcd", "extra"]).is_err());
+    }
+
+    #[test]
+    fn test_parse_unknown() {
+        assert!(parse_args(&[]).is_err());
+        assert!(parse_args(&["frobnicate"]).is_err());
+        assert!(parse_args(&["--verbose", "groups"]).is_err());
+    }
+}
@@ -0,0 +1,203 @@
+//! A headless White Noise client for scripts and CI.
+//! Runs the same core as the app without a webview, see `args::USAGE` for the commands.
+
+mod args;
+
+use args::{Args, Command, USAGE};
+use nostr_sdk::prelude::*;
+use nostr_sdk::util::hex;
+use serde::Serialize;
+use serde_json::Value;
+use std::error::Error;
+use std::io::{Read, Write};
+use std::path::PathBuf;
+use std::process::ExitCode;
+use std::sync::{Arc, OnceLock};
+use tracing_subscriber::{filter::EnvFilter, fmt};
+use whitenoise_lib::accounts::Account;
+use whitenoise_lib::groups::{Group, CHAT_MESSAGE_KIND};
+use whitenoise_lib::invites::Invite;
+use whitenoise_lib::key_packages;
+use whitenoise_lib::{EventSink, Whitenoise};
+
+type Result<T> = std::result::Result<T, Box<dyn Error>>;
+
+/// Prints the processed messages of the tailed group, and ignores all other events.
+#[derive(Default)]
+struct CliEventSink {
+    /// Nostr group id of the group being tailed
+    tail: OnceLock<String>,
+}
+
+impl EventSink for CliEventSink {
+    fn emit(&self, event: &str, payload: Value) {
+        let Some(nostr_group_id) = self.tail.get() else {
+            return;
+        };
+        // The payload is the group and the message
+        if event == "mls_message_processed"
+            && payload[0]["nostr_group_id"].as_str() == Some(nostr_group_id)
+        {
+            if let Err(e) = print_json(&payload[1]) {
+                tracing::error!(target: "whitenoise::cli", "Error printing message: {}", e);
+            }
+        }
+    }
+}
+
+#[tokio::main]
+async fn main() -> ExitCode {
+    let default_data_dir = std::env::var_os("WHITENOISE_DATA_DIR")
+        .map(PathBuf::from)
+        .unwrap_or_else(|| PathBuf::from("whitenoise-data"));
+
+    let args = match args::parse(std::env::args().skip(1), default_data_dir) {
+        Ok(args) => args,
+        Err(e) => {
+            eprintln!("{}\n\n{}", e, USAGE);
+            return ExitCode::from(2);
+        }
+    };
+
+    if args.command == Command::Help {
+        println!("{}", USAGE);
+        return ExitCode::SUCCESS;
+    }
+
+    // Logs go to stderr so they never mix with the JSON output
+    fmt()
+        .with_env_filter(
+            EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new("warn")),
+        )
+        .with_writer(std::io::stderr)
+        .init();
+
+    match run(args).await {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+async fn run(args: Args) -> Result<()> {
+    let logs_dir = args.data_dir.join("logs");
+    std::fs::create_dir_all(&logs_dir)?;
+
+    let events = Arc::new(CliEventSink::default());
+    let wn = Whitenoise::new(args.data_dir, logs_dir, events.clone()).await;
+
+    if args.command.needs_nostr() {
+        // Connects the active account to its relays, if there is one
+        if let Ok(account) = Account::get_active(&wn).await {
+            account.set_active(&wn).await?;
+        }
+    }
+
+    match args.command {
+        Command::CreateIdentity => {
+            let account = Account::new(&wn).await?.set_active(&wn).await?;
+            print_json(&account)?;
+        }
+        Command::Login => {
+            let secret = read_stdin()?;
+            let password = std::env::var("WHITENOISE_PASSWORD").ok();
+            let account = Account::login(secret.trim(), password.as_deref(), &wn).await?;
+            print_json(&account)?;
+        }
+        Command::Accounts => {
+            for account in Account::all(&wn).await? {
+                print_json(&account)?;
+            }
+        }
+        Command::Use { pubkey } => {
+            let account = Account::find_by_pubkey(&PublicKey::parse(&pubkey)?, &wn)
+                .await?
+                .set_active(&wn)
+                .await?;
+            print_json(&account)?;
+        }
+        Command::PublishKeyPackage => {
+            key_packages::publish_key_package(&wn).await?;
+            print_json(&serde_json::json!({ "published": true }))?;
+        }
+        Command::Groups => {
+            for group in Account::get_active(&wn).await?.groups(&wn).await? {
+                print_json(&group)?;
+            }
+        }
+        Command::CreateGroup {
+            name,
+            description,
+            admins,
+            members,
+        } => {
+            let creator = Account::get_active(&wn).await?.pubkey.to_hex();
+            // The creator is always an admin
+            let mut admin_pubkeys = vec![creator.clone()];
+            admin_pubkeys.extend(admins.into_iter().filter(|admin| *admin != creator));
+            let group =
+                Group::create(creator, members, admin_pubkeys, name, description, &wn).await?;
+            print_json(&group)?;
+        }
+        Command::Invites => {
+            for invite in Invite::pending(&wn).await? {
+                print_json(&invite)?;
+            }
+        }
+        Command::AcceptInvite { invite_id } => {
+            let account = Account::get_active(&wn).await?;
+            let mut invite = Invite::find_by_id(&account.pubkey.to_hex(), &invite_id, &wn).await?;
+            let group = invite.accept(&wn).await?;
+            print_json(&group)?;
+        }
+        Command::Send { group_id, message } => {
+            let message = match message.as_str() {
+                "-" => read_stdin()?,
+                _ => message,
+            };
+            let group = find_group(&group_id, &wn).await?;
+            let event = group
+                .send_message(message, CHAT_MESSAGE_KIND, None, &wn)
+                .await?;
+            print_json(&event)?;
+        }
+        Command::Tail { group_id } => {
+            let group = find_group(&group_id, &wn).await?;
+            let _ = events.tail.set(group.nostr_group_id);
+            tokio::signal::ctrl_c().await?;
+        }
+        Command::Help => unreachable!("help is handled before starting White Noise"),
+    }
+
+    Ok(())
+}
+
+/// Finds one of the active account's groups by its hex encoded MLS group id.
+async fn find_group(group_id: &str, wn: &Whitenoise) -> Result<Group> {
+    let mls_group_id = hex::decode(group_id).map_err(|e| format!("Invalid group id: {}", e))?;
+    Ok(Group::find_by_mls_group_id(&mls_group_id, wn).await?)
+}
+
+fn read_stdin() -> Result<String> {
+    let mut input = String::new();
+    std::io::stdin().read_to_string(&mut input)?;
+    Ok(input)
+}
+
+/// Prints a value as one line of JSON. MLS group ids are printed hex encoded, the way commands take them.
+fn print_json<T: Serialize>(value: &T) -> Result<()> {
+    let mut value = serde_json::to_value(value)?;
+    if let Some(mls_group_id) = value
+        .get("mls_group_id")
+        .and_then(|id| serde_json::from_value::<Vec<u8>>(id.clone()).ok())
+    {
+        value["mls_group_id"] = Value::String(hex::encode(mls_group_id));
+    }
+
+    let mut stdout = std::io::stdout().lock();
+    writeln!(stdout, "{}", value)?;
+    stdout.flush()?;
+    Ok(())
+}
@@ -0,0 +1,485 @@
+//! A runtime for bots that live in White Noise groups.
+//!
+//! A bot is a `Whitenoise` instance whose events go to a [`BotEventSink`] instead of a UI. The [`Bot`]
+//! reads them, accepts invites from allowlisted pubkeys and hands messages and other events to the
+//! registered handlers. Handlers reply through [`BotContext`] and can keep their own state in the
+//! `bot_state` table through [`BotState`].
+//!
+//! ```no_run
+//! # use std::sync::Arc;
+//! # use whitenoise_lib::bot::{self, Bot, BotContext, HandlerResult, MessageHandler};
+//! # use whitenoise_lib::groups::Group;
+//! # use whitenoise_lib::Whitenoise;
+//! # use nostr_sdk::prelude::*;
+//! struct Ping;
+//!
+//! #[async_trait::async_trait]
+//! impl MessageHandler for Ping {
+//!     async fn handle(&self, ctx: &BotContext, group: &Group, message: &UnsignedEvent) -> HandlerResult {
+//!         if message.content == "ping" {
+//!             ctx.send(group, "pong").await?;
+//!         }
+//!         Ok(())
+//!     }
+//! }
+//!
+//! # async fn example(data_dir: std::path::PathBuf, logs_dir: std::path::PathBuf, owner: PublicKey) -> Result<(), Box<dyn std::error::Error>> {
+//! let (sink, events) = bot::event_channel();
+//! let wn = Whitenoise::new(data_dir, logs_dir, Arc::new(sink)).await;
+//! let mut bot = Bot::new(wn, events).await?;
+//! bot.allow_inviter(owner);
+//! bot.on_message(Ping);
+//! bot.run().await?;
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::accounts::{Account, AccountError};
+use crate::event_sink::EventSink;
+use crate::groups::{Group, GroupError, CHAT_MESSAGE_KIND};
+use crate::invites::{Invite, InviteError, InviteState};
+use crate::key_packages::{self, KeyPackageError};
+use crate::Whitenoise;
+use nostr_sdk::prelude::*;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::HashSet;
+use thiserror::Error;
+use tokio::sync::mpsc;
+
+#[derive(Error, Debug)]
+pub enum BotError {
+    #[error("Database error: {0}")]
+    DatabaseError(#[from] sqlx::Error),
+    #[error("Account error: {0}")]
+    AccountError(#[from] AccountError),
+    #[error("Group error: {0}")]
+    GroupError(#[from] GroupError),
+    #[error("Invite error: {0}")]
+    InviteError(#[from] InviteError),
+    #[error("Key package error: {0}")]
+    KeyPackageError(#[from] KeyPackageError),
+    #[error("JSON error: {0}")]
+    JsonError(#[from] serde_json::Error),
+}
+
+pub type Result<T> = std::result::Result<T, BotError>;
+
+/// What handlers return. Errors are logged and don't stop the bot.
+pub type HandlerResult = std::result::Result<(), Box<dyn std::error::Error + Send + Sync>>;
+
+/// Handles messages that other members send to the bot's groups.
+#[async_trait::async_trait]
+pub trait MessageHandler: Send + Sync {
+    async fn handle(
+        &self,
+        ctx: &BotContext,
+        group: &Group,
+        message: &UnsignedEvent,
+    ) -> HandlerResult;
+}
+
+/// Handles any event the core emits, e.g. `group_added` or `invite_declined`, with its JSON payload.
+#[async_trait::async_trait]
+pub trait EventHandler: Send + Sync {
+    async fn handle(&self, ctx: &BotContext, payload: &Value) -> HandlerResult;
+}
+
+/// Forwards the core's events to a [`Bot`].
+pub struct BotEventSink {
+    sender: mpsc::UnboundedSender<(String, Value)>,
+}
+
+impl EventSink for BotEventSink {
+    fn emit(&self, event: &str, payload: Value) {
+        // The receiver is only gone once the bot has stopped
+        let _ = self.sender.send((event.to_string(), payload));
+    }
+}
+
+/// The events a [`BotEventSink`] forwards, read by [`Bot::run`].
+pub struct BotEvents {
+    receiver: mpsc::UnboundedReceiver<(String, Value)>,
+}
+
+/// Creates the sink to start the bot's `Whitenoise` instance with, and the events to start the bot with.
+pub fn event_channel() -> (BotEventSink, BotEvents) {
+    let (sender, receiver) = mpsc::unbounded_channel();
+    (BotEventSink { sender }, BotEvents { receiver })
+}
+
+/// What handlers get to act as the bot.
+#[derive(Clone)]
+pub struct BotContext {
+    wn: Whitenoise,
+    account: Account,
+}
+
+impl BotContext {
+    pub fn wn(&self) -> &Whitenoise {
+        &self.wn
+    }
+
+    /// The account the bot runs as.
+    pub fn account(&self) -> &Account {
+        &self.account
+    }
+
+    /// Sends a chat message to the group.
+    pub async fn send(&self, group: &Group, message: impl Into<String>) -> Result<UnsignedEvent> {
+        self.send_event(group, message, CHAT_MESSAGE_KIND, None)
+            .await
+    }
+
+    /// Sends a message of any kind to the group, e.g. a reaction or a zap receipt.
+    pub async fn send_event(
+        &self,
+        group: &Group,
+        message: impl Into<String>,
+        kind: u16,
+        tags: Option<Vec<Tag>>,
+    ) -> Result<UnsignedEvent> {
+        Ok(group
+            .send_message(message.into(), kind, tags, &self.wn)
+            .await?)
+    }
+
+    /// The bot's persistent state.
+    pub fn state(&self) -> BotState {
+        BotState {
+            account_pubkey: self.account.pubkey,
+            wn: self.wn.clone(),
+        }
+    }
+}
+
+/// Key/value state of a bot account, stored as JSON in the `bot_state` table.
+pub struct BotState {
+    account_pubkey: PublicKey,
+    wn: Whitenoise,
+}
+
+impl BotState {
+    pub async fn get<T: DeserializeOwned>(&self, key: &str) -> Result<Option<T>> {
+        let value = sqlx::query_scalar::<_, String>(
+            "SELECT value FROM bot_state WHERE account_pubkey = ? AND key = ?",
+        )
+        .bind(self.account_pubkey.to_hex())
+        .bind(key)
+        .fetch_optional(&self.wn.database.pool)
+        .await?;
+
+        Ok(value
+            .map(|value| serde_json::from_str(&value))
+            .transpose()?)
+    }
+
+    pub async fn set<T: Serialize>(&self, key: &str, value: &T) -> Result<()> {
+        sqlx::query(
+            "INSERT INTO bot_state (account_pubkey, key, value, updated_at)
+             VALUES (?, ?, ?, ?)
+             ON CONFLICT(account_pubkey, key) DO UPDATE SET
+                value = excluded.value,
+                updated_at = excluded.updated_at",
+        )
+        .bind(self.account_pubkey.to_hex())
+        .bind(key)
+        .bind(serde_json::to_string(value)?)
+        .bind(Timestamp::now().as_u64() as i64)
+        .execute(&self.wn.database.pool)
+        .await?;
+        Ok(())
+    }
+
+    pub async fn remove(&self, key: &str) -> Result<()> {
+        sqlx::query("DELETE FROM bot_state WHERE account_pubkey = ? AND key = ?")
+            .bind(self.account_pubkey.to_hex())
+            .bind(key)
+            .execute(&self.wn.database.pool)
+            .await?;
+        Ok(())
+    }
+}
+
+pub struct Bot {
+    ctx: BotContext,
+    events: BotEvents,
+    allowed_inviters: HashSet<PublicKey>,
+    message_handlers: Vec<Box<dyn MessageHandler>>,
+    event_handlers: Vec<(String, Box<dyn EventHandler>)>,
+}
+
+impl Bot {
+    /// Creates a bot that runs as the active account of `wn`.
+    pub async fn new(wn: Whitenoise, events: BotEvents) -> Result<Bot> {
+        let account = Account::get_active(&wn).await?;
+        Ok(Bot {
+            ctx: BotContext { wn, account },
+            events,
+            allowed_inviters: HashSet::new(),
+            message_handlers: Vec::new(),
+            event_handlers: Vec::new(),
+        })
+    }
+
+    pub fn context(&self) -> &BotContext {
+        &self.ctx
+    }
+
+    /// Accepts invites from this pubkey. Invites from anyone else stay pending.
+    pub fn allow_inviter(&mut self, pubkey: PublicKey) {
+        self.allowed_inviters.insert(pubkey);
+    }
+
+    pub fn on_message(&mut self, handler: impl MessageHandler + 'static) {
+        self.message_handlers.push(Box::new(handler));
+    }
+
+    pub fn on_event(&mut self, event: &str, handler: impl EventHandler + 'static) {
+        self.event_handlers
+            .push((event.to_string(), Box::new(handler)));
+    }
+
+    /// Connects the bot account and handles events until the process exits.
+    /// Invites that arrived while the bot was offline are handled first.
+    pub async fn run(mut self) -> Result<()> {
+        self.ctx.account = self.ctx.account.set_active(&self.ctx.wn).await?;
+
+        for invite in Invite::pending(&self.ctx.wn).await? {
+            if let Err(e) = self.handle_invite(invite).await {
+                tracing::error!(target: "whitenoise::bot", "Error handling invite: {}", e);
+            }
+        }
+
+        while let Some((event, payload)) = self.events.receiver.recv().await {
+            if let Err(e) = self.dispatch(&event, &payload).await {
+                tracing::error!(target: "whitenoise::bot", "Error handling {}: {}", event, e);
+            }
+        }
+
+        Ok(())
+    }
+
+    async fn dispatch(&self, event: &str, payload: &Value) -> Result<()> {
+        match event {
+            "invite_processed" => {
+                self.handle_invite(serde_json::from_value(payload.clone())?)
+                    .await?
+            }
+            "mls_message_processed" => {
+                let (group, message): (Group, UnsignedEvent) =
+                    serde_json::from_value(payload.clone())?;
+                self.handle_message(&group, &message).await;
+            }
+            _ => {}
+        }
+
+        for (_, handler) in self.event_handlers.iter().filter(|(name, _)| name == event) {
+            if let Err(e) = handler.handle(&self.ctx, payload).await {
+                tracing::error!(target: "whitenoise::bot", "Handler for {} failed: {}", event, e);
+            }
+        }
+
+        Ok(())
+    }
+
+    async fn handle_invite(&self, mut invite: Invite) -> Result<()> {
+        // Invites of other accounts on the same instance aren't the bot's to accept
+        if invite.account_pubkey != self.ctx.account.pubkey.to_hex()
+            || invite.state != InviteState::Pending
+        {
+            return Ok(());
+        }
+
+        let allowed = PublicKey::parse(&invite.inviter)
+            .map(|inviter| self.allowed_inviters.contains(&inviter))
+            .unwrap_or(false);
+        if !allowed {
+            tracing::info!(
+                target: "whitenoise::bot",
+                "Leaving invite to {} from {} pending, inviter is not allowlisted",
+                invite.group_name,
+                invite.inviter
+            );
+            return Ok(());
+        }
+
+        let group = invite.accept(&self.ctx.wn).await?;
+        tracing::info!(target: "whitenoise::bot", "Joined group {}", group.name);
+
+        // The event processor replaces the key package a welcome says it used. Without that
+        // reference there is nothing to replace, so publish a fresh one for the next invite.
+        if invite.event.tags.event_ids().next().is_none() {
+            key_packages::publish_key_package(&self.ctx.wn).await?;
+        }
+
+        Ok(())
+    }
+
+    async fn handle_message(&self, group: &Group, message: &UnsignedEvent) {
+        // Messages of other accounts on the same instance, and the bot's own, aren't for the handlers
+        if group.account_pubkey != self.ctx.account.pubkey
+            || message.pubkey == self.ctx.account.pubkey
+        {
+            return;
+        }
+
+        for handler in &self.message_handlers {
+            if let Err(e) = handler.handle(&self.ctx, group, message).await {
+                tracing::error!(target: "whitenoise::bot", "Message handler failed: {}", e);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_harness::{wait_for, TestNetwork};
+    use std::sync::Arc;
+
+    struct Echo;
+
+    #[async_trait::async_trait]
+    impl MessageHandler for Echo {
+        async fn handle(
+            &self,
+            ctx: &BotContext,
+            group: &Group,
+            message: &UnsignedEvent,
+        ) -> HandlerResult {
+            let count = ctx.state().get::<u64>("count").await?.unwrap_or(0) + 1;
+            ctx.state().set("count", &count).await?;
+            ctx.send(group, format!("{} #{}", message.content, count))
+                .await?;
+            Ok(())
+        }
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_bot_joins_and_replies() {
+        let network = TestNetwork::start().await;
+        let alice = network.instance().await;
+        let (sink, events) = event_channel();
+        let bot_instance = network.instance_with_events(Arc::new(sink)).await;
+
+        let alice_account = alice.new_account().await;
+        let bot_account = bot_instance.new_account().await;
+
+        let mut bot = Bot::new(bot_instance.wn().clone(), events)
+            .await
+            .expect("Failed to create bot");
+        bot.allow_inviter(alice_account.pubkey);
+        bot.on_message(Echo);
+        let state = bot.context().state();
+        tokio::spawn(bot.run());
+
+        let group = Group::create(
+            alice_account.pubkey.to_hex(),
+            vec![bot_account.pubkey.to_hex()],
+            vec![alice_account.pubkey.to_hex()],
+            "Deploys".to_string(),
+            "Deploy notifications".to_string(),
+            alice.wn(),
+        )
+        .await
+        .expect("Failed to create group");
+
+        // The bot joins on its own once the welcome arrives
+        let bot_group = wait_for("bot to join", || async {
+            Group::find_by_mls_group_id(&group.mls_group_id, bot_instance.wn())
+                .await
+                .ok()
+        })
+        .await;
+        assert_eq!(bot_group.name, "Deploys");
+
+        let ping = group
+            .send_message("ping".to_string(), CHAT_MESSAGE_KIND, None, alice.wn())
+            .await
+            .expect("Failed to send message");
+        bot_instance.wait_for_message(&bot_group, &ping).await;
+
+        let reply = wait_for("bot reply", || async {
+            group
+                .messages(alice.wn())
+                .await
+                .ok()?
+                .into_iter()
+                .find(|message| message.pubkey == bot_account.pubkey)
+        })
+        .await;
+        assert_eq!(reply.content, "ping #1");
+        assert_eq!(state.get::<u64>("count").await.unwrap(), Some(1));
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_bot_leaves_unknown_invites_pending() {
+        let network = TestNetwork::start().await;
+        let mallory = network.instance().await;
+        let (sink, events) = event_channel();
+        let bot_instance = network.instance_with_events(Arc::new(sink)).await;
+
+        let mallory_account = mallory.new_account().await;
+        let bot_account = bot_instance.new_account().await;
+
+        let bot = Bot::new(bot_instance.wn().clone(), events)
+            .await
+            .expect("Failed to create bot");
+        tokio::spawn(bot.run());
+
+        Group::create(
+            mallory_account.pubkey.to_hex(),
+            vec![bot_account.pubkey.to_hex()],
+            vec![mallory_account.pubkey.to_hex()],
+            "Spam".to_string(),
+            String::new(),
+            mallory.wn(),
+        )
+        .await
+        .expect("Failed to create group");
+
+        let invite = wait_for("invite", || async {
+            Invite::pending(bot_instance.wn())
+                .await
+                .ok()?
+                .into_iter()
+                .next()
+        })
+        .await;
+        // Give the bot the chance to wrongly accept it
+        tokio::time::sleep(std::time::Duration::from_secs(1)).await;
+        let invite = Invite::find_by_id(
+            &bot_account.pubkey.to_hex(),
+            &invite.event_id,
+            bot_instance.wn(),
+        )
+        .await
+        .expect("Invite not found");
+        assert_eq!(invite.state, InviteState::Pending);
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_bot_state() {
+        let network = TestNetwork::start().await;
+        let (sink, events) = event_channel();
+        let instance = network.instance_with_events(Arc::new(sink)).await;
+        instance.new_account().await;
+
+        let bot = Bot::new(instance.wn().clone(), events)
+            .await
+            .expect("Failed to create bot");
+        let state = bot.context().state();
+
+        assert_eq!(state.get::<String>("on_call").await.unwrap(), None);
+        state.set("on_call", &"alice").await.unwrap();
+        state.set("on_call", &"bob").await.unwrap();
+        assert_eq!(
+            state.get::<String>("on_call").await.unwrap(),
+            Some("bob".to_string())
+        );
+        state.remove("on_call").await.unwrap();
+        assert_eq!(state.get::<String>("on_call").await.unwrap(), None);
+    }
+}
@@ -1,28 +1,21 @@
 use crate::accounts::Account;
+use crate::commands::CommandError;
 use crate::whitenoise::Whitenoise;
-use nostr_sdk::prelude::*;
 
-/// Creates a new identity by generating a new keypair and logging in with it.
+/// Creates a new identity from a freshly generated NIP-06 mnemonic and logs in with it.
+///
+/// The mnemonic can be shown to the user once with `reveal_identity_mnemonic`.
 ///
 /// # Arguments
 ///
 /// * `wn` - A reference to the Whitenoise state.
-/// * `app_handle` - The app handle.
 ///
 /// # Returns
 ///
 /// * `Ok(Account)` - The newly created account.
-/// * `Err(String)` - An error message if there was an issue creating the identity.
+/// * `Err(CommandError)` - An error message if there was an issue creating the identity.
 #[tauri::command]
-pub async fn create_identity(
-    wn: tauri::State<'_, Whitenoise>,
-    app_handle: tauri::AppHandle,
-) -> Result<Account, String> {
-    let account = Account::new(wn.clone())
-        .await
-        .map_err(|e| format!("Error creating account: {}", e))?;
-    account
-        .set_active(wn.clone(), &app_handle)
-        .await
-        .map_err(|e| format!("Error setting active account: {}", e))
+pub async fn create_identity(wn: tauri::State<'_, Whitenoise>) -> Result<Account, CommandError> {
+    let account = Account::new(&wn).await?;
+    Ok(account.set_active(&wn).await?)
 }
@@ -0,0 +1,30 @@
+use crate::commands::CommandError;
+use crate::nostr_manager::remote_signer;
+use crate::secrets_store;
+use crate::whitenoise::Whitenoise;
+use nostr_sdk::prelude::*;
+
+/// Creates a `nostrconnect://` URI for logging in with a NIP-46 remote signer.
+///
+/// The URI is shown to the user (e.g. as a QR code) and scanned by their signer app.
+/// The login is completed by passing the same URI to `login`, which waits for the signer to connect.
+///
+/// # Arguments
+///
+/// * `wn` - A reference to the Whitenoise state
+///
+/// # Returns
+///
+/// * `Ok(String)` - The `nostrconnect://` URI
+/// * `Err(CommandError)` - An error message if the app keys could not be stored
+#[tauri::command]
+pub async fn create_nostr_connect_uri(
+    wn: tauri::State<'_, Whitenoise>,
+) -> Result<String, CommandError> {
+    let app_keys = Keys::generate();
+    let relays = wn.nostr.relays().await?;
+
+    secrets_store::store_pending_remote_signer_app_keys(&app_keys, &wn.data_dir)?;
+
+    Ok(remote_signer::nostr_connect_uri(&app_keys, &relays).to_string())
+}
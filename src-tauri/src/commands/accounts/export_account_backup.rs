@@ -0,0 +1,31 @@
+use crate::backup;
+use crate::commands::{find_account, CommandError};
+use crate::whitenoise::Whitenoise;
+
+/// Writes a passphrase-encrypted backup of an account to a file.
+///
+/// The backup contains the account's private key (or remote signer connection), its groups,
+/// messages and MLS state, and can be restored on another device with `restore_account_backup`.
+///
+/// # Arguments
+///
+/// * `pubkey` - The public key of the account to back up
+/// * `passphrase` - The passphrase to encrypt the backup with
+/// * `path` - The file to write the backup to
+/// * `wn` - A reference to the Whitenoise state
+///
+/// # Returns
+///
+/// * `Ok(())` - If the backup was written
+/// * `Err(CommandError)` - An error message if creating or writing the backup fails
+#[tauri::command]
+pub async fn export_account_backup(
+    pubkey: String,
+    passphrase: String,
+    path: String,
+    wn: tauri::State<'_, Whitenoise>,
+) -> Result<(), CommandError> {
+    let account = find_account(&pubkey, &wn).await?;
+    let archive = backup::export_account_backup(&account, &passphrase, &wn).await?;
+    Ok(std::fs::write(&path, archive)?)
+}
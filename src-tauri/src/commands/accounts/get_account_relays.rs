@@ -0,0 +1,24 @@
+use crate::accounts::Account;
+use crate::commands::CommandError;
+use crate::relays::RelayType;
+use crate::whitenoise::Whitenoise;
+
+/// Lists the relays on one of the active account's relay lists.
+///
+/// # Arguments
+///
+/// * `relay_type` - The relay list to read: `Nostr` (kind 10002), `Inbox` (kind 10050) or `KeyPackage` (kind 10051)
+/// * `wn` - A reference to the Whitenoise state
+///
+/// # Returns
+///
+/// * `Ok(Vec<String>)` - The relay URLs on the list
+/// * `Err(CommandError)` - An error message if there is no active account or the relays couldn't be read
+#[tauri::command]
+pub async fn get_account_relays(
+    relay_type: RelayType,
+    wn: tauri::State<'_, Whitenoise>,
+) -> Result<Vec<String>, CommandError> {
+    let account = Account::get_active(&wn).await?;
+    Ok(account.relays(relay_type, &wn).await?)
+}
@@ -0,0 +1,23 @@
+use crate::accounts::AccountSettings;
+use crate::commands::{find_account, CommandError};
+use crate::whitenoise::Whitenoise;
+
+/// Gets the settings for a specific account.
+///
+/// # Arguments
+///
+/// * `pubkey` - The public key of the account
+/// * `wn` - A reference to the Whitenoise state
+///
+/// # Returns
+///
+/// * `Ok(AccountSettings)` - The account's settings
+/// * `Err(CommandError)` - An error message if there was an issue fetching the account
+#[tauri::command]
+pub async fn get_account_settings(
+    pubkey: String,
+    wn: tauri::State<'_, Whitenoise>,
+) -> Result<AccountSettings, CommandError> {
+    let account = find_account(&pubkey, &wn).await?;
+    Ok(account.settings)
+}
@@ -1,6 +1,6 @@
 use crate::accounts::Account;
+use crate::commands::CommandError;
 use crate::whitenoise::Whitenoise;
-use nostr_sdk::prelude::*;
 
 /// Lists all accounts.
 ///
@@ -11,10 +11,8 @@ use nostr_sdk::prelude::*;
 /// # Returns
 ///
 /// * `Ok(Vec<Account>)` - A vector of accounts if successful.
-/// * `Err(String)` - An error message if there was an issue listing the accounts.
+/// * `Err(CommandError)` - An error message if there was an issue listing the accounts.
 #[tauri::command]
-pub async fn get_accounts(wn: tauri::State<'_, Whitenoise>) -> Result<Vec<Account>, String> {
-    Account::all(wn.clone())
-        .await
-        .map_err(|e| format!("Error fetching accounts: {}", e))
+pub async fn get_accounts(wn: tauri::State<'_, Whitenoise>) -> Result<Vec<Account>, CommandError> {
+    Ok(Account::all(&wn).await?)
 }
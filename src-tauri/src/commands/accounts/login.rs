@@ -1,39 +1,26 @@
 use crate::accounts::Account;
+use crate::commands::CommandError;
 use crate::whitenoise::Whitenoise;
-use nostr_sdk::prelude::*;
 
-/// Logs in with the given public key. Will set the active account if successful.
+/// Logs in with the given private key or NIP-46 remote signer URI. Will set the active account if successful.
 ///
 /// # Arguments
 ///
+/// * `nsec_or_hex_privkey` - The private key (nsec, hex or NIP-49 ncryptsec), a NIP-06 mnemonic, or a
+///   `bunker://` or `nostrconnect://` URI. With a remote signer URI no private key is stored on this device.
+/// * `password` - The password to decrypt an ncryptsec with, or the optional passphrase of a mnemonic.
+///   Ignored for other formats.
 /// * `wn` - A reference to the Whitenoise state.
-/// * `hex_pubkey` - The public key in hexadecimal format.
 ///
 /// # Returns
 ///
 /// * `Ok(Account)` - The account if login was successful.
-/// * `Err(String)` - An error message if there was an issue logging in.
+/// * `Err(CommandError)` - An error message if there was an issue logging in.
 #[tauri::command]
 pub async fn login(
     nsec_or_hex_privkey: String,
+    password: Option<String>,
     wn: tauri::State<'_, Whitenoise>,
-    app_handle: tauri::AppHandle,
-) -> Result<Account, String> {
-    let keys = Keys::parse(&nsec_or_hex_privkey).map_err(|e| e.to_string())?;
-
-    match Account::find_by_pubkey(&keys.public_key, wn.clone()).await {
-        Ok(account) => {
-            tracing::debug!("Account found, setting active");
-            account
-                .set_active(wn.clone(), &app_handle)
-                .await
-                .map_err(|e| format!("Error logging in: {}", e))
-        }
-        _ => {
-            tracing::debug!(target: "whitenoise::commands::accounts","Account not found, adding from keys");
-            Account::add_from_keys(&keys, true, wn.clone(), &app_handle)
-                .await
-                .map_err(|e| format!("Error logging in: {}", e))
-        }
-    }
+) -> Result<Account, CommandError> {
+    Ok(Account::login(&nsec_or_hex_privkey, password.as_deref(), &wn).await?)
 }
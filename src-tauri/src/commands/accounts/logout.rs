@@ -1,12 +1,12 @@
-use crate::accounts::Account;
+use crate::accounts::AccountRemovalReport;
+use crate::commands::{find_account, CommandError};
 use crate::whitenoise::Whitenoise;
-use nostr_sdk::prelude::*;
 
 /// Logs out the specified account.
 ///
 /// This function:
-/// 1. Removes the account from the account manager
-/// 2. Removes the private key from the secrets store
+/// 1. Removes the account and all of its data from the database
+/// 2. Removes the account's secrets, MLS storage and cached nostr events
 /// 3. Updates the Nostr identity to the new active account if needed
 ///
 /// # Arguments
@@ -16,21 +16,13 @@ use nostr_sdk::prelude::*;
 ///
 /// # Returns
 ///
-/// * `Ok(())` - If the logout was successful
-/// * `Err(String)` - An error message if there was an issue during logout
+/// * `Ok(AccountRemovalReport)` - What was removed, if the logout was successful
+/// * `Err(CommandError)` - An error message if there was an issue during logout
 #[tauri::command]
 pub async fn logout(
     hex_pubkey: String,
     wn: tauri::State<'_, Whitenoise>,
-    app_handle: tauri::AppHandle,
-) -> Result<(), String> {
-    let pubkey =
-        PublicKey::parse(&hex_pubkey).map_err(|e| format!("Error parsing public key: {}", e))?;
-    let account = Account::find_by_pubkey(&pubkey, wn.clone())
-        .await
-        .map_err(|e| format!("Error fetching account: {}", e))?;
-    account
-        .remove(wn.clone(), app_handle)
-        .await
-        .map_err(|e| format!("Error logging out: {}", e))
+) -> Result<AccountRemovalReport, CommandError> {
+    let account = find_account(&hex_pubkey, &wn).await?;
+    Ok(account.remove(&wn).await?)
 }
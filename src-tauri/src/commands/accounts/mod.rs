@@ -1,19 +1,41 @@
+mod add_account_relay;
 mod create_identity;
+mod create_nostr_connect_uri;
+mod export_account_backup;
+mod get_account_relays;
+mod get_account_settings;
 mod get_accounts;
 mod has_nostr_wallet_connect_uri;
 mod login;
 mod logout;
+mod remove_account_relay;
 mod remove_nostr_wallet_connect_uri;
+mod restore_account_backup;
+mod reveal_identity_mnemonic;
 mod set_active_account;
 mod set_nostr_wallet_connect_uri;
 mod update_account_onboarding;
+mod update_account_settings;
+mod update_profile;
+mod verify_identity_mnemonic;
 
+pub use add_account_relay::add_account_relay;
 pub use create_identity::create_identity;
+pub use create_nostr_connect_uri::create_nostr_connect_uri;
+pub use export_account_backup::export_account_backup;
+pub use get_account_relays::get_account_relays;
+pub use get_account_settings::get_account_settings;
 pub use get_accounts::get_accounts;
 pub use has_nostr_wallet_connect_uri::has_nostr_wallet_connect_uri;
 pub use login::login;
 pub use logout::logout;
+pub use remove_account_relay::remove_account_relay;
 pub use remove_nostr_wallet_connect_uri::remove_nostr_wallet_connect_uri;
+pub use restore_account_backup::restore_account_backup;
+pub use reveal_identity_mnemonic::reveal_identity_mnemonic;
 pub use set_active_account::set_active_account;
 pub use set_nostr_wallet_connect_uri::set_nostr_wallet_connect_uri;
 pub use update_account_onboarding::update_account_onboarding;
+pub use update_account_settings::update_account_settings;
+pub use update_profile::update_profile;
+pub use verify_identity_mnemonic::verify_identity_mnemonic;
@@ -0,0 +1,33 @@
+use crate::accounts::Account;
+use crate::commands::CommandError;
+use crate::relays::RelayType;
+use crate::whitenoise::Whitenoise;
+
+/// Removes a relay from one of the active account's relay lists.
+///
+/// The updated list is published as the matching replaceable event and the relay is
+/// disconnected, unless it's still on another of the account's lists or a default relay.
+///
+/// # Arguments
+///
+/// * `relay_type` - The relay list to change: `Nostr` (kind 10002), `Inbox` (kind 10050) or `KeyPackage` (kind 10051)
+/// * `url` - The relay URL to remove
+/// * `wn` - A reference to the Whitenoise state
+///
+/// # Returns
+///
+/// * `Ok(Vec<String>)` - The updated relay list
+/// * `Err(CommandError)` - An error message if the URL is invalid or publishing or saving fails
+#[tauri::command]
+pub async fn remove_account_relay(
+    relay_type: RelayType,
+    url: String,
+    wn: tauri::State<'_, Whitenoise>,
+) -> Result<Vec<String>, CommandError> {
+    let account = Account::get_active(&wn).await?;
+
+    account
+        .remove_relay(relay_type, &url, &wn)
+        .await
+        .map_err(|e| CommandError::from(e).with_relays(vec![url]))
+}
@@ -0,0 +1,29 @@
+use crate::backup::{self, RestoreReport};
+use crate::commands::CommandError;
+use crate::whitenoise::Whitenoise;
+
+/// Restores an account from a backup file created with `export_account_backup` and makes it the active account.
+///
+/// Groups that moved on since the backup was made can't be restored. They are returned in
+/// `stale_groups` together with their admins, who need to add the user back. Groups whose relays
+/// couldn't be checked are restored and listed in `unchecked_groups`.
+///
+/// # Arguments
+///
+/// * `path` - The backup file
+/// * `passphrase` - The passphrase the backup was encrypted with
+/// * `wn` - A reference to the Whitenoise state
+///
+/// # Returns
+///
+/// * `Ok(RestoreReport)` - The restored account and the state of its groups
+/// * `Err(CommandError)` - An error message if the passphrase is wrong, the account already exists or restoring fails
+#[tauri::command]
+pub async fn restore_account_backup(
+    path: String,
+    passphrase: String,
+    wn: tauri::State<'_, Whitenoise>,
+) -> Result<RestoreReport, CommandError> {
+    let archive = std::fs::read(&path)?;
+    Ok(backup::restore_account_backup(&archive, &passphrase, &wn).await?)
+}
@@ -0,0 +1,32 @@
+use crate::commands::{find_account, CommandError, ErrorCode};
+use crate::whitenoise::Whitenoise;
+
+/// Returns the mnemonic of a newly created identity so the user can write it down.
+///
+/// The mnemonic is removed from the secrets store once it has been revealed, so this only
+/// succeeds once per identity. Use `verify_identity_mnemonic` to check the user's backup.
+///
+/// # Arguments
+///
+/// * `pubkey` - The public key of the account
+/// * `wn` - A reference to the Whitenoise state
+///
+/// # Returns
+///
+/// * `Ok(String)` - The space separated mnemonic words
+/// * `Err(CommandError)` - An error message if the mnemonic was already revealed or doesn't exist
+#[tauri::command]
+pub async fn reveal_identity_mnemonic(
+    pubkey: String,
+    wn: tauri::State<'_, Whitenoise>,
+) -> Result<String, CommandError> {
+    let account = find_account(&pubkey, &wn).await?;
+
+    account.take_mnemonic(&wn)?.ok_or_else(|| {
+        CommandError::new(
+            ErrorCode::NotFound,
+            "The mnemonic for this account has already been shown",
+        )
+        .with_pubkey(&pubkey)
+    })
+}
@@ -1,6 +1,6 @@
 use crate::accounts::Account;
+use crate::commands::{CommandError, ErrorCode};
 use crate::whitenoise::Whitenoise;
-use nostr_sdk::prelude::*;
 use nwc::prelude::*;
 
 /// Sets the Nostr Wallet Connect URI for the active account.
@@ -13,23 +13,30 @@ use nwc::prelude::*;
 /// # Returns
 ///
 /// * `Ok(())` - If the URI was stored successfully
-/// * `Err(String)` - An error message if there was an issue storing the URI
+/// * `Err(CommandError)` - An error message if there was an issue storing the URI
 #[tauri::command]
 pub async fn set_nostr_wallet_connect_uri(
     nostr_wallet_connect_uri: String,
     wn: tauri::State<'_, Whitenoise>,
-) -> Result<(), String> {
-    let active_account = Account::get_active(wn.clone())
-        .await
-        .map_err(|e| format!("Error getting active account: {}", e))?;
-    let uri: NostrWalletConnectURI =
-        NostrWalletConnectURI::parse(&nostr_wallet_connect_uri).expect("Failed to parse NWC URI");
-    let nwc: NWC = NWC::new(uri);
-    nwc.get_info()
-        .await
-        .map_err(|e| format!("Error getting NWC info: {}", e))?;
+) -> Result<(), CommandError> {
+    let active_account = Account::get_active(&wn).await?;
+    let uri: NostrWalletConnectURI = NostrWalletConnectURI::parse(&nostr_wallet_connect_uri)
+        .map_err(|e| CommandError::invalid_input(format!("Invalid NWC URI: {}", e)))?;
+    // The wallet's relays are connected to through the proxy, like every other relay
+    let connection_mode = wn
+        .nostr
+        .connection_mode_for_uri(&nostr_wallet_connect_uri)
+        .await?;
+    let nwc: NWC = NWC::with_opts(
+        uri,
+        NostrWalletConnectOptions::new().connection_mode(connection_mode),
+    );
+    nwc.get_info().await.map_err(|e| {
+        CommandError::new(
+            ErrorCode::RelayError,
+            format!("Error getting NWC info: {}", e),
+        )
+    })?;
 
-    active_account
-        .store_nostr_wallet_connect_uri(&nostr_wallet_connect_uri, wn.clone())
-        .map_err(|e| format!("Error storing NWC URI: {}", e))
+    Ok(active_account.store_nostr_wallet_connect_uri(&nostr_wallet_connect_uri, &wn)?)
 }
@@ -1,6 +1,6 @@
 use crate::accounts::Account;
+use crate::commands::{find_account, CommandError};
 use crate::whitenoise::Whitenoise;
-use nostr_sdk::prelude::*;
 
 /// Updates the onboarding status for a specific account.
 ///
@@ -15,7 +15,7 @@ use nostr_sdk::prelude::*;
 /// # Returns
 ///
 /// * `Ok(Account)` - The updated account if successful
-/// * `Err(String)` - An error message if there was an issue updating the account
+/// * `Err(CommandError)` - An error message if there was an issue updating the account
 #[tauri::command]
 pub async fn update_account_onboarding(
     pubkey: String,
@@ -23,18 +23,11 @@ pub async fn update_account_onboarding(
     key_package_relays: bool,
     publish_key_package: bool,
     wn: tauri::State<'_, Whitenoise>,
-) -> Result<Account, String> {
-    let pubkey =
-        PublicKey::parse(&pubkey).map_err(|e| format!("Error parsing public key: {}", e))?;
-    let mut account = Account::find_by_pubkey(&pubkey, wn.clone())
-        .await
-        .map_err(|e| format!("Error fetching account: {}", e))?;
+) -> Result<Account, CommandError> {
+    let mut account = find_account(&pubkey, &wn).await?;
     account.onboarding.inbox_relays = inbox_relays;
     account.onboarding.key_package_relays = key_package_relays;
     account.onboarding.publish_key_package = publish_key_package;
-    account
-        .save(wn.clone())
-        .await
-        .map_err(|e| format!("Error saving account: {}", e))?;
+    account.save(&wn).await?;
     Ok(account)
 }
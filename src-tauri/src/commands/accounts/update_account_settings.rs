@@ -0,0 +1,28 @@
+use crate::accounts::{Account, AccountSettings};
+use crate::commands::{find_account, CommandError};
+use crate::whitenoise::Whitenoise;
+
+/// Updates the settings for a specific account.
+///
+/// # Arguments
+///
+/// * `pubkey` - The public key of the account to update
+/// * `settings` - The new settings
+/// * `wn` - A reference to the Whitenoise state
+///
+/// # Returns
+///
+/// * `Ok(Account)` - The updated account if successful
+/// * `Err(CommandError)` - An error message if the settings are invalid or couldn't be saved
+///
+/// # Events Emitted
+/// * `settings_changed` - Emitted with the account's pubkey and new settings
+#[tauri::command]
+pub async fn update_account_settings(
+    pubkey: String,
+    settings: AccountSettings,
+    wn: tauri::State<'_, Whitenoise>,
+) -> Result<Account, CommandError> {
+    let mut account = find_account(&pubkey, &wn).await?;
+    Ok(account.update_settings(settings, &wn).await?)
+}
@@ -0,0 +1,33 @@
+use crate::accounts::{Account, ProfileUpdate};
+use crate::commands::CommandError;
+use crate::whitenoise::Whitenoise;
+
+/// Updates the profile metadata (kind 0) of the active account.
+///
+/// The given fields are merged into the existing metadata, signed and published
+/// to the account's NIP-65 write relays, and saved to the database.
+///
+/// # Arguments
+///
+/// * `update` - The fields to change. Missing fields are left untouched, empty strings clear the field.
+/// * `wn` - A reference to the Whitenoise state
+///
+/// # Returns
+///
+/// * `Ok(Account)` - The updated account if successful
+/// * `Err(CommandError)` - An error message if validation, publishing or saving fails
+///
+/// # Events Emitted
+/// * `account_changed` - Emitted after the account has been saved
+#[tauri::command]
+pub async fn update_profile(
+    update: ProfileUpdate,
+    wn: tauri::State<'_, Whitenoise>,
+) -> Result<Account, CommandError> {
+    let mut account = Account::get_active(&wn).await?;
+    let account = account.update_profile(update, &wn).await?;
+
+    wn.emit("account_changed", ());
+
+    Ok(account)
+}
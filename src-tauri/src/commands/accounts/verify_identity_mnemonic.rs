@@ -0,0 +1,27 @@
+use crate::commands::{find_account, CommandError};
+use crate::whitenoise::Whitenoise;
+
+/// Checks that a mnemonic entered by the user restores the given account.
+///
+/// # Arguments
+///
+/// * `pubkey` - The public key of the account
+/// * `mnemonic` - The mnemonic as written down by the user
+/// * `passphrase` - The optional BIP-39 passphrase
+/// * `wn` - A reference to the Whitenoise state
+///
+/// # Returns
+///
+/// * `Ok(bool)` - Whether the mnemonic derives the account's keys
+/// * `Err(CommandError)` - An error message if the account couldn't be found
+#[tauri::command]
+pub async fn verify_identity_mnemonic(
+    pubkey: String,
+    mnemonic: String,
+    passphrase: Option<String>,
+    wn: tauri::State<'_, Whitenoise>,
+) -> Result<bool, CommandError> {
+    let account = find_account(&pubkey, &wn).await?;
+
+    Ok(account.verify_mnemonic(&mnemonic, passphrase.as_deref()))
+}
@@ -0,0 +1,20 @@
+use crate::api::ApiSettings;
+use crate::commands::CommandError;
+use crate::whitenoise::Whitenoise;
+
+/// Gets the settings of the local API server, including its token.
+///
+/// # Arguments
+///
+/// * `wn` - A reference to the Whitenoise state
+///
+/// # Returns
+///
+/// * `Ok(ApiSettings)` - The API settings, disabled if they were never set
+/// * `Err(CommandError)` - An error message if the settings couldn't be read
+#[tauri::command]
+pub async fn get_api_settings(
+    wn: tauri::State<'_, Whitenoise>,
+) -> Result<ApiSettings, CommandError> {
+    Ok(ApiSettings::load(&wn.data_dir)?)
+}
@@ -0,0 +1,5 @@
+mod get_api_settings;
+mod set_api_settings;
+
+pub use get_api_settings::get_api_settings;
+pub use set_api_settings::set_api_settings;
@@ -0,0 +1,33 @@
+use crate::api::{ApiServer, ApiSettings};
+use crate::commands::CommandError;
+use crate::whitenoise::Whitenoise;
+
+/// Saves the settings of the local API server and starts, restarts or stops it to match.
+///
+/// Enabling the API without a token generates one.
+///
+/// # Arguments
+///
+/// * `settings` - The new API settings
+/// * `wn` - A reference to the Whitenoise state
+/// * `api_server` - The API server
+///
+/// # Returns
+///
+/// * `Ok(ApiSettings)` - The saved settings, with the generated token if there is one
+/// * `Err(CommandError)` - An error message if the settings are invalid, couldn't be saved or the
+///   server couldn't be started, e.g. because the port is taken
+#[tauri::command]
+pub async fn set_api_settings(
+    mut settings: ApiSettings,
+    wn: tauri::State<'_, Whitenoise>,
+    api_server: tauri::State<'_, ApiServer>,
+) -> Result<ApiSettings, CommandError> {
+    if settings.enabled && settings.token.is_empty() {
+        settings.token = ApiSettings::generate_token();
+    }
+    settings.validate()?;
+    settings.save(&wn.data_dir)?;
+    api_server.apply(&settings, &wn).await?;
+    Ok(settings)
+}
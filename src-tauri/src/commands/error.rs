@@ -0,0 +1,393 @@
+//! The error every command returns.
+//!
+//! It serializes to `{ "code": ..., "message": ..., "pubkey": ..., "relays": [...] }`. The code is
+//! stable, so the frontend can tell errors apart without matching on messages, and the message is
+//! meant for the user. `pubkey` and `relays` are only set when the error is about a specific user
+//! or specific relays.
+
+use crate::accounts::AccountError;
+use crate::api::ApiError;
+use crate::backup::BackupError;
+use crate::database::DatabaseError;
+use crate::groups::GroupError;
+use crate::invites::InviteError;
+use crate::key_packages::KeyPackageError;
+use crate::messages::MessageError;
+use crate::nostr_manager::NostrManagerError;
+use crate::payments::PaymentError;
+use crate::secrets_store::SecretsStoreError;
+use nostr_sdk::prelude::DatabaseError as NostrDatabaseError;
+use serde::Serialize;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ErrorCode {
+    /// There is no active account.
+    NoActiveAccount,
+    /// The account, group, message or invite doesn't exist.
+    NotFound,
+    /// The account or other thing being created already exists.
+    AlreadyExists,
+    /// An argument is malformed or not allowed, e.g. a pubkey, a group id or a relay URL.
+    InvalidInput,
+    /// The password or passphrase is wrong.
+    WrongPassword,
+    /// The account signs with a remote signer, so its private key isn't on this device.
+    RemoteSigner,
+    /// Only admins, or the author of a message, can do this.
+    PermissionDenied,
+    /// The user in `pubkey` has no valid key package, so they can't be added to a group.
+    NoKeyPackage,
+    /// The welcome for the user in `pubkey` couldn't be sent to `relays`.
+    WelcomeNotSent,
+    /// Relays couldn't be reached or rejected an event. `relays` is set when it's known which.
+    RelayError,
+    /// Publishing is blocked because lockdown mode is on.
+    LockdownMode,
+    /// No Nostr Wallet Connect URI is set for the account.
+    NoWallet,
+    /// The lightning invoice is malformed or expired.
+    InvalidInvoice,
+    /// The wallet couldn't pay the invoice.
+    PaymentFailed,
+    /// The MLS group state couldn't be read or updated.
+    Mls,
+    /// The database, the secrets store or a file couldn't be read or written.
+    Storage,
+    /// Anything else.
+    Internal,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, thiserror::Error)]
+#[error("{message}")]
+pub struct CommandError {
+    pub code: ErrorCode,
+    pub message: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pubkey: Option<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub relays: Vec<String>,
+}
+
+pub type Result<T> = std::result::Result<T, CommandError>;
+
+impl CommandError {
+    pub fn new(code: ErrorCode, message: impl Into<String>) -> Self {
+        Self {
+            code,
+            message: message.into(),
+            pubkey: None,
+            relays: Vec::new(),
+        }
+    }
+
+    pub fn invalid_input(message: impl Into<String>) -> Self {
+        Self::new(ErrorCode::InvalidInput, message)
+    }
+
+    pub fn with_pubkey(mut self, pubkey: impl ToString) -> Self {
+        self.pubkey = Some(pubkey.to_string());
+        self
+    }
+
+    pub fn with_relays(mut self, relays: Vec<String>) -> Self {
+        self.relays = relays;
+        self
+    }
+}
+
+impl From<AccountError> for CommandError {
+    fn from(error: AccountError) -> Self {
+        let message = error.to_string();
+        match error {
+            AccountError::DatabaseError(e) => e.into(),
+            AccountError::SqlxError(e) => e.into(),
+            AccountError::NostrManagerError(e) => e.into(),
+            AccountError::SecretsStoreError(e) => e.into(),
+            AccountError::NoActiveAccount => Self::new(ErrorCode::NoActiveAccount, message),
+            AccountError::WrongPassword => Self::new(ErrorCode::WrongPassword, message),
+            AccountError::RemoteSignerAccount => Self::new(ErrorCode::RemoteSigner, message),
+            AccountError::PublicKeyError(_)
+            | AccountError::InvalidSettings(_)
+            | AccountError::InvalidProfile(_)
+            | AccountError::InvalidRelay(_)
+            | AccountError::InvalidPassword(_)
+            | AccountError::InvalidEncryptedKey(_)
+            | AccountError::InvalidMnemonic(_)
+            | AccountError::NotActiveAccount => Self::invalid_input(message),
+            AccountError::FileError(_) => Self::new(ErrorCode::Storage, message),
+            AccountError::SerializationError(_)
+            | AccountError::NostrEventError(_)
+            | AccountError::SignerError(_) => Self::new(ErrorCode::Internal, message),
+        }
+    }
+}
+
+impl From<GroupError> for CommandError {
+    fn from(error: GroupError) -> Self {
+        let message = error.to_string();
+        match error {
+            GroupError::KeyPackageError(e) => e.into(),
+            GroupError::AccountError(e) => e.into(),
+            GroupError::DatabaseError(e) => e.into(),
+            GroupError::NostrManagerError(e) => e.into(),
+            GroupError::SecretsStoreError(e) => e.into(),
+            GroupError::SqlxError(e) => e.into(),
+            GroupError::GroupNotFound | GroupError::MessageNotFound(_) => {
+                Self::new(ErrorCode::NotFound, message)
+            }
+            GroupError::PermissionDenied(_) => Self::new(ErrorCode::PermissionDenied, message),
+            GroupError::WelcomeNotSent { pubkey, relays, .. } => {
+                Self::new(ErrorCode::WelcomeNotSent, message)
+                    .with_pubkey(pubkey.to_hex())
+                    .with_relays(relays)
+            }
+            GroupError::InvalidParameters(_)
+            | GroupError::KeyError(_)
+            | GroupError::EventIdError(_) => Self::invalid_input(message),
+            GroupError::MlsError(_) => Self::new(ErrorCode::Mls, message),
+            GroupError::NostrError(_) => Self::new(ErrorCode::RelayError, message),
+            GroupError::NostrEventError(_)
+            | GroupError::NostrEncryptionError(_)
+            | GroupError::SerializationError(_)
+            | GroupError::SignerError(_) => Self::new(ErrorCode::Internal, message),
+        }
+    }
+}
+
+impl From<KeyPackageError> for CommandError {
+    fn from(error: KeyPackageError) -> Self {
+        let message = error.to_string();
+        match error {
+            KeyPackageError::AccountError(e) => e.into(),
+            KeyPackageError::NostrError(e) => e.into(),
+            KeyPackageError::NoValidKeyPackage { pubkey } => {
+                Self::new(ErrorCode::NoKeyPackage, message).with_pubkey(pubkey)
+            }
+            KeyPackageError::FetchingKeyPackage { pubkey, .. } => {
+                Self::new(ErrorCode::RelayError, message).with_pubkey(pubkey)
+            }
+            KeyPackageError::NostrClientError(_) => Self::new(ErrorCode::RelayError, message),
+            KeyPackageError::NostrMlsError(_) => Self::new(ErrorCode::Mls, message),
+            KeyPackageError::NostrSignerError(_) => Self::new(ErrorCode::Internal, message),
+        }
+    }
+}
+
+impl From<InviteError> for CommandError {
+    fn from(error: InviteError) -> Self {
+        let message = error.to_string();
+        match error {
+            InviteError::Database(e) => e.into(),
+            InviteError::Sqlx(e) => e.into(),
+            InviteError::Account(e) => e.into(),
+            InviteError::Group(e) => e.into(),
+            InviteError::NostrManager(e) => e.into(),
+            InviteError::Welcome(_) => Self::new(ErrorCode::Mls, message),
+            InviteError::Event(_) | InviteError::Json(_) => Self::new(ErrorCode::Internal, message),
+        }
+    }
+}
+
+impl From<PaymentError> for CommandError {
+    fn from(error: PaymentError) -> Self {
+        let message = error.to_string();
+        match error {
+            PaymentError::InvalidInvoice(_) | PaymentError::ExpiredInvoice => {
+                Self::new(ErrorCode::InvalidInvoice, message)
+            }
+            PaymentError::InvalidNwcUri(_) => Self::new(ErrorCode::NoWallet, message),
+            PaymentError::PaymentFailure(_) => Self::new(ErrorCode::PaymentFailed, message),
+        }
+    }
+}
+
+impl From<NostrManagerError> for CommandError {
+    fn from(error: NostrManagerError) -> Self {
+        let message = error.to_string();
+        match error {
+            NostrManagerError::Database(e) => e.into(),
+            NostrManagerError::Client(_) | NostrManagerError::SyncIncomplete(_) => {
+                Self::new(ErrorCode::RelayError, message)
+            }
+            NostrManagerError::DirectConnectionRefused(relay) => {
+                Self::new(ErrorCode::RelayError, message).with_relays(vec![relay])
+            }
+            NostrManagerError::LockdownMode(_) => Self::new(ErrorCode::LockdownMode, message),
+            NostrManagerError::RemoteSigner(_) => Self::new(ErrorCode::RemoteSigner, message),
+            NostrManagerError::Metadata(_)
+            | NostrManagerError::InvalidRelayUrl(_)
+            | NostrManagerError::Proxy(_)
+            | NostrManagerError::NetworkProfile(_)
+            | NostrManagerError::InvalidInput(_) => Self::invalid_input(message),
+            NostrManagerError::SecretsStoreError(_) | NostrManagerError::SyncCursor(_) => {
+                Self::new(ErrorCode::Storage, message)
+            }
+            NostrManagerError::Signer(_)
+            | NostrManagerError::FailedToQueueEvent(_)
+            | NostrManagerError::FailedToShutdownEventProcessor(_)
+            | NostrManagerError::AccountError(_) => Self::new(ErrorCode::Internal, message),
+        }
+    }
+}
+
+impl From<MessageError> for CommandError {
+    fn from(error: MessageError) -> Self {
+        match error {
+            MessageError::Sqlx(e) => e.into(),
+            MessageError::Account(e) => e.into(),
+            MessageError::Database(e) => e.into(),
+            MessageError::NotFound => Self::new(ErrorCode::NotFound, error.to_string()),
+        }
+    }
+}
+
+impl From<BackupError> for CommandError {
+    fn from(error: BackupError) -> Self {
+        let message = error.to_string();
+        match error {
+            BackupError::DatabaseError(e) => e.into(),
+            BackupError::AccountError(e) => e.into(),
+            BackupError::GroupError(e) => e.into(),
+            BackupError::KeyPackageError(e) => e.into(),
+            BackupError::NostrManagerError(e) => e.into(),
+            BackupError::SecretsStoreError(e) => e.into(),
+            BackupError::WrongPassphrase => Self::new(ErrorCode::WrongPassword, message),
+            BackupError::AccountExists => Self::new(ErrorCode::AlreadyExists, message),
+            BackupError::EmptyPassphrase
+            | BackupError::InvalidArchive(_)
+            | BackupError::UnsupportedVersion(_)
+            | BackupError::HexError(_)
+            | BackupError::Base64Error(_)
+            | BackupError::KeyError(_) => Self::invalid_input(message),
+            BackupError::FileError(_) => Self::new(ErrorCode::Storage, message),
+            BackupError::EncryptionError(_) | BackupError::JsonError(_) => {
+                Self::new(ErrorCode::Internal, message)
+            }
+        }
+    }
+}
+
+impl From<ApiError> for CommandError {
+    fn from(error: ApiError) -> Self {
+        match error {
+            ApiError::InvalidSettings(_) => Self::invalid_input(error.to_string()),
+            _ => Self::new(ErrorCode::Internal, error.to_string()),
+        }
+    }
+}
+
+impl From<DatabaseError> for CommandError {
+    fn from(error: DatabaseError) -> Self {
+        match error {
+            DatabaseError::Sqlx(e) => e.into(),
+            _ => Self::new(ErrorCode::Storage, error.to_string()),
+        }
+    }
+}
+
+impl From<sqlx::Error> for CommandError {
+    fn from(error: sqlx::Error) -> Self {
+        match error {
+            sqlx::Error::RowNotFound => Self::new(ErrorCode::NotFound, "Not found"),
+            _ => Self::new(ErrorCode::Storage, format!("Database error: {}", error)),
+        }
+    }
+}
+
+impl From<SecretsStoreError> for CommandError {
+    fn from(error: SecretsStoreError) -> Self {
+        match error {
+            SecretsStoreError::KeyNotFound => Self::new(ErrorCode::NotFound, error.to_string()),
+            _ => Self::new(
+                ErrorCode::Storage,
+                format!("Error with secrets store: {}", error),
+            ),
+        }
+    }
+}
+
+impl From<NostrDatabaseError> for CommandError {
+    fn from(error: NostrDatabaseError) -> Self {
+        Self::new(
+            ErrorCode::Storage,
+            format!("Event database error: {}", error),
+        )
+    }
+}
+
+impl From<std::io::Error> for CommandError {
+    fn from(error: std::io::Error) -> Self {
+        Self::new(ErrorCode::Storage, format!("File error: {}", error))
+    }
+}
+
+impl From<nostr_sdk::client::Error> for CommandError {
+    fn from(error: nostr_sdk::client::Error) -> Self {
+        Self::new(ErrorCode::RelayError, error.to_string())
+    }
+}
+
+impl From<nostr_sdk::SignerError> for CommandError {
+    fn from(error: nostr_sdk::SignerError) -> Self {
+        Self::new(ErrorCode::Internal, format!("Signer error: {}", error))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use nostr_sdk::prelude::*;
+
+    #[test]
+    fn test_serializes_code_and_fields() {
+        let pubkey = Keys::generate().public_key();
+        let error: CommandError = GroupError::WelcomeNotSent {
+            pubkey,
+            relays: vec!["wss://relay.example.com".to_string()],
+            attempts: 5,
+            reason: "timeout".to_string(),
+        }
+        .into();
+
+        let json = serde_json::to_value(&error).unwrap();
+        assert_eq!(json["code"], "welcome_not_sent");
+        assert_eq!(json["pubkey"], pubkey.to_hex());
+        assert_eq!(json["relays"][0], "wss://relay.example.com");
+        assert!(json["message"].as_str().unwrap().contains("timeout"));
+    }
+
+    #[test]
+    fn test_skips_empty_fields() {
+        let json = serde_json::to_value(CommandError::from(AccountError::NoActiveAccount)).unwrap();
+        assert_eq!(
+            json,
+            serde_json::json!({
+                "code": "no_active_account",
+                "message": "No active account found",
+            })
+        );
+    }
+
+    #[test]
+    fn test_nested_errors_keep_their_code() {
+        let missing_key_package = GroupError::KeyPackageError(KeyPackageError::NoValidKeyPackage {
+            pubkey: "abcd".to_string(),
+        });
+        let error = CommandError::from(missing_key_package);
+        assert_eq!(error.code, ErrorCode::NoKeyPackage);
+        assert_eq!(error.pubkey.as_deref(), Some("abcd"));
+
+        let no_account =
+            InviteError::Group(GroupError::AccountError(AccountError::NoActiveAccount));
+        assert_eq!(
+            CommandError::from(no_account).code,
+            ErrorCode::NoActiveAccount
+        );
+
+        assert_eq!(
+            CommandError::from(AccountError::SqlxError(sqlx::Error::RowNotFound)).code,
+            ErrorCode::NotFound
+        );
+    }
+}
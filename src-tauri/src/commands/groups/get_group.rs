@@ -1,6 +1,6 @@
+use crate::commands::{parse_group_id, CommandError};
 use crate::groups::{Group, GroupWithRelays};
 use crate::whitenoise::Whitenoise;
-use nostr_sdk::prelude::*;
 
 /// Gets a single MLS group by its group ID
 ///
@@ -10,7 +10,7 @@ use nostr_sdk::prelude::*;
 ///
 /// # Returns
 /// * `Ok(Group)` - The requested group if found
-/// * `Err(String)` - Error message if group not found or other error occurs
+/// * `Err(CommandError)` - Error message if group not found or other error occurs
 ///
 /// # Errors
 /// Returns error if:
@@ -21,13 +21,10 @@ use nostr_sdk::prelude::*;
 pub async fn get_group(
     group_id: &str,
     wn: tauri::State<'_, Whitenoise>,
-) -> Result<GroupWithRelays, String> {
-    let mls_group_id =
-        hex::decode(group_id).map_err(|e| format!("Error decoding group id: {}", e))?;
-    let group = Group::find_by_mls_group_id(&mls_group_id, wn.clone())
-        .await
-        .map_err(|e| format!("Error fetching group: {}", e))?;
-    let relays = group.relays(wn.clone()).await.map_err(|e| e.to_string())?;
+) -> Result<GroupWithRelays, CommandError> {
+    let mls_group_id = parse_group_id(group_id)?;
+    let group = Group::find_by_mls_group_id(&mls_group_id, &wn).await?;
+    let relays = group.relays(&wn).await?;
     tracing::debug!(
         target: "whitenoise::commands::groups::get_group",
         "Group Relays: {:?}",
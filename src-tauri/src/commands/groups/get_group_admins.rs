@@ -1,3 +1,4 @@
+use crate::commands::{parse_group_id, CommandError};
 use crate::groups::Group;
 use crate::whitenoise::Whitenoise;
 use nostr_sdk::prelude::*;
@@ -10,7 +11,7 @@ use nostr_sdk::prelude::*;
 ///
 /// # Returns
 /// * `Ok(Vec<String>)` - List of admin public keys if successful
-/// * `Err(String)` - Error message if operation fails
+/// * `Err(CommandError)` - Error message if operation fails
 ///
 /// # Errors
 /// * If no active account is found
@@ -21,12 +22,9 @@ use nostr_sdk::prelude::*;
 pub async fn get_group_admins(
     group_id: &str,
     wn: tauri::State<'_, Whitenoise>,
-) -> Result<Vec<PublicKey>, String> {
-    let mls_group_id =
-        hex::decode(group_id).map_err(|e| format!("Error decoding group id: {}", e))?;
-    let group = Group::find_by_mls_group_id(&mls_group_id, wn.clone())
-        .await
-        .map_err(|e| format!("Error fetching group: {}", e))?;
-    let admins = group.admins().map_err(|e| e.to_string())?;
+) -> Result<Vec<PublicKey>, CommandError> {
+    let mls_group_id = parse_group_id(group_id)?;
+    let group = Group::find_by_mls_group_id(&mls_group_id, &wn).await?;
+    let admins = group.admins()?;
     Ok(admins)
 }
@@ -1,3 +1,4 @@
+use crate::commands::{parse_group_id, CommandError};
 use crate::groups::Group;
 use crate::whitenoise::Whitenoise;
 use nostr_sdk::prelude::*;
@@ -12,7 +13,7 @@ use nostr_sdk::prelude::*;
 /// * `Ok((Group, Vec<UnsignedEvent>))` - Tuple containing:
 ///   - The requested group if found
 ///   - Vector of unsigned message events for the group
-/// * `Err(String)` - Error message if operation fails
+/// * `Err(CommandError)` - Error message if operation fails
 ///
 /// # Errors
 /// Returns error if:
@@ -24,26 +25,20 @@ use nostr_sdk::prelude::*;
 pub async fn get_group_and_messages(
     group_id: &str,
     wn: tauri::State<'_, Whitenoise>,
-) -> Result<(Group, Vec<UnsignedEvent>), String> {
-    let mls_group_id =
-        hex::decode(group_id).map_err(|e| format!("Error decoding group id: {}", e))?;
+) -> Result<(Group, Vec<UnsignedEvent>), CommandError> {
+    let mls_group_id = parse_group_id(group_id)?;
     tracing::debug!(
         target: "whitenoise::commands::groups::get_group_and_messages",
         "Getting group and messages for group ID: {:?}",
         mls_group_id
     );
-    let group = Group::find_by_mls_group_id(&mls_group_id, wn.clone())
-        .await
-        .map_err(|e| format!("Error fetching group: {}", e))?;
+    let group = Group::find_by_mls_group_id(&mls_group_id, &wn).await?;
     tracing::debug!(
         target: "whitenoise::commands::groups::get_group_and_messages",
         "Group: {:?}",
         group
     );
-    let messages = group
-        .messages(wn.clone())
-        .await
-        .map_err(|e| format!("Error fetching messages: {}", e))?;
+    let messages = group.messages(&wn).await?;
     tracing::debug!(
         target: "whitenoise::commands::groups::get_group_and_messages",
         "Messages: {:?}",
@@ -1,3 +1,4 @@
+use crate::commands::CommandError;
 use crate::groups::Group;
 use crate::whitenoise::Whitenoise;
 
@@ -9,15 +10,13 @@ use crate::whitenoise::Whitenoise;
 ///
 /// # Returns
 /// * `Ok(Vec<Group>)` - List of groups the active account belongs to
-/// * `Err(String)` - Error message if retrieval fails
+/// * `Err(CommandError)` - Error message if retrieval fails
 ///
 /// # Errors
 /// Returns error if:
 /// - No active account found
 /// - Database error occurs retrieving groups
 #[tauri::command]
-pub async fn get_groups(wn: tauri::State<'_, Whitenoise>) -> Result<Vec<Group>, String> {
-    Group::get_all_groups(wn.clone())
-        .await
-        .map_err(|e| format!("Error fetching groups for account: {}", e))
+pub async fn get_groups(wn: tauri::State<'_, Whitenoise>) -> Result<Vec<Group>, CommandError> {
+    Ok(Group::get_all_groups(&wn).await?)
 }
@@ -1,3 +1,4 @@
+use crate::commands::CommandError;
 use crate::invites::{Invite, ProcessedInvite};
 use crate::whitenoise::Whitenoise;
 use nostr_sdk::prelude::*;
@@ -11,14 +12,12 @@ pub struct InvitesWithFailures {
 
 /// Fetches invites from the database for the active user
 #[tauri::command]
-pub async fn get_invites(wn: tauri::State<'_, Whitenoise>) -> Result<InvitesWithFailures, String> {
-    let pending_invites = Invite::pending(wn.clone())
-        .await
-        .map_err(|e| e.to_string())?;
+pub async fn get_invites(
+    wn: tauri::State<'_, Whitenoise>,
+) -> Result<InvitesWithFailures, CommandError> {
+    let pending_invites = Invite::pending(&wn).await?;
 
-    let failed_invites: Vec<(EventId, String)> = ProcessedInvite::failed_with_reason(wn.clone())
-        .await
-        .map_err(|e| e.to_string())?;
+    let failed_invites: Vec<(EventId, String)> = ProcessedInvite::failed_with_reason(&wn).await?;
 
     Ok(InvitesWithFailures {
         invites: pending_invites,
@@ -1,3 +1,4 @@
+use crate::commands::CommandError;
 use crate::key_packages::publish_key_package;
 use crate::Whitenoise;
 
@@ -11,7 +12,7 @@ use crate::Whitenoise;
 ///
 /// # Returns
 /// * `Ok(())` - Key package was successfully published
-/// * `Err(String)` - Error message if publishing fails
+/// * `Err(CommandError)` - Error message if publishing fails
 ///
 /// # Flow
 /// 1. Gets active account's public key
@@ -27,8 +28,6 @@ use crate::Whitenoise;
 /// - Key package creation fails
 /// - Event publishing fails
 #[tauri::command]
-pub async fn publish_new_key_package(wn: tauri::State<'_, Whitenoise>) -> Result<(), String> {
-    publish_key_package(wn.clone())
-        .await
-        .map_err(|e| e.to_string())
+pub async fn publish_new_key_package(wn: tauri::State<'_, Whitenoise>) -> Result<(), CommandError> {
+    Ok(publish_key_package(&wn).await?)
 }
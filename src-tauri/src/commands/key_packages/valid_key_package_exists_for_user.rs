@@ -1,3 +1,4 @@
+use crate::commands::{parse_pubkey, CommandError};
 use crate::key_packages::fetch_key_package_for_pubkey;
 use crate::Whitenoise;
 
@@ -9,7 +10,7 @@ use crate::Whitenoise;
 ///
 /// # Returns
 /// * `Ok(bool)` - True if valid key package exists, false otherwise
-/// * `Err(String)` - Error message if check fails
+/// * `Err(CommandError)` - Error message if check fails
 ///
 /// # Errors
 /// Returns error if:
@@ -20,9 +21,8 @@ use crate::Whitenoise;
 pub async fn valid_key_package_exists_for_user(
     pubkey: String,
     wn: tauri::State<'_, Whitenoise>,
-) -> Result<bool, String> {
-    let key_package = fetch_key_package_for_pubkey(pubkey, wn.clone())
-        .await
-        .map_err(|e| e.to_string())?;
+) -> Result<bool, CommandError> {
+    let pubkey = parse_pubkey(&pubkey)?.to_hex();
+    let key_package = fetch_key_package_for_pubkey(pubkey, &wn).await?;
     Ok(key_package.is_some())
 }
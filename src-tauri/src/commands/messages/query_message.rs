@@ -1,3 +1,4 @@
+use crate::commands::CommandError;
 use crate::messages::Message;
 use crate::whitenoise::Whitenoise;
 use nostr_sdk::prelude::*;
@@ -6,13 +7,10 @@ use nostr_sdk::prelude::*;
 pub async fn query_message(
     message_id: &str,
     wn: tauri::State<'_, Whitenoise>,
-) -> Result<UnsignedEvent, String> {
-    let message = Message::find_by_event_id(
-        EventId::parse(message_id).map_err(|e| e.to_string())?,
-        wn.clone(),
-    )
-    .await
-    .map_err(|e| format!("Error fetching message: {}", e))?;
+) -> Result<UnsignedEvent, CommandError> {
+    let event_id = EventId::parse(message_id)
+        .map_err(|e| CommandError::invalid_input(format!("Invalid message id: {}", e)))?;
+    let message = Message::find_by_event_id(event_id, &wn).await?;
 
     Ok(message.event)
 }
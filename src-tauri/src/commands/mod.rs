@@ -1,6 +1,10 @@
+use crate::accounts::Account;
 use crate::whitenoise::Whitenoise;
+use nostr_sdk::prelude::*;
 
 pub mod accounts;
+pub mod api;
+pub mod error;
 pub mod groups;
 pub mod invites;
 pub mod key_packages;
@@ -8,8 +12,37 @@ pub mod messages;
 pub mod nostr;
 pub mod payments;
 
+pub use error::{CommandError, ErrorCode};
+
 #[tauri::command]
-pub async fn delete_all_data(wn: tauri::State<'_, Whitenoise>) -> Result<(), String> {
-    wn.delete_all_data().await.map_err(|e| e.to_string())?;
-    Ok(())
+pub async fn delete_all_data(wn: tauri::State<'_, Whitenoise>) -> Result<(), CommandError> {
+    wn.delete_all_data()
+        .await
+        .map_err(|e| CommandError::new(ErrorCode::Storage, e.to_string()))
+}
+
+/// Parses a public key passed to a command, in hex or bech32.
+pub(crate) fn parse_pubkey(pubkey: &str) -> error::Result<PublicKey> {
+    PublicKey::parse(pubkey).map_err(|e| {
+        CommandError::invalid_input(format!("Invalid pubkey: {}", e)).with_pubkey(pubkey)
+    })
+}
+
+/// Parses a hex encoded MLS group id passed to a command.
+pub(crate) fn parse_group_id(group_id: &str) -> error::Result<Vec<u8>> {
+    hex::decode(group_id)
+        .map_err(|e| CommandError::invalid_input(format!("Invalid group id: {}", e)))
+}
+
+/// Looks up the account of a public key passed to a command.
+pub(crate) async fn find_account(pubkey: &str, wn: &Whitenoise) -> error::Result<Account> {
+    let public_key = parse_pubkey(pubkey)?;
+    Account::find_by_pubkey(&public_key, wn)
+        .await
+        .map_err(|e| match CommandError::from(e) {
+            error if error.code == ErrorCode::NotFound => {
+                CommandError::new(ErrorCode::NotFound, "Account not found").with_pubkey(pubkey)
+            }
+            error => error,
+        })
 }
@@ -1,6 +1,6 @@
+use crate::commands::CommandError;
 use crate::types::NostrEncryptionMethod;
 use crate::whitenoise::Whitenoise;
-use nostr_sdk::prelude::*;
 
 #[tauri::command]
 pub async fn encrypt_content(
@@ -8,9 +8,6 @@ pub async fn encrypt_content(
     pubkey: String,
     method: NostrEncryptionMethod,
     wn: tauri::State<'_, Whitenoise>,
-) -> Result<String, String> {
-    wn.nostr
-        .encrypt_content(content, pubkey, method)
-        .await
-        .map_err(|e| e.to_string())
+) -> Result<String, CommandError> {
+    Ok(wn.nostr.encrypt_content(content, pubkey, method).await?)
 }
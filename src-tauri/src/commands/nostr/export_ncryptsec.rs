@@ -0,0 +1,29 @@
+use crate::accounts::NCRYPTSEC_MIN_LOG_N;
+use crate::commands::{find_account, CommandError};
+use crate::whitenoise::Whitenoise;
+
+/// Exports an account's private key encrypted with a password (NIP-49 `ncryptsec`).
+///
+/// # Arguments
+///
+/// * `pubkey` - The public key of the account to export
+/// * `password` - The password to encrypt the private key with
+/// * `log_n` - The scrypt work factor, between 16 and 22. Defaults to 16.
+///   Each step doubles the time and memory needed to decrypt the key.
+/// * `wn` - A reference to the Whitenoise state
+///
+/// # Returns
+///
+/// * `Ok(String)` - The `ncryptsec` string
+/// * `Err(CommandError)` - An error message if the account has no local private key or encryption fails
+#[tauri::command]
+pub async fn export_ncryptsec(
+    pubkey: String,
+    password: String,
+    log_n: Option<u8>,
+    wn: tauri::State<'_, Whitenoise>,
+) -> Result<String, CommandError> {
+    let account = find_account(&pubkey, &wn).await?;
+
+    Ok(account.export_ncryptsec(&password, log_n.unwrap_or(NCRYPTSEC_MIN_LOG_N), &wn)?)
+}
@@ -1,3 +1,4 @@
+use crate::commands::{CommandError, ErrorCode};
 use crate::secrets_store;
 use crate::whitenoise::Whitenoise;
 use nostr_sdk::prelude::*;
@@ -6,9 +7,18 @@ use nostr_sdk::prelude::*;
 pub async fn export_nsec(
     pubkey: String,
     wn: tauri::State<'_, Whitenoise>,
-) -> Result<String, String> {
-    let keys = secrets_store::get_nostr_keys_for_pubkey(&pubkey, &wn.data_dir)
-        .map_err(|e| e.to_string())?;
+) -> Result<String, CommandError> {
+    if secrets_store::get_remote_signer(&pubkey, &wn.data_dir)?.is_some() {
+        return Err(CommandError::new(
+            ErrorCode::RemoteSigner,
+            "This account uses a remote signer, its private key is not stored on this device",
+        )
+        .with_pubkey(&pubkey));
+    }
 
-    keys.secret_key().to_bech32().map_err(|e| e.to_string())
+    let keys = secrets_store::get_nostr_keys_for_pubkey(&pubkey, &wn.data_dir)?;
+
+    keys.secret_key()
+        .to_bech32()
+        .map_err(|e| CommandError::new(ErrorCode::Internal, e.to_string()))
 }
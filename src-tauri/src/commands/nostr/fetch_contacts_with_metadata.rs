@@ -1,3 +1,4 @@
+use crate::commands::CommandError;
 use crate::whitenoise::Whitenoise;
 use nostr_sdk::prelude::*;
 use std::collections::HashMap;
@@ -5,13 +6,16 @@ use std::collections::HashMap;
 #[tauri::command]
 pub async fn fetch_contacts_with_metadata(
     wn: tauri::State<'_, Whitenoise>,
-) -> Result<HashMap<String, Metadata>, String> {
-    let events = wn.nostr.fetch_contacts().await.map_err(|e| e.to_string())?;
+) -> Result<HashMap<String, Metadata>, CommandError> {
+    let events = wn.nostr.fetch_contacts().await?;
     let mut metadata_map = HashMap::new();
 
     for event in events {
         if let Ok(metadata) = serde_json::from_str::<Metadata>(&event.content) {
-            metadata_map.insert(event.pubkey.to_hex(), metadata);
+            metadata_map.insert(
+                event.pubkey.to_hex(),
+                wn.nostr.sanitize_contact_metadata(metadata),
+            );
         }
     }
 
@@ -1,3 +1,4 @@
+use crate::commands::CommandError;
 use crate::types::EnrichedContact;
 use crate::whitenoise::Whitenoise;
 use nostr_sdk::prelude::*;
@@ -6,14 +7,13 @@ use std::collections::HashMap;
 #[tauri::command]
 pub async fn fetch_enriched_contacts(
     wn: tauri::State<'_, Whitenoise>,
-) -> Result<HashMap<String, EnrichedContact>, String> {
+) -> Result<HashMap<String, EnrichedContact>, CommandError> {
     // Fetch contact list public keys
     let contact_list_pubkeys = wn
         .nostr
-        .client
-        .get_contact_list_public_keys(wn.nostr.timeout().await.unwrap())
-        .await
-        .expect("Failed to fetch contact list public keys");
+        .client()
+        .get_contact_list_public_keys(wn.nostr.timeout().await?)
+        .await?;
 
     tracing::debug!(
         "fetch_enriched_contacts contact_list_pubkeys length: {:?}",
@@ -45,27 +45,35 @@ pub async fn fetch_enriched_contacts(
         );
     }
 
+    let kinds = vec![
+        Kind::Metadata,
+        Kind::RelayList,
+        Kind::InboxRelays,
+        Kind::MlsKeyPackage,
+        Kind::MlsKeyPackageRelays,
+    ];
     let filter = Filter::new()
-        .kinds(vec![
-            Kind::Metadata,
-            Kind::RelayList,
-            Kind::InboxRelays,
-            Kind::MlsKeyPackage,
-            Kind::MlsKeyPackageRelays,
-        ])
+        .kinds(kinds.clone())
         .authors(contact_list_pubkeys.clone());
 
-    // Fetch all events in parallel using a single request
-    let (stored_events, fetched_events) = tokio::join!(
-        wn.nostr.client.database().query(vec![filter.clone()]),
+    // In lockdown mode we only use what we already have stored locally
+    let all_events = if wn.nostr.lockdown_mode() {
         wn.nostr
-            .client
-            .fetch_events(vec![filter.clone()], wn.nostr.timeout().await.unwrap())
-    );
+            .client()
+            .database()
+            .query(vec![filter.clone()])
+            .await?
+    } else {
+        // Fetch all events in parallel from each contact's own relays
+        let client = wn.nostr.client();
+        let (stored_events, fetched_events) = tokio::join!(
+            client.database().query(vec![filter.clone()]),
+            wn.nostr
+                .fetch_events_from_outboxes(&contact_list_pubkeys, kinds)
+        );
 
-    let all_events = stored_events
-        .expect("Failed to query stored events")
-        .merge(fetched_events.expect("Failed to fetch events"));
+        stored_events?.merge(fetched_events?)
+    };
 
     // Process all events
     for event in all_events {
@@ -74,7 +82,7 @@ pub async fn fetch_enriched_contacts(
             match event.kind {
                 Kind::Metadata => {
                     if let Ok(metadata) = Metadata::from_json(&event.content) {
-                        contact.metadata = metadata;
+                        contact.metadata = wn.nostr.sanitize_contact_metadata(metadata);
                     }
                 }
                 Kind::RelayList => {
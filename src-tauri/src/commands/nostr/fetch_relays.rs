@@ -1,3 +1,4 @@
+use crate::commands::CommandError;
 use crate::whitenoise::Whitenoise;
 use nostr_sdk::prelude::*;
 use std::collections::HashMap;
@@ -5,10 +6,10 @@ use std::collections::HashMap;
 #[tauri::command]
 pub async fn fetch_relays(
     wn: tauri::State<'_, Whitenoise>,
-) -> Result<HashMap<String, String>, String> {
+) -> Result<HashMap<String, String>, CommandError> {
     Ok(wn
         .nostr
-        .client
+        .client()
         .relays()
         .await
         .into_iter()
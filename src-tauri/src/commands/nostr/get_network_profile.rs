@@ -0,0 +1,19 @@
+use crate::commands::CommandError;
+use crate::nostr_manager::network::NetworkProfile;
+use crate::whitenoise::Whitenoise;
+
+/// Gets the network profile: the default relays, the timeout and any fixed key package and welcome relays.
+///
+/// # Arguments
+///
+/// * `wn` - A reference to the Whitenoise state
+///
+/// # Returns
+///
+/// * `Ok(NetworkProfile)` - The network profile in use
+#[tauri::command]
+pub async fn get_network_profile(
+    wn: tauri::State<'_, Whitenoise>,
+) -> Result<NetworkProfile, CommandError> {
+    Ok(wn.nostr.network_profile().await)
+}
@@ -0,0 +1,19 @@
+use crate::commands::CommandError;
+use crate::nostr_manager::proxy::ProxySettings;
+use crate::whitenoise::Whitenoise;
+
+/// Gets the proxy settings used for relay connections.
+///
+/// # Arguments
+///
+/// * `wn` - A reference to the Whitenoise state
+///
+/// # Returns
+///
+/// * `Ok(ProxySettings)` - The proxy settings
+#[tauri::command]
+pub async fn get_proxy_settings(
+    wn: tauri::State<'_, Whitenoise>,
+) -> Result<ProxySettings, CommandError> {
+    Ok(wn.nostr.settings.lock().await.proxy.clone())
+}
@@ -0,0 +1,22 @@
+use crate::accounts::Account;
+use crate::commands::CommandError;
+use crate::nostr_manager::auth::RelayAuthStatus;
+use crate::whitenoise::Whitenoise;
+
+/// Returns the NIP-42 authentication state of the active account on each relay that asked it to authenticate.
+///
+/// # Arguments
+///
+/// * `wn` - A reference to the Whitenoise state
+///
+/// # Returns
+///
+/// * `Ok(Vec<RelayAuthStatus>)` - Whether we refused, are authenticating, authenticated or failed, per relay
+/// * `Err(CommandError)` - An error message if there is no active account
+#[tauri::command]
+pub async fn get_relay_auth_status(
+    wn: tauri::State<'_, Whitenoise>,
+) -> Result<Vec<RelayAuthStatus>, CommandError> {
+    let pubkey = Account::get_active_pubkey(&wn).await?;
+    Ok(wn.nostr.relay_auth_status(&pubkey))
+}
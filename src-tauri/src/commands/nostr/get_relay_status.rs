@@ -0,0 +1,23 @@
+use crate::commands::CommandError;
+use crate::nostr_manager::relay_status::RelayHealth;
+use crate::whitenoise::Whitenoise;
+
+/// Returns the health of every relay in the relay pools.
+///
+/// # Arguments
+///
+/// * `wn` - A reference to the Whitenoise state
+///
+/// # Returns
+///
+/// * `Ok(Vec<RelayHealth>)` - Status, connect and disconnect counts, latency, the last error,
+///   the NIP-11 document and whether the relay is flapping, for each relay
+///
+/// # Events Emitted
+/// * `relay_status_changed` - Emitted separately with a `RelayHealth` whenever a relay's health changes
+#[tauri::command]
+pub async fn get_relay_status(
+    wn: tauri::State<'_, Whitenoise>,
+) -> Result<Vec<RelayHealth>, CommandError> {
+    Ok(wn.nostr.relay_status())
+}
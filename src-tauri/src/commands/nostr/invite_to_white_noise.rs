@@ -1,3 +1,5 @@
+use crate::commands::{parse_pubkey, CommandError};
+use crate::nostr_manager::PublishTarget;
 use crate::types::NostrEncryptionMethod;
 use crate::whitenoise::Whitenoise;
 use nostr_sdk::prelude::*;
@@ -6,14 +8,14 @@ use nostr_sdk::prelude::*;
 pub async fn invite_to_white_noise(
     pubkey: String,
     wn: tauri::State<'_, Whitenoise>,
-) -> Result<(), String> {
-    let public_key = PublicKey::from_hex(&pubkey).map_err(|e| e.to_string())?;
+) -> Result<(), CommandError> {
+    let public_key = parse_pubkey(&pubkey)?;
+    wn.nostr.check_publish_allowed(PublishTarget::Outbox)?;
     let content = "Hi, I'm using White Noise to chat securely on Nostr. Join me! https://github.com/erskingardner/whitenoise/releases".to_string();
     let encrypted_content = wn
         .nostr
         .encrypt_content(content, pubkey, NostrEncryptionMethod::Nip04)
-        .await
-        .map_err(|e| e.to_string())?;
+        .await?;
 
     let event = EventBuilder::new(Kind::EncryptedDirectMessage, encrypted_content)
         .tag(Tag::public_key(public_key));
@@ -23,11 +25,7 @@ pub async fn invite_to_white_noise(
         "Sending event: {:?}",
         event
     );
-    wn.nostr
-        .client
-        .send_event_builder(event)
-        .await
-        .map_err(|e| e.to_string())?;
+    wn.nostr.client().send_event_builder(event).await?;
 
     Ok(())
 }
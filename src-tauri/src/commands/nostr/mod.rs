@@ -1,10 +1,15 @@
 mod decrypt_content;
 mod encrypt_content;
+mod export_ncryptsec;
 mod export_nsec;
 mod fetch_contacts_with_metadata;
 mod fetch_enriched_contact;
 mod fetch_enriched_contacts;
 mod fetch_relays;
+mod get_network_profile;
+mod get_proxy_settings;
+mod get_relay_auth_status;
+mod get_relay_status;
 mod init_nostr_for_current_user;
 mod invite_to_white_noise;
 mod publish_relay_list;
@@ -12,14 +17,21 @@ mod query_contacts_with_metadata;
 mod query_enriched_contact;
 mod query_enriched_contacts;
 mod search_for_enriched_contacts;
+mod set_network_profile;
+mod set_proxy_settings;
 
 pub use decrypt_content::decrypt_content;
 pub use encrypt_content::encrypt_content;
+pub use export_ncryptsec::export_ncryptsec;
 pub use export_nsec::export_nsec;
 pub use fetch_contacts_with_metadata::fetch_contacts_with_metadata;
 pub use fetch_enriched_contact::fetch_enriched_contact;
 pub use fetch_enriched_contacts::fetch_enriched_contacts;
 pub use fetch_relays::fetch_relays;
+pub use get_network_profile::get_network_profile;
+pub use get_proxy_settings::get_proxy_settings;
+pub use get_relay_auth_status::get_relay_auth_status;
+pub use get_relay_status::get_relay_status;
 pub use init_nostr_for_current_user::init_nostr_for_current_user;
 pub use invite_to_white_noise::invite_to_white_noise;
 pub use publish_relay_list::publish_relay_list;
@@ -27,3 +39,5 @@ pub use query_contacts_with_metadata::query_contacts_with_metadata;
 pub use query_enriched_contact::query_enriched_contact;
 pub use query_enriched_contacts::query_enriched_contacts;
 pub use search_for_enriched_contacts::search_for_enriched_contacts;
+pub use set_network_profile::set_network_profile;
+pub use set_proxy_settings::set_proxy_settings;
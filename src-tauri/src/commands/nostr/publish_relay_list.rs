@@ -1,4 +1,5 @@
 use crate::accounts::Account;
+use crate::commands::CommandError;
 use crate::relays::RelayType;
 use crate::whitenoise::Whitenoise;
 use nostr_sdk::prelude::*;
@@ -8,8 +9,8 @@ pub async fn publish_relay_list(
     relays: Vec<String>,
     kind: u64,
     wn: tauri::State<'_, Whitenoise>,
-) -> Result<(), String> {
-    let signer = wn.nostr.client.signer().await.map_err(|e| e.to_string())?;
+) -> Result<(), CommandError> {
+    let signer = wn.nostr.client().signer().await?;
 
     let mut tags: Vec<Tag> = Vec::new();
     for relay in relays.clone() {
@@ -19,39 +20,30 @@ pub async fn publish_relay_list(
     let event_kind = match kind {
         10050 => Kind::InboxRelays,
         10051 => Kind::MlsKeyPackageRelays,
-        _ => return Err("Invalid relay list kind".to_string()),
+        _ => return Err(CommandError::invalid_input("Invalid relay list kind")),
     };
 
     let event = EventBuilder::new(event_kind, "")
         .tags(tags)
         .sign(&signer)
-        .await
-        .map_err(|e| e.to_string())?;
+        .await?;
 
-    wn.nostr
-        .client
-        .send_event(event)
-        .await
-        .map_err(|e| e.to_string())?;
+    wn.nostr.publish_event(event).await?;
 
-    let active_account = Account::get_active(wn.clone())
-        .await
-        .map_err(|e| e.to_string())?;
+    let active_account = Account::get_active(&wn).await?;
 
     match kind {
         10050 => {
             active_account
-                .update_relays(RelayType::Inbox, &relays, wn.clone())
-                .await
-                .map_err(|e| format!("Failed to update relays: {}", e))?;
+                .update_relays(RelayType::Inbox, &relays, &wn)
+                .await?;
         }
         10051 => {
             active_account
-                .update_relays(RelayType::KeyPackage, &relays, wn.clone())
-                .await
-                .map_err(|e| format!("Failed to update relays: {}", e))?;
+                .update_relays(RelayType::KeyPackage, &relays, &wn)
+                .await?;
         }
-        _ => return Err("Invalid relay list kind".to_string()),
+        _ => return Err(CommandError::invalid_input("Invalid relay list kind")),
     }
     Ok(())
 }
@@ -1,3 +1,4 @@
+use crate::commands::CommandError;
 use crate::types::EnrichedContact;
 use crate::whitenoise::Whitenoise;
 use nostr_sdk::prelude::*;
@@ -6,13 +7,9 @@ use std::collections::HashMap;
 #[tauri::command]
 pub async fn query_enriched_contacts(
     wn: tauri::State<'_, Whitenoise>,
-) -> Result<HashMap<String, EnrichedContact>, String> {
+) -> Result<HashMap<String, EnrichedContact>, CommandError> {
     // Query contact list public keys from local database
-    let contact_list_pubkeys = wn
-        .nostr
-        .query_contact_list_pubkeys()
-        .await
-        .map_err(|e| e.to_string())?;
+    let contact_list_pubkeys = wn.nostr.query_contact_list_pubkeys().await?;
 
     tracing::debug!(
         "query_enriched_contacts contact_list_pubkeys length: {:?}",
@@ -53,11 +50,10 @@ pub async fn query_enriched_contacts(
 
     let stored_events = wn
         .nostr
-        .client
+        .client()
         .database()
         .query(vec![filter.clone()])
-        .await
-        .map_err(|e| e.to_string())?;
+        .await?;
 
     // Process all events
     for event in stored_events {
@@ -66,7 +62,7 @@ pub async fn query_enriched_contacts(
             match event.kind {
                 Kind::Metadata => {
                     if let Ok(metadata) = Metadata::from_json(&event.content) {
-                        contact.metadata = metadata;
+                        contact.metadata = wn.nostr.sanitize_contact_metadata(metadata);
                     }
                 }
                 Kind::RelayList => {
@@ -1,3 +1,4 @@
+use crate::commands::CommandError;
 use crate::types::EnrichedContact;
 use crate::whitenoise::Whitenoise;
 use nostr_sdk::prelude::*;
@@ -7,12 +8,8 @@ use std::collections::HashMap;
 pub async fn search_for_enriched_contacts(
     query: String,
     wn: tauri::State<'_, Whitenoise>,
-) -> Result<HashMap<String, EnrichedContact>, String> {
-    let enriched_users = wn
-        .nostr
-        .search_users(query, wn.clone())
-        .await
-        .map_err(|e| e.to_string())?;
+) -> Result<HashMap<String, EnrichedContact>, CommandError> {
+    let enriched_users = wn.nostr.search_users(query, &wn).await?;
 
     Ok(enriched_users)
 }
@@ -0,0 +1,28 @@
+use crate::commands::CommandError;
+use crate::nostr_manager::network::NetworkProfile;
+use crate::whitenoise::Whitenoise;
+
+/// Switches to and saves a new network profile.
+///
+/// The profile is applied to the default client and the running account sessions right away.
+/// Once applied it's saved to the data directory and used again on the next start, unless
+/// `WHITENOISE_NETWORK_PROFILE` selects another one.
+///
+/// # Arguments
+///
+/// * `profile` - The new network profile
+/// * `wn` - A reference to the Whitenoise state
+///
+/// # Returns
+///
+/// * `Ok(())` - If the profile was applied and saved
+/// * `Err(CommandError)` - An error message if the profile is invalid or couldn't be applied or saved
+#[tauri::command]
+pub async fn set_network_profile(
+    profile: NetworkProfile,
+    wn: tauri::State<'_, Whitenoise>,
+) -> Result<(), CommandError> {
+    profile.validate()?;
+    wn.nostr.set_network_profile(profile.clone(), &wn).await?;
+    Ok(profile.save(&wn.data_dir)?)
+}
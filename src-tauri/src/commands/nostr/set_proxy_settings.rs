@@ -0,0 +1,27 @@
+use crate::commands::CommandError;
+use crate::nostr_manager::proxy::ProxySettings;
+use crate::whitenoise::Whitenoise;
+
+/// Updates and saves the proxy settings used for relay connections.
+///
+/// The relays of the default client and of every account session are reconnected with the new
+/// settings. Relays that would now be connected to directly while that is refused are dropped.
+///
+/// # Arguments
+///
+/// * `settings` - The new proxy settings
+/// * `wn` - A reference to the Whitenoise state
+///
+/// # Returns
+///
+/// * `Ok(())` - If the settings were applied and saved
+/// * `Err(CommandError)` - An error message if the settings are invalid or couldn't be saved
+#[tauri::command]
+pub async fn set_proxy_settings(
+    settings: ProxySettings,
+    wn: tauri::State<'_, Whitenoise>,
+) -> Result<(), CommandError> {
+    wn.nostr.set_proxy_settings(settings.clone()).await?;
+    settings.save(&wn.data_dir)?;
+    Ok(())
+}
@@ -1,28 +1,9 @@
 use crate::accounts::Account;
-use crate::commands::groups::send_mls_message;
+use crate::commands::{CommandError, ErrorCode};
 use crate::groups::Group;
 use crate::payments::{self, PaymentError};
 use crate::whitenoise::Whitenoise;
 use nostr_sdk::prelude::*;
-use serde::Serialize;
-
-#[derive(Debug, thiserror::Error, Serialize)]
-pub enum CommandError {
-    #[error("Account error: No active account found")]
-    NoActiveAccount,
-    #[error("Account error: No NWC URI configured")]
-    NoNWCUri,
-    #[error("Message error: Can not build message")]
-    MessageError,
-    #[error("Payment error: {0}")]
-    PaymentError(String),
-}
-
-impl From<PaymentError> for CommandError {
-    fn from(err: PaymentError) -> Self {
-        CommandError::PaymentError(err.to_string())
-    }
-}
 
 #[tauri::command]
 pub async fn pay_invoice(
@@ -30,32 +11,27 @@ pub async fn pay_invoice(
     tags: Option<Vec<Tag>>,
     bolt11: String,
     wn: tauri::State<'_, Whitenoise>,
-    app_handle: tauri::AppHandle,
 ) -> Result<UnsignedEvent, CommandError> {
-    let active_account = Account::get_active(wn.clone())
-        .await
-        .map_err(|_| CommandError::NoActiveAccount)?;
+    let active_account = Account::get_active(&wn).await?;
 
     let nwc_uri = active_account
-        .get_nostr_wallet_connect_uri(wn.clone())
-        .map_err(|_| CommandError::NoNWCUri)?
-        .ok_or(CommandError::NoNWCUri)?;
-
-    let payment_service = DefaultPaymentService;
-    let message_params = pay_invoice_and_get_msg_params(&payment_service, tags, &bolt11, &nwc_uri)
-        .await
-        .map_err(|_| CommandError::MessageError)?;
-
-    let unsigned_message = send_mls_message(
-        group,
-        message_params.message,
-        message_params.kind,
-        message_params.tags,
-        wn,
-        app_handle,
-    )
-    .await
-    .map_err(|_| CommandError::MessageError)?;
+        .get_nostr_wallet_connect_uri(&wn)?
+        .ok_or_else(|| CommandError::new(ErrorCode::NoWallet, "No NWC URI configured"))?;
+
+    let payment_service = DefaultPaymentService {
+        connection_mode: wn.nostr.connection_mode_for_uri(&nwc_uri).await?,
+    };
+    let message_params =
+        pay_invoice_and_get_msg_params(&payment_service, tags, &bolt11, &nwc_uri).await?;
+
+    let unsigned_message = group
+        .send_message(
+            message_params.message,
+            message_params.kind,
+            message_params.tags,
+            &wn,
+        )
+        .await?;
 
     Ok(unsigned_message)
 }
@@ -69,18 +45,25 @@ struct MlsMessageParams {
 #[async_trait::async_trait]
 trait PaymentService: Send + Sync {
     /// Pay a BOLT11 invoice and return the preimage
+    async fn pay_bolt11_invoice(&self, bolt11: &str, nwc_uri: &str)
+        -> Result<String, PaymentError>;
+}
+
+struct DefaultPaymentService {
+    connection_mode: ConnectionMode,
+}
+
+#[async_trait::async_trait]
+impl PaymentService for DefaultPaymentService {
     async fn pay_bolt11_invoice(
         &self,
         bolt11: &str,
         nwc_uri: &str,
     ) -> Result<String, PaymentError> {
-        payments::pay_bolt11_invoice(bolt11, nwc_uri).await
+        payments::pay_bolt11_invoice(bolt11, nwc_uri, self.connection_mode).await
     }
 }
 
-struct DefaultPaymentService;
-impl PaymentService for DefaultPaymentService {}
-
 async fn pay_invoice_and_get_msg_params(
     payment_service: &impl PaymentService,
     tags: Option<Vec<Tag>>,
@@ -249,9 +232,10 @@ mod tests {
 
         assert!(result.is_err(), "Expected error result");
         match result {
-            Err(CommandError::PaymentError(err)) => {
+            Err(err) => {
+                assert_eq!(err.code, ErrorCode::PaymentFailed);
                 assert!(
-                    err.contains(&error_message),
+                    err.message.contains(&error_message),
                     "Error message should contain '{}'",
                     error_message
                 );
@@ -1,9 +1,10 @@
+use crate::Whitenoise;
 use sqlx::sqlite::SqlitePoolOptions;
 use sqlx::{migrate::MigrateDatabase, Sqlite, SqlitePool};
+use std::fmt;
 use std::fs;
 use std::path::PathBuf;
 use std::time::Duration;
-use tauri::{path::BaseDirectory, AppHandle, Manager};
 use thiserror::Error;
 
 const MIGRATION_FILES: &[(&str, &[u8])] = &[
@@ -11,9 +12,16 @@ const MIGRATION_FILES: &[(&str, &[u8])] = &[
         "0001_initial.sql",
         include_bytes!("../db_migrations/0001_initial.sql"),
     ),
+    (
+        "0002_sync_cursors.sql",
+        include_bytes!("../db_migrations/0002_sync_cursors.sql"),
+    ),
+    (
+        "0003_bot_state.sql",
+        include_bytes!("../db_migrations/0003_bot_state.sql"),
+    ),
     // Add new migrations here in order, for example:
-    // ("0002_something.sql", include_bytes!("../db_migrations/0002_something.sql")),
-    // ("0003_another.sql", include_bytes!("../db_migrations/0003_another.sql")),
+    // ("0004_another.sql", include_bytes!("../db_migrations/0004_another.sql")),
 ];
 
 #[derive(Error, Debug)]
@@ -24,8 +32,54 @@ pub enum DatabaseError {
     Sqlx(#[from] sqlx::Error),
     #[error("Migrate error: {0}")]
     Migrate(#[from] sqlx::migrate::MigrateError),
-    #[error("Tauri error: {0}")]
-    Tauri(#[from] tauri::Error),
+    #[error("Invalid {kind} in database: {value}")]
+    InvalidValue { kind: &'static str, value: String },
+    #[error("Corrupt row in {table}: {reason}")]
+    CorruptRow { table: &'static str, reason: String },
+}
+
+impl DatabaseError {
+    pub(crate) fn corrupt_row(table: &'static str, reason: impl fmt::Display) -> Self {
+        DatabaseError::CorruptRow {
+            table,
+            reason: reason.to_string(),
+        }
+    }
+}
+
+/// Converts the rows of a list query, skipping the ones that can't be converted, so that a single
+/// corrupt row or a value written by a newer version doesn't fail the whole list. Skipped rows are
+/// logged and reported with a `corrupt_rows` event.
+pub(crate) fn convert_rows<R, T>(table: &'static str, rows: Vec<R>, wn: &Whitenoise) -> Vec<T>
+where
+    T: TryFrom<R, Error = DatabaseError>,
+{
+    let mut errors = Vec::new();
+    let converted = rows
+        .into_iter()
+        .filter_map(|row| match T::try_from(row) {
+            Ok(value) => Some(value),
+            Err(e) => {
+                errors.push(e.to_string());
+                None
+            }
+        })
+        .collect();
+
+    if !errors.is_empty() {
+        tracing::warn!(
+            target: "whitenoise::database",
+            "Skipped {} rows of {} that couldn't be read: {:?}",
+            errors.len(),
+            table,
+            errors
+        );
+        wn.emit(
+            "corrupt_rows",
+            serde_json::json!({ "table": table, "errors": errors }),
+        );
+    }
+    converted
 }
 
 #[derive(Clone)]
@@ -38,7 +92,7 @@ pub struct Database {
 }
 
 impl Database {
-    pub async fn new(db_path: PathBuf, app_handle: AppHandle) -> Result<Self, DatabaseError> {
+    pub async fn new(db_path: PathBuf) -> Result<Self, DatabaseError> {
         // Create parent directories if they don't exist
         if let Some(parent) = db_path.parent() {
             std::fs::create_dir_all(parent)?;
@@ -94,46 +148,28 @@ impl Database {
         // Run migrations
         tracing::info!("Running migrations...");
 
-        let migrations_path = if cfg!(target_os = "android") {
-            // On Android, we need to copy migrations to a temporary directory
-            let temp_dir = app_handle.path().app_data_dir()?.join("temp_migrations");
-            if temp_dir.exists() {
-                fs::remove_dir_all(&temp_dir)?;
-            }
-            fs::create_dir_all(&temp_dir)?;
-
-            // Copy all migration files from the embedded assets
-            for (filename, content) in MIGRATION_FILES {
-                tracing::info!("Writing migration file: {}", filename);
-                fs::write(temp_dir.join(filename), content)?;
-            }
+        // The migrations are embedded in the binary, so that the database works the same on every
+        // platform and without bundled resources. The migrator reads them from a temporary directory.
+        let migrations_path = db_path.with_file_name("temp_migrations");
+        if migrations_path.exists() {
+            fs::remove_dir_all(&migrations_path)?;
+        }
+        fs::create_dir_all(&migrations_path)?;
 
-            temp_dir
-        } else {
-            app_handle
-                .path()
-                .resolve("db_migrations", BaseDirectory::Resource)?
-        };
+        // Copy all migration files from the embedded assets
+        for (filename, content) in MIGRATION_FILES {
+            tracing::info!("Writing migration file: {}", filename);
+            fs::write(migrations_path.join(filename), content)?;
+        }
 
         tracing::info!("Migrations path: {:?}", migrations_path);
-        if !migrations_path.exists() {
-            tracing::error!("Migrations directory not found at {:?}", migrations_path);
-            return Err(DatabaseError::FileSystem(std::io::Error::new(
-                std::io::ErrorKind::NotFound,
-                format!("Migrations directory not found at {:?}", migrations_path),
-            )));
-        }
 
-        match sqlx::migrate::Migrator::new(migrations_path).await {
+        match sqlx::migrate::Migrator::new(migrations_path.clone()).await {
             Ok(migrator) => {
                 migrator.run(&pool).await?;
                 tracing::info!("Migrations applied successfully");
-                // Clean up temp directory on Android after successful migration
-                if cfg!(target_os = "android") {
-                    if let Ok(temp_dir) = app_handle.path().app_data_dir() {
-                        let _ = fs::remove_dir_all(temp_dir.join("temp_migrations"));
-                    }
-                }
+                // Clean up the temp directory after successful migration
+                let _ = fs::remove_dir_all(&migrations_path);
             }
             Err(e) => {
                 tracing::error!("Failed to create migrator: {:?}", e);
@@ -166,6 +202,15 @@ impl Database {
         sqlx::query("DELETE FROM messages")
             .execute(&mut *txn)
             .await?;
+        sqlx::query("DELETE FROM sync_gaps")
+            .execute(&mut *txn)
+            .await?;
+        sqlx::query("DELETE FROM sync_cursors")
+            .execute(&mut *txn)
+            .await?;
+        sqlx::query("DELETE FROM bot_state")
+            .execute(&mut *txn)
+            .await?;
         sqlx::query("DELETE FROM processed_invites")
             .execute(&mut *txn)
             .await?;
@@ -0,0 +1,122 @@
+//! Event sinks receive the events the core emits while it runs, e.g. when an invite or a group
+//! message has been processed, and the notifications that should be shown to the user.
+//! The desktop app forwards them to the frontend; bots and servers that embed White Noise
+//! can plug in their own sink, or use `NoopEventSink` to ignore them.
+
+use serde::Serialize;
+use serde_json::Value;
+use std::sync::Arc;
+use tokio::sync::broadcast;
+
+/// How many events a slow subscriber can fall behind before it misses some.
+const BROADCAST_CAPACITY: usize = 1024;
+
+pub trait EventSink: Send + Sync {
+    /// Called with the name of the event and its JSON payload.
+    fn emit(&self, event: &str, payload: Value);
+
+    /// Called when the user should be notified, e.g. about a new message. Ignored by default.
+    fn notify(&self, _title: &str, _body: &str) {}
+}
+
+impl dyn EventSink {
+    /// Serializes the payload and emits it. Payloads that can't be serialized are logged and dropped.
+    pub fn emit_serialized<T: Serialize>(&self, event: &str, payload: T) {
+        match serde_json::to_value(payload) {
+            Ok(payload) => self.emit(event, payload),
+            Err(e) => tracing::error!(
+                target: "whitenoise::event_sink",
+                "Error serializing payload of {}: {}",
+                event,
+                e
+            ),
+        }
+    }
+}
+
+/// Drops every event and notification.
+pub struct NoopEventSink;
+
+impl EventSink for NoopEventSink {
+    fn emit(&self, _event: &str, _payload: Value) {}
+}
+
+/// Forwards events to another sink and broadcasts them to subscribers, e.g. API clients.
+/// `Whitenoise` wraps every sink in one, see `Whitenoise::subscribe_events`.
+pub(crate) struct BroadcastEventSink {
+    inner: Arc<dyn EventSink>,
+    sender: broadcast::Sender<(String, Value)>,
+}
+
+impl BroadcastEventSink {
+    pub(crate) fn new(inner: Arc<dyn EventSink>) -> Self {
+        let (sender, _) = broadcast::channel(BROADCAST_CAPACITY);
+        Self { inner, sender }
+    }
+
+    pub(crate) fn subscribe(&self) -> broadcast::Receiver<(String, Value)> {
+        self.sender.subscribe()
+    }
+}
+
+impl EventSink for BroadcastEventSink {
+    fn emit(&self, event: &str, payload: Value) {
+        if self.sender.receiver_count() > 0 {
+            // Only fails if the last subscriber just went away
+            let _ = self.sender.send((event.to_string(), payload.clone()));
+        }
+        self.inner.emit(event, payload);
+    }
+
+    fn notify(&self, title: &str, body: &str) {
+        self.inner.notify(title, body);
+    }
+}
+
+/// Forwards events to the frontend of the Tauri app and shows notifications with the notification plugin.
+#[cfg(feature = "desktop")]
+pub struct TauriEventSink {
+    app_handle: tauri::AppHandle,
+}
+
+#[cfg(feature = "desktop")]
+impl TauriEventSink {
+    pub fn new(app_handle: tauri::AppHandle) -> Self {
+        Self { app_handle }
+    }
+}
+
+#[cfg(feature = "desktop")]
+impl EventSink for TauriEventSink {
+    fn emit(&self, event: &str, payload: Value) {
+        use tauri::Emitter;
+
+        if let Err(e) = self.app_handle.emit(event, payload) {
+            tracing::error!(
+                target: "whitenoise::event_sink",
+                "Error emitting {}: {}",
+                event,
+                e
+            );
+        }
+    }
+
+    fn notify(&self, title: &str, body: &str) {
+        use tauri_plugin_notification::NotificationExt;
+
+        if let Err(e) = self
+            .app_handle
+            .notification()
+            .builder()
+            .title(title)
+            .body(body)
+            .show()
+        {
+            tracing::error!(
+                target: "whitenoise::event_sink",
+                "Error showing notification: {}",
+                e
+            );
+        }
+    }
+}
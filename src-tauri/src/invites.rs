@@ -1,5 +1,7 @@
 use crate::accounts::Account;
-use crate::database::DatabaseError;
+use crate::database::{convert_rows, DatabaseError};
+use crate::groups::{Group, GroupError, GroupType};
+use crate::nostr_manager::NostrManagerError;
 use crate::Whitenoise;
 use nostr_sdk::prelude::*;
 use serde::{Deserialize, Serialize};
@@ -21,6 +23,15 @@ pub enum InviteError {
 
     #[error("Account error: {0}")]
     Account(#[from] crate::accounts::AccountError),
+
+    #[error("Group error: {0}")]
+    Group(#[from] GroupError),
+
+    #[error("Nostr Manager error: {0}")]
+    NostrManager(#[from] NostrManagerError),
+
+    #[error("Error joining group from welcome: {0}")]
+    Welcome(String),
 }
 
 pub type Result<T> = std::result::Result<T, InviteError>;
@@ -92,16 +103,19 @@ pub struct ProcessedInvite {
     pub failure_reason: String,
 }
 
-impl From<ProcessedInviteRow> for ProcessedInvite {
-    fn from(row: ProcessedInviteRow) -> Self {
-        ProcessedInvite {
+impl TryFrom<ProcessedInviteRow> for ProcessedInvite {
+    type Error = DatabaseError;
+
+    fn try_from(row: ProcessedInviteRow) -> std::result::Result<Self, Self::Error> {
+        Ok(ProcessedInvite {
             event_id: row.event_id,
             invite_event_id: row.invite_event_id,
-            account_pubkey: PublicKey::from_hex(&row.account_pubkey).unwrap(),
+            account_pubkey: PublicKey::from_hex(&row.account_pubkey)
+                .map_err(|e| DatabaseError::corrupt_row("processed_invites", e))?,
             processed_at: row.processed_at,
-            state: ProcessedInviteState::from(row.state),
+            state: ProcessedInviteState::try_from(row.state)?,
             failure_reason: row.failure_reason,
-        }
+        })
     }
 }
 
@@ -124,12 +138,17 @@ pub enum ProcessedInviteState {
     Failed,
 }
 
-impl From<String> for ProcessedInviteState {
-    fn from(s: String) -> Self {
+impl TryFrom<String> for ProcessedInviteState {
+    type Error = DatabaseError;
+
+    fn try_from(s: String) -> std::result::Result<Self, Self::Error> {
         match s.to_lowercase().as_str() {
-            "processed" => ProcessedInviteState::Processed,
-            "failed" => ProcessedInviteState::Failed,
-            _ => panic!("Invalid processed invite state: {}", s),
+            "processed" => Ok(ProcessedInviteState::Processed),
+            "failed" => Ok(ProcessedInviteState::Failed),
+            _ => Err(DatabaseError::InvalidValue {
+                kind: "processed invite state",
+                value: s,
+            }),
         }
     }
 }
@@ -151,14 +170,19 @@ pub enum InviteState {
     Ignored,
 }
 
-impl From<String> for InviteState {
-    fn from(s: String) -> Self {
+impl TryFrom<String> for InviteState {
+    type Error = DatabaseError;
+
+    fn try_from(s: String) -> std::result::Result<Self, Self::Error> {
         match s.to_lowercase().as_str() {
-            "pending" => InviteState::Pending,
-            "accepted" => InviteState::Accepted,
-            "declined" => InviteState::Declined,
-            "ignored" => InviteState::Ignored,
-            _ => panic!("Invalid invite state: {}", s),
+            "pending" => Ok(InviteState::Pending),
+            "accepted" => Ok(InviteState::Accepted),
+            "declined" => Ok(InviteState::Declined),
+            "ignored" => Ok(InviteState::Ignored),
+            _ => Err(DatabaseError::InvalidValue {
+                kind: "invite state",
+                value: s,
+            }),
         }
     }
 }
@@ -174,23 +198,28 @@ impl From<InviteState> for String {
     }
 }
 
-impl From<InviteRow> for Invite {
-    fn from(row: InviteRow) -> Self {
-        Invite {
+impl TryFrom<InviteRow> for Invite {
+    type Error = DatabaseError;
+
+    fn try_from(row: InviteRow) -> std::result::Result<Self, Self::Error> {
+        fn corrupt(e: impl std::fmt::Display) -> DatabaseError {
+            DatabaseError::corrupt_row("invites", e)
+        }
+        Ok(Invite {
             event_id: row.event_id,
             account_pubkey: row.account_pubkey,
-            event: UnsignedEvent::from_json(&row.event).unwrap(),
+            event: UnsignedEvent::from_json(&row.event).map_err(corrupt)?,
             mls_group_id: row.mls_group_id,
             nostr_group_id: row.nostr_group_id,
             group_name: row.group_name,
             group_description: row.group_description,
-            group_admin_pubkeys: serde_json::from_str(&row.group_admin_pubkeys).unwrap(),
-            group_relays: serde_json::from_str(&row.group_relays).unwrap(),
+            group_admin_pubkeys: serde_json::from_str(&row.group_admin_pubkeys).map_err(corrupt)?,
+            group_relays: serde_json::from_str(&row.group_relays).map_err(corrupt)?,
             inviter: row.inviter,
             member_count: row.member_count,
-            state: InviteState::from(row.state),
+            state: InviteState::try_from(row.state)?,
             outer_event_id: row.outer_event_id,
-        }
+        })
     }
 }
 
@@ -198,7 +227,7 @@ impl Invite {
     pub async fn find_by_id(
         account_pubkey: &str,
         invite_event_id: &str,
-        wn: tauri::State<'_, Whitenoise>,
+        wn: &Whitenoise,
     ) -> Result<Invite> {
         let invite_row = sqlx::query_as::<_, InviteRow>(
             "SELECT * FROM invites WHERE account_pubkey = ? AND event_id = ?",
@@ -208,35 +237,21 @@ impl Invite {
         .fetch_one(&wn.database.pool)
         .await?;
 
-        Ok(Invite {
-            event_id: invite_row.event_id,
-            account_pubkey: invite_row.account_pubkey,
-            event: UnsignedEvent::from_json(&invite_row.event)?,
-            mls_group_id: invite_row.mls_group_id,
-            nostr_group_id: invite_row.nostr_group_id,
-            group_name: invite_row.group_name,
-            group_description: invite_row.group_description,
-            group_admin_pubkeys: serde_json::from_str(&invite_row.group_admin_pubkeys)?,
-            group_relays: serde_json::from_str(&invite_row.group_relays)?,
-            inviter: invite_row.inviter,
-            member_count: invite_row.member_count,
-            state: InviteState::from(invite_row.state),
-            outer_event_id: invite_row.outer_event_id,
-        })
+        Ok(Invite::try_from(invite_row)?)
     }
 
-    pub async fn pending(wn: tauri::State<'_, Whitenoise>) -> Result<Vec<Invite>> {
-        let active_account = Account::get_active(wn.clone()).await?;
+    pub async fn pending(wn: &Whitenoise) -> Result<Vec<Invite>> {
+        let active_account = Account::get_active(wn).await?;
         let invites = sqlx::query_as::<_, InviteRow>(
             "SELECT * FROM invites WHERE state = 'pending' AND account_pubkey = ?",
         )
         .bind(active_account.pubkey.to_hex())
         .fetch_all(&wn.database.pool)
         .await?;
-        Ok(invites.into_iter().map(|row| row.into()).collect())
+        Ok(convert_rows("invites", invites, wn))
     }
 
-    pub async fn save(&self, wn: tauri::State<'_, Whitenoise>) -> Result<Invite> {
+    pub async fn save(&self, wn: &Whitenoise) -> Result<Invite> {
         let mut txn = wn.database.pool.begin().await?;
         sqlx::query("INSERT OR REPLACE INTO invites (event_id, account_pubkey, event, mls_group_id, nostr_group_id, group_name, group_description, group_admin_pubkeys, group_relays, inviter, member_count, outer_event_id, state) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)")
             .bind(&self.event_id)
@@ -262,35 +277,105 @@ impl Invite {
     // pub fn find_by_event_id(event_id: &str, database: &Database) -> Result<Option<Invite>> {}
     // pub fn fetch_invites_from_relays(database: &Database) -> Result<()> {}
 
-    // pub fn accept(&self, database: &Database) -> Result<()> {}
-    // pub fn decline(&self, database: &Database) -> Result<()> {}
+    /// Accepts the invite and joins the corresponding group.
+    ///
+    /// # Events Emitted
+    /// * `group_added` - Emitted with the newly joined group after successful join
+    /// * `invite_accepted` - Emitted with the updated invite after it is accepted
+    pub async fn accept(&mut self, wn: &Whitenoise) -> Result<Group> {
+        tracing::debug!(target: "whitenoise::invites::accept", "Accepting invite {:?}", self.event.id);
+
+        let active_account = Account::get_active(wn).await?;
+
+        // Scope the MutexGuard to drop it before the .await points
+        let (mls_group, nostr_group_data) = {
+            let nostr_mls = wn.nostr_mls().lock_owned().await;
+            let welcome = hex::decode(&self.event.content).map_err(|e| {
+                InviteError::Welcome(format!("Error decoding welcome event: {}", e))
+            })?;
+            let joined_group_result = nostr_mls
+                .join_group_from_welcome(welcome)
+                .map_err(|e| InviteError::Welcome(e.to_string()))?;
+
+            (
+                joined_group_result.mls_group,
+                joined_group_result.nostr_group_data,
+            )
+        };
+
+        let group_type = match mls_group.members().count() {
+            2 => GroupType::DirectMessage,
+            _ => GroupType::Group,
+        };
+
+        let group = Group::new(
+            mls_group.group_id().to_vec(),
+            mls_group.epoch().as_u64(),
+            group_type,
+            nostr_group_data,
+            wn,
+        )
+        .await?;
+
+        // Update the subscription for MLS group messages to include the new group
+        let group_ids = active_account.nostr_group_ids(wn).await?;
+        wn.nostr
+            .subscribe_mls_group_messages(group_ids.clone())
+            .await?;
+
+        // Manually fetch for MLS messages for the new group
+        wn.nostr
+            .fetch_group_messages(Timestamp::zero(), group_ids)
+            .await?;
+
+        wn.emit("group_added", group.clone());
+
+        self.state = InviteState::Accepted;
+        self.save(wn).await?;
+
+        wn.emit("invite_accepted", self.clone());
+
+        tracing::debug!(target: "whitenoise::invites::accept", "Accepted invite - Added group: {:?}", group);
+
+        Ok(group)
+    }
+
+    /// Declines the invite. Emits `invite_declined` with the updated invite.
+    pub async fn decline(&mut self, wn: &Whitenoise) -> Result<()> {
+        tracing::debug!(target: "whitenoise::invites::decline", "Declining invite {:?}", self.event.id);
+
+        self.state = InviteState::Declined;
+        self.save(wn).await?;
+
+        wn.emit("invite_declined", self.clone());
+
+        Ok(())
+    }
+
     // pub fn remove(&self, database: &Database) -> Result<()> {}
 }
 
 impl ProcessedInvite {
     pub async fn find_by_invite_event_id(
         event_id: EventId,
-        wn: tauri::State<'_, Whitenoise>,
+        account_pubkey: &PublicKey,
+        wn: &Whitenoise,
     ) -> Result<Option<ProcessedInvite>> {
-        let active_account = Account::get_active(wn.clone()).await?;
-
         let processed_invite_row = sqlx::query_as::<_, ProcessedInviteRow>(
             "SELECT * FROM processed_invites WHERE event_id = ? AND account_pubkey = ?",
         )
         .bind(event_id.to_string())
-        .bind(active_account.pubkey.to_hex())
+        .bind(account_pubkey.to_hex())
         .fetch_optional(&wn.database.pool)
         .await?;
         match processed_invite_row {
-            Some(row) => Ok(Some(row.into())),
+            Some(row) => Ok(Some(row.try_into()?)),
             None => Ok(None),
         }
     }
 
-    pub async fn failed_with_reason(
-        wn: tauri::State<'_, Whitenoise>,
-    ) -> Result<Vec<(EventId, String)>> {
-        let active_account = Account::get_active(wn.clone()).await?;
+    pub async fn failed_with_reason(wn: &Whitenoise) -> Result<Vec<(EventId, String)>> {
+        let active_account = Account::get_active(wn).await?;
 
         let processed_invite_rows = sqlx::query_as::<_, ProcessedInviteRow>(
             "SELECT * FROM processed_invites WHERE state = 'failed' AND account_pubkey = ?",
@@ -298,10 +383,23 @@ impl ProcessedInvite {
         .bind(active_account.pubkey.to_hex())
         .fetch_all(&wn.database.pool)
         .await?;
-        Ok(processed_invite_rows
-            .into_iter()
-            .map(|row| (EventId::parse(&row.event_id).unwrap(), row.failure_reason))
-            .collect())
+        Ok(
+            convert_rows::<_, ProcessedInvite>("processed_invites", processed_invite_rows, wn)
+                .into_iter()
+                .filter_map(|invite| match EventId::parse(&invite.event_id) {
+                    Ok(event_id) => Some((event_id, invite.failure_reason)),
+                    Err(e) => {
+                        tracing::warn!(
+                            target: "whitenoise::invites::failed_with_reason",
+                            "Skipping processed invite with invalid event id {}: {}",
+                            invite.event_id,
+                            e
+                        );
+                        None
+                    }
+                })
+                .collect(),
+        )
     }
 
     pub async fn create_with_state_and_reason(
@@ -309,16 +407,15 @@ impl ProcessedInvite {
         invite_event_id: EventId,
         state: ProcessedInviteState,
         reason: String,
-        wn: tauri::State<'_, Whitenoise>,
+        account_pubkey: &PublicKey,
+        wn: &Whitenoise,
     ) -> Result<ProcessedInvite> {
-        let active_account = Account::get_active(wn.clone()).await?;
-
         let mut txn = wn.database.pool.begin().await?;
         let processed_at = chrono::Utc::now().timestamp() as u64;
         sqlx::query("INSERT INTO processed_invites (event_id, invite_event_id, account_pubkey, processed_at, state, failure_reason) VALUES (?, ?, ?, ?, ?, ?)")
             .bind(event_id.to_string())
             .bind(invite_event_id.to_string())
-            .bind(active_account.pubkey.to_hex())
+            .bind(account_pubkey.to_hex())
             .bind(processed_at as i64)
             .bind(String::from(state.clone()))
             .bind(reason.clone())
@@ -329,7 +426,7 @@ impl ProcessedInvite {
         Ok(ProcessedInvite {
             event_id: event_id.to_string(),
             invite_event_id: invite_event_id.to_string(),
-            account_pubkey: active_account.pubkey,
+            account_pubkey: *account_pubkey,
             processed_at,
             state,
             failure_reason: reason,
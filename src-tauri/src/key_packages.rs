@@ -1,17 +1,20 @@
 use crate::accounts::{Account, AccountError};
 use crate::nostr_manager;
+use crate::nostr_manager::event_processor::ProcessingContext;
+use crate::nostr_manager::{NostrManager, PublishTarget};
 use crate::relays::RelayType;
 use crate::whitenoise::Whitenoise;
 use nostr_openmls::key_packages::{create_key_package_for_event, KeyPackage};
+use nostr_openmls::NostrMls;
 use nostr_sdk::prelude::*;
 use thiserror::Error;
 
 #[derive(Error, Debug)]
 pub enum KeyPackageError {
-    #[error("No valid key package found: {0}")]
-    NoValidKeyPackage(String),
-    #[error("Error fetching key package: {0}")]
-    FetchingKeyPackage(String),
+    #[error("No valid key package event found for member: {pubkey}")]
+    NoValidKeyPackage { pubkey: String },
+    #[error("Error fetching valid key package event for member {pubkey}: {reason}")]
+    FetchingKeyPackage { pubkey: String, reason: String },
     #[error("Account Error: {0}")]
     AccountError(#[from] AccountError),
     #[error("Nostr Error: {0}")]
@@ -36,7 +39,7 @@ pub type Result<T> = std::result::Result<T, KeyPackageError>;
 /// Fetches key packages for a list of pubkeys
 pub async fn fetch_key_packages_for_members(
     member_pubkeys: &[String],
-    wn: tauri::State<'_, Whitenoise>,
+    wn: &Whitenoise,
 ) -> Result<Vec<KeyPackageResponse>> {
     let mut member_key_packages: Vec<KeyPackageResponse> = Vec::new();
 
@@ -49,7 +52,7 @@ pub async fn fetch_key_packages_for_members(
     // Check that members are valid pubkeys & fetch key packages
     for pubkey in member_pubkeys.iter() {
         // Fetch prekeys from the members
-        match fetch_key_package_for_pubkey(pubkey.clone(), wn.clone()).await {
+        match fetch_key_package_for_pubkey(pubkey.clone(), wn).await {
             Ok(event_and_key_package) => match event_and_key_package {
                 Some((event_id, kp)) => member_key_packages.push(KeyPackageResponse {
                     pubkey: pubkey.clone(),
@@ -58,17 +61,16 @@ pub async fn fetch_key_packages_for_members(
                 }),
                 None => {
                     // TODO: Need to fix this when we get to adding more than one member to a group at once.
-                    return Err(KeyPackageError::NoValidKeyPackage(format!(
-                        "No valid key package event found for member: {}",
-                        pubkey
-                    )));
+                    return Err(KeyPackageError::NoValidKeyPackage {
+                        pubkey: pubkey.clone(),
+                    });
                 }
             },
-            Err(_) => {
-                return Err(KeyPackageError::FetchingKeyPackage(format!(
-                    "Error fetching valid key package event for member: {}",
-                    pubkey
-                )));
+            Err(e) => {
+                return Err(KeyPackageError::FetchingKeyPackage {
+                    pubkey: pubkey.clone(),
+                    reason: e.to_string(),
+                });
             }
         };
     }
@@ -78,19 +80,17 @@ pub async fn fetch_key_packages_for_members(
 /// Fetches key packages for a single pubkey
 pub async fn fetch_key_package_for_pubkey(
     pubkey: String,
-    wn: tauri::State<'_, Whitenoise>,
+    wn: &Whitenoise,
 ) -> Result<Option<(EventId, KeyPackage)>> {
     tracing::debug!(target: "whitenoise::key_packages::fetch_key_package_for_pubkey", "Fetching key package for pubkey: {:?}", pubkey);
     let public_key = PublicKey::from_hex(&pubkey).expect("Invalid pubkey");
-    let key_package_filter = Filter::new().kind(Kind::MlsKeyPackage).author(public_key);
+    // Key packages live on the user's key package and write relays, not necessarily on ours
     let key_package_events = wn
         .nostr
-        .client
-        .fetch_events(vec![key_package_filter], wn.nostr.timeout().await.unwrap())
-        .await
-        .expect("Error fetching key_package events");
+        .fetch_events_from_outboxes(&[public_key], vec![Kind::MlsKeyPackage])
+        .await?;
 
-    let nostr_mls = wn.nostr_mls.lock().await;
+    let nostr_mls = wn.nostr_mls().lock_owned().await;
     let ciphersuite = nostr_mls.ciphersuite;
     let extensions = nostr_mls.extensions.clone();
 
@@ -166,11 +166,11 @@ pub async fn delete_key_package_from_relays(
     event_id: &EventId,
     key_package_relays: &[String],
     delete_mls_stored_keys: bool,
-    wn: tauri::State<'_, Whitenoise>,
+    wn: &Whitenoise,
 ) -> Result<()> {
     let current_pubkey = wn
         .nostr
-        .client
+        .client()
         .signer()
         .await
         .unwrap()
@@ -179,7 +179,7 @@ pub async fn delete_key_package_from_relays(
         .unwrap();
     let key_package_events = wn
         .nostr
-        .client
+        .client()
         .fetch_events(
             vec![Filter::new()
                 .id(*event_id)
@@ -192,7 +192,7 @@ pub async fn delete_key_package_from_relays(
     if let Some(event) = key_package_events.first() {
         // Make sure we delete the private key material from MLS storage if requested
         if delete_mls_stored_keys {
-            let nostr_mls = wn.nostr_mls.lock().await;
+            let nostr_mls = wn.nostr_mls().lock_owned().await;
             let key_package = nostr_openmls::key_packages::parse_key_package(
                 event.content.to_string(),
                 &nostr_mls,
@@ -204,45 +204,110 @@ pub async fn delete_key_package_from_relays(
         }
         let builder = EventBuilder::delete(vec![event.id]);
         wn.nostr
-            .client
-            .send_event_builder_to(key_package_relays, builder)
+            .publish_event_builder_to(
+                PublishTarget::KeyPackage,
+                key_package_relays.to_vec(),
+                builder,
+            )
             .await?;
     }
     Ok(())
 }
 
-pub async fn publish_key_package(wn: tauri::State<'_, Whitenoise>) -> Result<()> {
-    let active_account = Account::get_active(wn.clone()).await?;
+/// Returns the relays the account's key packages are published to: the network profile's
+/// key package relays if it has them, otherwise the account's own key package relays.
+pub async fn key_package_relays(account: &Account, wn: &Whitenoise) -> Result<Vec<String>> {
+    match wn.nostr.network_profile().await.key_package_relays {
+        Some(relays) => Ok(relays),
+        None => Ok(account.relays(RelayType::KeyPackage, wn).await?),
+    }
+}
+
+pub async fn publish_key_package(wn: &Whitenoise) -> Result<()> {
+    let active_account = Account::get_active(wn).await?;
     let pubkey = active_account.pubkey;
 
     let event: EventBuilder;
-    let key_package_relays = if cfg!(dev) {
-        vec!["ws://localhost:8080".to_string()]
-    } else {
-        active_account
-            .relays(RelayType::KeyPackage, wn.clone())
-            .await?
-    };
+    let key_package_relays = key_package_relays(&active_account, wn).await?;
 
     {
-        let nostr_mls = wn.nostr_mls.lock().await;
-        let ciphersuite = nostr_mls.ciphersuite_value().to_string();
-        let extensions = nostr_mls.extensions_value();
+        let nostr_mls = wn.nostr_mls().lock_owned().await;
+        event = key_package_event_builder(pubkey, key_package_relays.clone(), &nostr_mls)?;
+    }
+    wn.nostr
+        .publish_event_builder_to(PublishTarget::KeyPackage, key_package_relays, event)
+        .await?;
 
-        let serialized_key_package = create_key_package_for_event(pubkey.to_hex(), &nostr_mls)?;
+    Ok(())
+}
 
-        event = EventBuilder::new(Kind::MlsKeyPackage, serialized_key_package).tags([
-            Tag::custom(TagKind::MlsProtocolVersion, ["1.0"]),
-            Tag::custom(TagKind::MlsCiphersuite, [ciphersuite]),
-            Tag::custom(TagKind::MlsExtensions, [extensions]),
-            Tag::custom(TagKind::Client, ["whitenoise"]),
-            Tag::custom(TagKind::Relays, key_package_relays.clone()),
-        ]);
+/// Deletes a key package that was used for an invite and publishes a fresh one in its place.
+///
+/// Unlike `delete_key_package_from_relays` and `publish_key_package` this works on behalf of
+/// the account of the given processing context, so it can be used for accounts that are
+/// synced in the background as well as for the active account.
+pub async fn rotate_key_package(
+    used_key_package_id: &EventId,
+    context: &ProcessingContext,
+    wn: &Whitenoise,
+) -> Result<()> {
+    NostrManager::check_publish_allowed_with(context.lockdown_mode(), PublishTarget::KeyPackage)?;
+
+    let pubkey = context.account.pubkey;
+    let key_package_relays = key_package_relays(&context.account, wn).await?;
+
+    let used_key_packages = context
+        .client
+        .fetch_events(
+            vec![Filter::new()
+                .id(*used_key_package_id)
+                .kind(Kind::MlsKeyPackage)
+                .author(pubkey)],
+            wn.nostr.timeout().await?,
+        )
+        .await?;
+
+    if let Some(used_key_package) = used_key_packages.first() {
+        context
+            .client
+            .send_event_builder_to(
+                key_package_relays.clone(),
+                EventBuilder::delete(vec![used_key_package.id]),
+            )
+            .await?;
     }
-    wn.nostr
+
+    let event: EventBuilder;
+    {
+        let nostr_mls = context.nostr_mls.lock().await;
+        event = key_package_event_builder(pubkey, key_package_relays.clone(), &nostr_mls)?;
+    }
+    context
         .client
-        .send_event_builder_to(key_package_relays.clone(), event)
+        .send_event_builder_to(key_package_relays, event)
         .await?;
 
     Ok(())
 }
+
+/// Builds an unsigned key package event (kind 443) for the given pubkey.
+fn key_package_event_builder(
+    pubkey: PublicKey,
+    key_package_relays: Vec<String>,
+    nostr_mls: &NostrMls,
+) -> Result<EventBuilder> {
+    let ciphersuite = nostr_mls.ciphersuite_value().to_string();
+    let extensions = nostr_mls.extensions_value();
+
+    let serialized_key_package = create_key_package_for_event(pubkey.to_hex(), nostr_mls)?;
+
+    Ok(
+        EventBuilder::new(Kind::MlsKeyPackage, serialized_key_package).tags([
+            Tag::custom(TagKind::MlsProtocolVersion, ["1.0"]),
+            Tag::custom(TagKind::MlsCiphersuite, [ciphersuite]),
+            Tag::custom(TagKind::MlsExtensions, [extensions]),
+            Tag::custom(TagKind::Client, ["whitenoise"]),
+            Tag::custom(TagKind::Relays, key_package_relays),
+        ]),
+    )
+}
@@ -1,4 +1,5 @@
 use crate::accounts::Account;
+use crate::database::{convert_rows, DatabaseError};
 use crate::Whitenoise;
 use nostr_sdk::prelude::*;
 use serde::{Deserialize, Serialize};
@@ -10,6 +11,8 @@ pub enum MessageError {
     Sqlx(#[from] sqlx::Error),
     #[error("Account error: {0}")]
     Account(#[from] crate::accounts::AccountError),
+    #[error("Database error: {0}")]
+    Database(#[from] DatabaseError),
     #[error("Message not found")]
     NotFound,
 }
@@ -52,12 +55,17 @@ pub enum ProcessedMessageState {
     Failed,
 }
 
-impl From<String> for ProcessedMessageState {
-    fn from(s: String) -> Self {
+impl TryFrom<String> for ProcessedMessageState {
+    type Error = DatabaseError;
+
+    fn try_from(s: String) -> std::result::Result<Self, Self::Error> {
         match s.to_lowercase().as_str() {
-            "processed" => ProcessedMessageState::Processed,
-            "failed" => ProcessedMessageState::Failed,
-            _ => panic!("Invalid processed message state: {}", s),
+            "processed" => Ok(ProcessedMessageState::Processed),
+            "failed" => Ok(ProcessedMessageState::Failed),
+            _ => Err(DatabaseError::InvalidValue {
+                kind: "processed message state",
+                value: s,
+            }),
         }
     }
 }
@@ -92,16 +100,25 @@ pub struct ProcessedMessage {
     pub failure_reason: String,
 }
 
-impl From<ProcessedMessageRow> for ProcessedMessage {
-    fn from(row: ProcessedMessageRow) -> Self {
-        ProcessedMessage {
-            event_id: EventId::parse(&row.event_id).unwrap(),
-            message_event_id: row.message_event_id.map(|id| EventId::parse(&id).unwrap()),
-            account_pubkey: PublicKey::from_hex(&row.account_pubkey).unwrap(),
+impl TryFrom<ProcessedMessageRow> for ProcessedMessage {
+    type Error = DatabaseError;
+
+    fn try_from(row: ProcessedMessageRow) -> std::result::Result<Self, Self::Error> {
+        fn corrupt(e: impl std::fmt::Display) -> DatabaseError {
+            DatabaseError::corrupt_row("processed_messages", e)
+        }
+        Ok(ProcessedMessage {
+            event_id: EventId::parse(&row.event_id).map_err(corrupt)?,
+            message_event_id: row
+                .message_event_id
+                .map(|id| EventId::parse(&id))
+                .transpose()
+                .map_err(corrupt)?,
+            account_pubkey: PublicKey::from_hex(&row.account_pubkey).map_err(corrupt)?,
             processed_at: row.processed_at,
-            state: ProcessedMessageState::from(row.state),
+            state: ProcessedMessageState::try_from(row.state)?,
             failure_reason: row.failure_reason,
-        }
+        })
     }
 }
 
@@ -122,29 +139,26 @@ impl From<ProcessedMessage> for ProcessedMessageRow {
 impl ProcessedMessage {
     pub async fn find_by_event_id(
         event_id: EventId,
-        wn: tauri::State<'_, Whitenoise>,
+        account_pubkey: &PublicKey,
+        wn: &Whitenoise,
     ) -> Result<Option<ProcessedMessage>> {
-        let active_account = Account::get_active(wn.clone()).await?;
-
         let processed_message_row = sqlx::query_as::<_, ProcessedMessageRow>(
             "SELECT * FROM processed_messages WHERE event_id = ? AND account_pubkey = ?",
         )
         .bind(event_id.to_string())
-        .bind(active_account.pubkey.to_hex())
+        .bind(account_pubkey.to_hex())
         .fetch_optional(&wn.database.pool)
         .await?;
 
         match processed_message_row {
-            Some(row) => Ok(Some(row.into())),
+            Some(row) => Ok(Some(row.try_into()?)),
             None => Ok(None),
         }
     }
 
     #[allow(dead_code)]
-    pub async fn failed_with_reason(
-        wn: tauri::State<'_, Whitenoise>,
-    ) -> Result<Vec<(EventId, String)>> {
-        let active_account = Account::get_active(wn.clone()).await?;
+    pub async fn failed_with_reason(wn: &Whitenoise) -> Result<Vec<(EventId, String)>> {
+        let active_account = Account::get_active(wn).await?;
 
         let processed_message_rows = sqlx::query_as::<_, ProcessedMessageRow>(
             "SELECT * FROM processed_messages WHERE state = 'failed' AND account_pubkey = ?",
@@ -152,10 +166,12 @@ impl ProcessedMessage {
         .bind(active_account.pubkey.to_hex())
         .fetch_all(&wn.database.pool)
         .await?;
-        Ok(processed_message_rows
-            .into_iter()
-            .map(|row| (EventId::parse(&row.event_id).unwrap(), row.failure_reason))
-            .collect())
+        Ok(
+            convert_rows::<_, ProcessedMessage>("processed_messages", processed_message_rows, wn)
+                .into_iter()
+                .map(|message| (message.event_id, message.failure_reason))
+                .collect(),
+        )
     }
 
     pub async fn create_with_state_and_reason(
@@ -163,16 +179,15 @@ impl ProcessedMessage {
         message_event_id: Option<EventId>,
         state: ProcessedMessageState,
         reason: String,
-        wn: tauri::State<'_, Whitenoise>,
+        account_pubkey: &PublicKey,
+        wn: &Whitenoise,
     ) -> Result<ProcessedMessage> {
-        let active_account = Account::get_active(wn.clone()).await?;
-
         let mut txn = wn.database.pool.begin().await?;
         let processed_at = chrono::Utc::now().timestamp() as u64;
         sqlx::query("INSERT INTO processed_messages (event_id, message_event_id, account_pubkey, processed_at, state, failure_reason) VALUES (?, ?, ?, ?, ?, ?) RETURNING id")
             .bind(event_id.to_string())
             .bind(message_event_id.map(|id| id.to_string()))
-            .bind(active_account.pubkey.to_hex())
+            .bind(account_pubkey.to_hex())
             .bind(processed_at as i64)
             .bind(String::from(state.clone()))
             .bind(reason.clone())
@@ -183,7 +198,7 @@ impl ProcessedMessage {
         Ok(ProcessedMessage {
             event_id,
             message_event_id,
-            account_pubkey: active_account.pubkey,
+            account_pubkey: *account_pubkey,
             processed_at,
             state,
             failure_reason: reason,
@@ -192,11 +207,8 @@ impl ProcessedMessage {
 }
 
 impl Message {
-    pub async fn find_by_event_id(
-        event_id: EventId,
-        wn: tauri::State<'_, Whitenoise>,
-    ) -> Result<Message> {
-        let active_account = Account::get_active(wn.clone()).await?;
+    pub async fn find_by_event_id(event_id: EventId, wn: &Whitenoise) -> Result<Message> {
+        let active_account = Account::get_active(wn).await?;
 
         let message_row = sqlx::query_as::<_, MessageRow>(
             "SELECT * FROM messages WHERE event_id = ? AND account_pubkey = ?",
@@ -207,24 +219,29 @@ impl Message {
         .await?;
 
         match message_row {
-            Some(row) => Ok(row.into()),
+            Some(row) => Ok(row.try_into()?),
             None => Err(MessageError::NotFound),
         }
     }
 }
 
-impl From<MessageRow> for Message {
-    fn from(row: MessageRow) -> Self {
-        Message {
-            event_id: EventId::parse(&row.event_id).unwrap(),
-            account_pubkey: PublicKey::from_hex(&row.account_pubkey).unwrap(),
-            author_pubkey: PublicKey::from_hex(&row.author_pubkey).unwrap(),
+impl TryFrom<MessageRow> for Message {
+    type Error = DatabaseError;
+
+    fn try_from(row: MessageRow) -> std::result::Result<Self, Self::Error> {
+        fn corrupt(e: impl std::fmt::Display) -> DatabaseError {
+            DatabaseError::corrupt_row("messages", e)
+        }
+        Ok(Message {
+            event_id: EventId::parse(&row.event_id).map_err(corrupt)?,
+            account_pubkey: PublicKey::from_hex(&row.account_pubkey).map_err(corrupt)?,
+            author_pubkey: PublicKey::from_hex(&row.author_pubkey).map_err(corrupt)?,
             mls_group_id: row.mls_group_id,
             created_at: Timestamp::from(row.created_at),
             content: row.content,
-            tags: serde_json::from_str(&row.tags).unwrap(),
-            event: serde_json::from_str(&row.event).unwrap(),
-            outer_event_id: EventId::parse(&row.outer_event_id).unwrap(),
-        }
+            tags: serde_json::from_str(&row.tags).map_err(corrupt)?,
+            event: serde_json::from_str(&row.event).map_err(corrupt)?,
+            outer_event_id: EventId::parse(&row.outer_event_id).map_err(corrupt)?,
+        })
     }
 }
@@ -0,0 +1,310 @@
+//! NIP-42 authentication functions for NostrManager
+//! Relays can require clients to authenticate before serving some requests, e.g. inbox relays only
+//! serve gift-wraps to their recipient. Authenticating reveals the account's pubkey to the relay,
+//! so each account has a `RelayAuthPolicy` that decides which relays we authenticate to.
+//! Subscriptions a relay closed because we weren't authenticated are sent again once we are.
+
+use crate::accounts::Account;
+use crate::nostr_manager::{NostrManager, NostrManagerError, Result};
+use crate::relays::RelayType;
+use crate::Whitenoise;
+use nostr_sdk::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, Mutex};
+
+/// The prefix relays use in `CLOSED` messages for subscriptions that require authentication.
+const AUTH_REQUIRED_PREFIX: &str = "auth-required:";
+
+/// Which relays an account authenticates to when they ask.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum RelayAuthPolicy {
+    /// Authenticate to any relay that asks.
+    Always,
+    /// Authenticate only to the relays on the account's relay lists and the relays of its groups.
+    #[default]
+    OwnRelays,
+    /// Never authenticate.
+    Never,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub enum RelayAuthState {
+    /// The policy doesn't allow authenticating to the relay.
+    Refused,
+    /// We sent an AUTH event and are waiting for the relay to accept it.
+    Authenticating,
+    Authenticated,
+    /// The relay rejected our AUTH event, with the reason it gave.
+    Failed(String),
+}
+
+/// The authentication state of an account on a relay that challenged it.
+#[derive(Debug, Clone, Serialize)]
+pub struct RelayAuthStatus {
+    pub url: String,
+    pub state: RelayAuthState,
+    pub updated_at: Timestamp,
+}
+
+/// The authentication state of every account on every relay that challenged it.
+#[derive(Debug, Clone, Default)]
+pub struct RelayAuthStates {
+    states: Arc<Mutex<HashMap<PublicKey, HashMap<RelayUrl, RelayAuthStatus>>>>,
+}
+
+impl RelayAuthStates {
+    fn set(&self, pubkey: PublicKey, url: &RelayUrl, state: RelayAuthState) {
+        self.states
+            .lock()
+            .unwrap()
+            .entry(pubkey)
+            .or_default()
+            .insert(
+                url.clone(),
+                RelayAuthStatus {
+                    url: url.to_string(),
+                    state,
+                    updated_at: Timestamp::now(),
+                },
+            );
+    }
+
+    fn get(&self, pubkey: &PublicKey, url: &RelayUrl) -> Option<RelayAuthState> {
+        self.states
+            .lock()
+            .unwrap()
+            .get(pubkey)
+            .and_then(|relays| relays.get(url))
+            .map(|status| status.state.clone())
+    }
+
+    /// Returns the authentication state of the account on each relay, sorted by URL.
+    pub fn for_account(&self, pubkey: &PublicKey) -> Vec<RelayAuthStatus> {
+        let mut statuses: Vec<RelayAuthStatus> = self
+            .states
+            .lock()
+            .unwrap()
+            .get(pubkey)
+            .map(|relays| relays.values().cloned().collect())
+            .unwrap_or_default();
+        statuses.sort_by(|a, b| a.url.cmp(&b.url));
+        statuses
+    }
+
+    pub fn remove_account(&self, pubkey: &PublicKey) {
+        self.states.lock().unwrap().remove(pubkey);
+    }
+}
+
+/// Answers the AUTH challenges sent to a single account session.
+#[derive(Clone)]
+pub(crate) struct RelayAuthenticator {
+    pubkey: PublicKey,
+    client: Client,
+    wn: Whitenoise,
+    states: RelayAuthStates,
+    /// AUTH events waiting for an OK from the relay
+    pending: Arc<Mutex<HashMap<EventId, RelayUrl>>>,
+    /// Subscriptions closed by relays because we weren't authenticated yet
+    closed: Arc<Mutex<HashMap<RelayUrl, HashSet<SubscriptionId>>>>,
+}
+
+impl RelayAuthenticator {
+    pub(crate) fn new(
+        pubkey: PublicKey,
+        client: Client,
+        wn: Whitenoise,
+        states: RelayAuthStates,
+    ) -> Self {
+        Self {
+            pubkey,
+            client,
+            wn,
+            states,
+            pending: Arc::new(Mutex::new(HashMap::new())),
+            closed: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    /// Handles the relay messages that are part of NIP-42 authentication.
+    /// Errors are logged rather than returned so they don't stop the notification handler.
+    pub(crate) async fn handle_message(&self, relay_url: &RelayUrl, message: &RelayMessage) {
+        let result = match message {
+            RelayMessage::Auth { challenge } => self.authenticate(relay_url, challenge).await,
+            RelayMessage::Ok {
+                event_id,
+                status,
+                message,
+            } => self.handle_ok(event_id, *status, message).await,
+            RelayMessage::Closed {
+                subscription_id,
+                message,
+            } if message.starts_with(AUTH_REQUIRED_PREFIX) => {
+                self.handle_auth_required(relay_url, subscription_id);
+                Ok(())
+            }
+            _ => Ok(()),
+        };
+
+        if let Err(e) = result {
+            tracing::error!(
+                target: "whitenoise::nostr_manager::auth",
+                "Error handling authentication with {} for {}: {}",
+                relay_url,
+                self.pubkey,
+                e
+            );
+        }
+    }
+
+    async fn authenticate(&self, relay_url: &RelayUrl, challenge: &str) -> Result<()> {
+        if !self.allowed(relay_url).await? {
+            tracing::debug!(
+                target: "whitenoise::nostr_manager::auth",
+                "Not authenticating to {}, the relay auth policy doesn't allow it",
+                relay_url
+            );
+            self.states
+                .set(self.pubkey, relay_url, RelayAuthState::Refused);
+            return Ok(());
+        }
+
+        let event = self
+            .client
+            .sign_event_builder(EventBuilder::auth(challenge, relay_url.clone()))
+            .await?;
+        self.pending
+            .lock()
+            .unwrap()
+            .insert(event.id, relay_url.clone());
+        self.states
+            .set(self.pubkey, relay_url, RelayAuthState::Authenticating);
+
+        self.client
+            .send_msg_to([relay_url.clone()], ClientMessage::auth(event))
+            .await?;
+        Ok(())
+    }
+
+    async fn handle_ok(&self, event_id: &EventId, status: bool, message: &str) -> Result<()> {
+        let Some(relay_url) = self.pending.lock().unwrap().remove(event_id) else {
+            // Not one of our AUTH events
+            return Ok(());
+        };
+
+        if !status {
+            tracing::warn!(
+                target: "whitenoise::nostr_manager::auth",
+                "Authentication to {} failed: {}",
+                relay_url,
+                message
+            );
+            self.states.set(
+                self.pubkey,
+                &relay_url,
+                RelayAuthState::Failed(message.to_string()),
+            );
+            return Ok(());
+        }
+
+        tracing::debug!(
+            target: "whitenoise::nostr_manager::auth",
+            "Authenticated to {} as {}",
+            relay_url,
+            self.pubkey
+        );
+        self.states
+            .set(self.pubkey, &relay_url, RelayAuthState::Authenticated);
+
+        // Send the subscriptions the relay closed before we were authenticated again
+        let closed = self
+            .closed
+            .lock()
+            .unwrap()
+            .remove(&relay_url)
+            .unwrap_or_default();
+        let subscriptions = self.client.subscriptions().await;
+        for subscription_id in closed {
+            if let Some(filters) = subscriptions.get(&subscription_id) {
+                self.client
+                    .subscribe_with_id_to(
+                        [relay_url.clone()],
+                        subscription_id,
+                        filters.clone(),
+                        None,
+                    )
+                    .await?;
+            }
+        }
+        Ok(())
+    }
+
+    fn handle_auth_required(&self, relay_url: &RelayUrl, subscription_id: &SubscriptionId) {
+        // Relays answer in order, so a subscription closed after our AUTH was accepted
+        // is refused for this pubkey and retrying it won't help
+        if self.states.get(&self.pubkey, relay_url) == Some(RelayAuthState::Authenticated) {
+            tracing::warn!(
+                target: "whitenoise::nostr_manager::auth",
+                "{} refused subscription {} although we're authenticated",
+                relay_url,
+                subscription_id
+            );
+            return;
+        }
+
+        self.closed
+            .lock()
+            .unwrap()
+            .entry(relay_url.clone())
+            .or_default()
+            .insert(subscription_id.clone());
+    }
+
+    /// Whether the account's relay auth policy allows authenticating to the relay.
+    async fn allowed(&self, relay_url: &RelayUrl) -> Result<bool> {
+        let wn = &self.wn;
+        let account = Account::find_by_pubkey(&self.pubkey, wn)
+            .await
+            .map_err(|e| NostrManagerError::AccountError(e.to_string()))?;
+
+        match account.settings.relay_auth {
+            RelayAuthPolicy::Always => Ok(true),
+            RelayAuthPolicy::Never => Ok(false),
+            RelayAuthPolicy::OwnRelays => {
+                let mut own_relays = Vec::new();
+                for relay_type in [RelayType::Nostr, RelayType::Inbox, RelayType::KeyPackage] {
+                    own_relays.extend(
+                        account
+                            .relays(relay_type, wn)
+                            .await
+                            .map_err(|e| NostrManagerError::AccountError(e.to_string()))?,
+                    );
+                }
+                let groups = account
+                    .groups(wn)
+                    .await
+                    .map_err(|e| NostrManagerError::AccountError(e.to_string()))?;
+                for group in groups {
+                    own_relays.extend(
+                        group
+                            .relays(wn)
+                            .await
+                            .map_err(|e| NostrManagerError::AccountError(e.to_string()))?,
+                    );
+                }
+
+                Ok(own_relays
+                    .iter()
+                    .any(|url| RelayUrl::parse(url).is_ok_and(|url| &url == relay_url)))
+            }
+        }
+    }
+}
+
+impl NostrManager {
+    /// Returns the authentication state of the account on each relay that challenged it.
+    pub fn relay_auth_status(&self, pubkey: &PublicKey) -> Vec<RelayAuthStatus> {
+        self.relay_auth.for_account(pubkey)
+    }
+}
@@ -1,19 +1,20 @@
-use crate::accounts::{Account, AccountError};
+use crate::accounts::{Account, AccountError, AccountSettings};
 use crate::groups::{Group, GroupError};
 use crate::invites::{Invite, InviteError, InviteState, ProcessedInvite, ProcessedInviteState};
 use crate::key_packages;
 use crate::messages::{MessageError, ProcessedMessage, ProcessedMessageState};
 use crate::nostr_manager::NostrManagerError;
-use crate::relays::RelayType;
 use crate::secrets_store;
 use crate::Whitenoise;
 use nostr_openmls::groups::GroupError as NostrOpenmlsGroupError;
+use nostr_openmls::NostrMls;
 use nostr_sdk::prelude::*;
 use serde::{Deserialize, Serialize};
-use tauri::{AppHandle, Emitter, Manager};
+use std::sync::{Arc, RwLock};
 use thiserror::Error;
 use tokio::sync::mpsc::error::SendError;
 use tokio::sync::mpsc::{self, Receiver, Sender};
+use tokio::sync::{oneshot, Mutex};
 
 #[derive(Error, Debug)]
 pub enum EventProcessorError {
@@ -43,6 +44,8 @@ pub enum EventProcessorError {
     UnparseableKey(#[from] nostr_sdk::key::Error),
     #[error("Message error: {0}")]
     MessageError(#[from] MessageError),
+    #[error("Event processor stopped before processing the batch")]
+    Stopped(#[from] oneshot::error::RecvError),
 }
 
 pub type Result<T> = std::result::Result<T, EventProcessorError>;
@@ -51,6 +54,9 @@ pub type Result<T> = std::result::Result<T, EventProcessorError>;
 pub enum ProcessableEvent {
     GiftWrap(Event),
     MlsMessage(Event),
+    /// Events processed together, e.g. the ones fetched while syncing a scope. Resolved once all of
+    /// them have been processed, with the number of them that failed. Batches can't be nested.
+    Batch(Vec<ProcessableEvent>, oneshot::Sender<usize>),
 }
 
 #[derive(Debug)]
@@ -61,19 +67,47 @@ pub struct EventProcessor {
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct MlsMessageReceivedEvent {
+    pub account_pubkey: PublicKey,
     pub group_id: Vec<u8>,
     pub event: UnsignedEvent,
 }
 
+/// The account that events are processed for, together with the client and MLS instance
+/// that hold its identity.
+#[derive(Clone)]
+pub struct ProcessingContext {
+    pub account: Account,
+    /// The account's current settings, shared with its session so that changes apply right away.
+    /// Use these rather than `account.settings`, which are the ones the session was started with.
+    pub settings: Arc<RwLock<AccountSettings>>,
+    pub client: Client,
+    pub nostr_mls: Arc<Mutex<NostrMls>>,
+}
+
+impl ProcessingContext {
+    pub fn lockdown_mode(&self) -> bool {
+        self.settings.read().unwrap().lockdown_mode
+    }
+
+    /// Returns the given content, or a placeholder if lockdown mode is enabled for the account.
+    pub fn loggable<'a>(&self, content: &'a str) -> &'a str {
+        if self.lockdown_mode() {
+            "[redacted]"
+        } else {
+            content
+        }
+    }
+}
+
 impl EventProcessor {
-    pub fn new(app_handle: AppHandle) -> Self {
+    /// Creates an event processor that processes events on behalf of the account of the given context.
+    pub fn new(wn: Whitenoise, context: ProcessingContext) -> Self {
         let (sender, receiver) = mpsc::channel(500);
         let (shutdown_tx, shutdown_rx) = mpsc::channel(1);
-        let app_handle_clone = app_handle.clone();
 
         // Spawn the processing loop
         tokio::spawn(async move {
-            Self::process_events(receiver, shutdown_rx, app_handle_clone).await;
+            Self::process_events(receiver, shutdown_rx, wn, context).await;
         });
 
         Self {
@@ -92,32 +126,41 @@ impl EventProcessor {
         Ok(())
     }
 
+    /// Processes the events as one batch and waits until they have been processed.
+    ///
+    /// Returns how many of the events failed to process. Events that can never be processed, like
+    /// messages from epochs we don't have the secrets for, are recorded as failed and don't count,
+    /// so a failure means that processing the event again may succeed.
+    pub async fn process_batch(&self, events: Vec<ProcessableEvent>) -> Result<usize> {
+        let (done, processed) = oneshot::channel();
+        self.sender
+            .send(ProcessableEvent::Batch(events, done))
+            .await?;
+        Ok(processed.await?)
+    }
+
     async fn process_events(
         mut receiver: Receiver<ProcessableEvent>,
         mut shutdown: Receiver<()>,
-        app_handle: AppHandle,
+        wn: Whitenoise,
+        context: ProcessingContext,
     ) {
         loop {
             tokio::select! {
                 Some(event) = receiver.recv() => {
                     match event {
-                        ProcessableEvent::GiftWrap(event) => {
-                            if let Err(e) = Self::process_giftwrap(&app_handle, event).await {
-                                tracing::error!(
-                                    target: "whitenoise::nostr_manager::event_processor",
-                                    "Error processing giftwrap: {}",
-                                    e
-                                );
+                        ProcessableEvent::Batch(events, done) => {
+                            let mut failed = 0;
+                            for event in events {
+                                if !Self::process_event(&wn, &context, event).await {
+                                    failed += 1;
+                                }
                             }
+                            // The waiter may have given up, which is fine
+                            let _ = done.send(failed);
                         }
-                        ProcessableEvent::MlsMessage(event) => {
-                            if let Err(e) = Self::process_mls_message(&app_handle, event).await {
-                                tracing::error!(
-                                    target: "whitenoise::nostr_manager::event_processor",
-                                    "Error processing MLS message: {}",
-                                    e
-                                );
-                            }
+                        event => {
+                            Self::process_event(&wn, &context, event).await;
                         }
                     }
                 }
@@ -133,6 +176,37 @@ impl EventProcessor {
         }
     }
 
+    /// Processes a single event and logs any error. Returns whether it was processed successfully.
+    async fn process_event(
+        wn: &Whitenoise,
+        context: &ProcessingContext,
+        event: ProcessableEvent,
+    ) -> bool {
+        let result = match event {
+            ProcessableEvent::GiftWrap(event) => Self::process_giftwrap(wn, context, event)
+                .await
+                .map_err(|e| format!("Error processing giftwrap: {}", e)),
+            ProcessableEvent::MlsMessage(event) => Self::process_mls_message(wn, context, event)
+                .await
+                .map_err(|e| format!("Error processing MLS message: {}", e)),
+            ProcessableEvent::Batch(events, done) => {
+                let _ = done.send(events.len());
+                Err("Batches can't be nested".to_string())
+            }
+        };
+        match result {
+            Ok(()) => true,
+            Err(e) => {
+                tracing::error!(
+                    target: "whitenoise::nostr_manager::event_processor",
+                    "{}",
+                    e
+                );
+                false
+            }
+        }
+    }
+
     pub async fn clear_queue(&self) -> Result<()> {
         // Send shutdown signal
         if let Err(e) = self.shutdown.send(()).await {
@@ -145,21 +219,26 @@ impl EventProcessor {
         Ok(())
     }
 
-    async fn process_giftwrap(app_handle: &AppHandle, event: Event) -> Result<()> {
-        let wn = app_handle.state::<Whitenoise>();
-        let active_account = Account::get_active(wn.clone()).await?;
-        let keys = active_account.keys(wn.clone())?;
-        if let Ok(unwrapped) = extract_rumor(&keys, &event).await {
+    async fn process_giftwrap(
+        wn: &Whitenoise,
+        context: &ProcessingContext,
+        event: Event,
+    ) -> Result<()> {
+        let signer = context
+            .client
+            .signer()
+            .await
+            .map_err(NostrManagerError::from)?;
+        if let Ok(unwrapped) = extract_rumor(&signer, &event).await {
             match unwrapped.rumor.kind {
                 Kind::MlsWelcome => {
-                    Self::process_invite(app_handle, active_account, event, unwrapped.rumor)
-                        .await?;
+                    Self::process_invite(wn, context, event, unwrapped.rumor).await?;
                 }
                 Kind::PrivateDirectMessage => {
                     tracing::debug!(
                         target: "whitenoise::nostr_manager::event_processor",
-                        "Received private direct message: {:?}",
-                        unwrapped.rumor
+                        "Received private direct message: {}",
+                        context.loggable(&format!("{:?}", unwrapped.rumor))
                     );
                 }
                 _ => {
@@ -175,16 +254,16 @@ impl EventProcessor {
     }
 
     async fn process_invite(
-        app_handle: &AppHandle,
-        account: Account,
+        wn: &Whitenoise,
+        context: &ProcessingContext,
         outer_event: Event,
         rumor_event: UnsignedEvent,
     ) -> Result<()> {
-        let wn = app_handle.state::<Whitenoise>();
+        let account = &context.account;
 
         // Check to see if the invite has already been processed
         let processed_invite =
-            ProcessedInvite::find_by_invite_event_id(outer_event.id, wn.clone()).await?;
+            ProcessedInvite::find_by_invite_event_id(outer_event.id, &account.pubkey, wn).await?;
         if processed_invite.is_some() {
             return Ok(());
         }
@@ -202,18 +281,17 @@ impl EventProcessor {
                     rumor_event.id.unwrap(),
                     ProcessedInviteState::Failed,
                     error_string.clone(),
-                    wn.clone(),
+                    &account.pubkey,
+                    wn,
                 )
                 .await?;
                 tracing::error!(target: "whitenoise::nostr_manager::event_processor", "{}", error_string);
-                app_handle
-                    .emit("invite_failed_to_process", processed_invite)
-                    .map_err(NostrManagerError::TauriError)?;
+                wn.emit("invite_failed_to_process", processed_invite);
                 return Ok(());
             }
 
             {
-                let nostr_mls = wn.nostr_mls.lock().await;
+                let nostr_mls = context.nostr_mls.lock().await;
                 welcome_preview = nostr_mls.preview_welcome_event(hex_content.unwrap());
             }
 
@@ -227,13 +305,12 @@ impl EventProcessor {
                     rumor_event.id.unwrap(),
                     ProcessedInviteState::Failed,
                     error_string.clone(),
-                    wn.clone(),
+                    &account.pubkey,
+                    wn,
                 )
                 .await?;
                 tracing::error!(target: "whitenoise::nostr_manager::event_processor", "{}", error_string);
-                app_handle
-                    .emit("invite_failed_to_process", processed_invite)
-                    .map_err(NostrManagerError::TauriError)?;
+                wn.emit("invite_failed_to_process", processed_invite);
                 return Ok(());
             }
         }
@@ -261,14 +338,15 @@ impl EventProcessor {
             outer_event_id: outer_event.id.to_string(),
         };
 
-        invite.save(wn.clone()).await?;
+        invite.save(wn).await?;
 
         ProcessedInvite::create_with_state_and_reason(
             outer_event.id,
             rumor_event.id.unwrap(),
             ProcessedInviteState::Processed,
             "".to_string(),
-            wn.clone(),
+            &account.pubkey,
+            wn,
         )
         .await?;
 
@@ -280,28 +358,33 @@ impl EventProcessor {
             })
             .and_then(|tag| tag.content());
 
-        app_handle
-            .emit("invite_processed", invite)
-            .map_err(NostrManagerError::TauriError)?;
-
-        let key_package_relays: Vec<String> = if cfg!(dev) {
-            vec!["ws://localhost:8080".to_string()]
-        } else {
-            account.relays(RelayType::KeyPackage, wn.clone()).await?
-        };
+        wn.emit("invite_processed", invite);
 
+        // For now we don't delete the used key package from MLS storage, only from relays.
+        // Lockdown mode doesn't publish to key package relays, so the used key package stays
+        // there until lockdown mode is turned off and a new one is published.
         if let Some(key_package_event_id) = key_package_event_id {
-            key_packages::delete_key_package_from_relays(
+            match key_packages::rotate_key_package(
                 &EventId::parse(key_package_event_id).unwrap(),
-                &key_package_relays,
-                false, // For now we don't want to delete the key packages from MLS storage
-                wn.clone(),
+                context,
+                wn,
             )
-            .await?;
-            tracing::debug!(target: "whitenoise::nostr_manager::event_processor", "Deleted used key package from relays");
-
-            key_packages::publish_key_package(wn.clone()).await?;
-            tracing::debug!(target: "whitenoise::nostr_manager::event_processor", "Published new key package");
+            .await
+            {
+                Ok(()) => {
+                    tracing::debug!(target: "whitenoise::nostr_manager::event_processor", "Replaced used key package with a new one");
+                }
+                Err(key_packages::KeyPackageError::NostrError(
+                    NostrManagerError::LockdownMode(_),
+                )) => {
+                    tracing::warn!(
+                        target: "whitenoise::nostr_manager::event_processor",
+                        "Not replacing used key package {} in lockdown mode",
+                        key_package_event_id
+                    );
+                }
+                Err(e) => return Err(e.into()),
+            }
         }
 
         Ok(())
@@ -310,7 +393,7 @@ impl EventProcessor {
     // TODO: Implement private direct message processing, maybe...
     #[allow(dead_code)]
     async fn process_private_direct_message(
-        _app_handle: &AppHandle,
+        _wn: &Whitenoise,
         _outer_event: Event,
         inner_event: UnsignedEvent,
     ) -> Result<()> {
@@ -323,11 +406,16 @@ impl EventProcessor {
         Ok(())
     }
 
-    async fn process_mls_message(app_handle: &AppHandle, event: Event) -> Result<()> {
-        let wn = app_handle.state::<Whitenoise>();
+    async fn process_mls_message(
+        wn: &Whitenoise,
+        context: &ProcessingContext,
+        event: Event,
+    ) -> Result<()> {
+        let account_pubkey = &context.account.pubkey;
 
         // Check to see if the event has already been processed
-        let processed_event = ProcessedMessage::find_by_event_id(event.id, wn.clone()).await?;
+        let processed_event =
+            ProcessedMessage::find_by_event_id(event.id, account_pubkey, wn).await?;
         if processed_event.is_some() {
             return Ok(());
         }
@@ -339,48 +427,37 @@ impl EventProcessor {
             .and_then(|tag| tag.content())
             .unwrap();
 
-        let group = Group::get_by_nostr_group_id(group_id, wn.clone()).await?;
+        let group = Group::get_by_nostr_group_id(group_id, account_pubkey, wn).await?;
 
         // TODO: Need to figure out how to reprocess events that fail because a commit arrives out of order
 
-        let nostr_keys = match secrets_store::get_export_secret_keys_for_group(
-            group.mls_group_id.clone(),
-            group.epoch,
-            wn.data_dir.as_path(),
-        ) {
-            Ok(keys) => keys,
-            Err(_) => {
-                tracing::debug!(
+        // Messages from epochs before we joined, or that we don't have the secret for, can never be
+        // decrypted. They are recorded as failed so that catching up doesn't retry them forever.
+        let decrypted_content = match Self::decrypt_mls_message(context, &group, &event, wn).await {
+            Ok(decrypted_content) => decrypted_content,
+            Err(e) => {
+                let error_string = format!("Failed to decrypt message: {}", e);
+                tracing::warn!(
                     target: "whitenoise::commands::groups::fetch_mls_messages",
-                    "No export secret keys found, fetching from nostr_openmls",
+                    "{}",
+                    error_string
                 );
-                // We need to get the export secret for the group from nostr_openmls
-                let nostr_mls = wn.nostr_mls.lock().await;
-                let (export_secret_hex, epoch) = nostr_mls
-                    .export_secret_as_hex_secret_key_and_epoch(group.mls_group_id.clone())?;
-
-                // Store the export secret key in the secrets store
-                secrets_store::store_mls_export_secret(
-                    group.mls_group_id.clone(),
-                    epoch,
-                    export_secret_hex.clone(),
-                    wn.data_dir.as_path(),
-                )?;
-
-                Keys::parse(&export_secret_hex)?
+                ProcessedMessage::create_with_state_and_reason(
+                    event.id,
+                    None,
+                    ProcessedMessageState::Failed,
+                    error_string,
+                    account_pubkey,
+                    wn,
+                )
+                .await?;
+                return Ok(());
             }
         };
 
-        // Decrypt events using export secret key
-        let decrypted_content = nip44::decrypt_to_bytes(
-            nostr_keys.secret_key(),
-            &nostr_keys.public_key(),
-            &event.content,
-        )?;
-
         let message_vec;
         {
-            let nostr_mls = wn.nostr_mls.lock().await;
+            let nostr_mls = context.nostr_mls.lock().await;
 
             // TODO: This only handles application messages for now. We need to handle commits and proposals
             match nostr_mls
@@ -401,7 +478,8 @@ impl EventProcessor {
                                     None,
                                     ProcessedMessageState::Failed,
                                     "Cannot decrypt own messages".to_string(),
-                                    wn.clone(),
+                                    account_pubkey,
+                                    wn,
                                 )
                                 .await?;
                             }
@@ -419,7 +497,8 @@ impl EventProcessor {
                                 None,
                                 ProcessedMessageState::Failed,
                                 error_string,
-                                wn.clone(),
+                                account_pubkey,
+                                wn,
                             )
                             .await?;
                         }
@@ -437,16 +516,13 @@ impl EventProcessor {
                 tracing::debug!(
                     target: "whitenoise::commands::groups::fetch_mls_messages",
                     "Deserialized JSON message: {}",
-                    json_value
+                    context.loggable(&json_value.to_string())
                 );
                 let json_str = json_value.to_string();
                 json_event = UnsignedEvent::from_json(&json_str).unwrap();
 
-                if !group
-                    .members(wn.clone())
-                    .await?
-                    .contains(&json_event.pubkey)
-                {
+                let members = group.members_in(&*context.nostr_mls.lock().await)?;
+                if !members.contains(&json_event.pubkey) {
                     tracing::error!(
                         target: "whitenoise::commands::groups::fetch_mls_messages",
                         "Message from non-member: {:?}",
@@ -457,24 +533,18 @@ impl EventProcessor {
                         Some(json_event.id.unwrap()),
                         ProcessedMessageState::Failed,
                         "Message from non-member".to_string(),
-                        wn.clone(),
+                        account_pubkey,
+                        wn,
                     )
                     .await?;
                     return Ok(());
                 }
 
                 group
-                    .add_message(
-                        event.id.to_string(),
-                        json_event.clone(),
-                        wn.clone(),
-                        app_handle.clone(),
-                    )
+                    .add_message(event.id.to_string(), json_event.clone(), wn)
                     .await?;
 
-                app_handle
-                    .emit("mls_message_processed", (group.clone(), json_event.clone()))
-                    .expect("Couldn't emit event");
+                wn.emit("mls_message_processed", (group.clone(), json_event.clone()));
             }
             Err(e) => {
                 tracing::error!(
@@ -488,25 +558,68 @@ impl EventProcessor {
                     None,
                     ProcessedMessageState::Failed,
                     error_string.clone(),
-                    wn.clone(),
+                    account_pubkey,
+                    wn,
                 )
                 .await?;
                 return Ok(());
             }
         }
 
-        app_handle
-            .emit(
-                "mls_message_received",
-                MlsMessageReceivedEvent {
-                    group_id: group.mls_group_id.clone(),
-                    event: json_event.clone(),
-                },
-            )
-            .map_err(NostrManagerError::TauriError)?;
+        wn.emit(
+            "mls_message_received",
+            MlsMessageReceivedEvent {
+                account_pubkey: *account_pubkey,
+                group_id: group.mls_group_id.clone(),
+                event: json_event.clone(),
+            },
+        );
         Ok(())
     }
 
+    /// Decrypts the outer NIP-44 layer of a group message with the group's export secret.
+    async fn decrypt_mls_message(
+        context: &ProcessingContext,
+        group: &Group,
+        event: &Event,
+        wn: &Whitenoise,
+    ) -> Result<Vec<u8>> {
+        let nostr_keys = match secrets_store::get_export_secret_keys_for_group(
+            group.mls_group_id.clone(),
+            group.epoch,
+            wn.data_dir.as_path(),
+        ) {
+            Ok(keys) => keys,
+            Err(_) => {
+                tracing::debug!(
+                    target: "whitenoise::commands::groups::fetch_mls_messages",
+                    "No export secret keys found, fetching from nostr_openmls",
+                );
+                // We need to get the export secret for the group from nostr_openmls
+                let nostr_mls = context.nostr_mls.lock().await;
+                let (export_secret_hex, epoch) = nostr_mls
+                    .export_secret_as_hex_secret_key_and_epoch(group.mls_group_id.clone())?;
+
+                // Store the export secret key in the secrets store
+                secrets_store::store_mls_export_secret(
+                    group.mls_group_id.clone(),
+                    epoch,
+                    export_secret_hex.clone(),
+                    wn.data_dir.as_path(),
+                )?;
+
+                Keys::parse(&export_secret_hex)?
+            }
+        };
+
+        // Decrypt events using export secret key
+        Ok(nip44::decrypt_to_bytes(
+            nostr_keys.secret_key(),
+            &nostr_keys.public_key(),
+            &event.content,
+        )?)
+    }
+
     // async fn schedule_retry(app_handle: &AppHandle, event: Event, retry_count: u32) -> Result<()> {
     //     // Give up after 5 retries
     //     if retry_count >= 5 {
@@ -8,26 +8,47 @@ use crate::nostr_manager::{NostrManager, NostrManagerError, Result};
 use nostr_sdk::prelude::*;
 
 impl NostrManager {
-    pub async fn fetch_for_user(
+    /// A short-lived client for relays that aren't meant to stay in a pool. It shares the database
+    /// and follows the proxy settings. Shut it down once done with it.
+    pub(crate) async fn temporary_client(&self) -> Client {
+        Client::builder()
+            .database(self.default_client.database().clone())
+            .opts(self.settings.lock().await.proxy.client_options())
+            .build()
+    }
+
+    /// Fetches events from relays that aren't meant to stay in a pool, through a temporary client
+    /// that is shut down afterwards. Relays refused by the proxy settings are skipped.
+    pub async fn fetch_events_from_temporary_relays(
         &self,
-        pubkey: PublicKey,
-        last_synced: Timestamp,
-        group_ids: Vec<String>, // Nostr group ids
-    ) -> Result<()> {
-        self.fetch_user_metadata(pubkey).await?;
-        self.fetch_contacts().await?;
-        self.fetch_user_relays(pubkey).await?;
-        self.fetch_user_inbox_relays(pubkey).await?;
-        self.fetch_user_key_package_relays(pubkey).await?;
-        self.fetch_user_key_packages(pubkey).await?;
-        self.fetch_user_giftwrapped_events(pubkey).await?;
-        self.fetch_group_messages(last_synced, group_ids).await?;
-        Ok(())
+        relays: &[String],
+        filters: Vec<Filter>,
+    ) -> Result<Events> {
+        let client = self.temporary_client().await;
+
+        let result: Result<Events> = async {
+            let added = self.add_relays_to(&client, relays, false).await?;
+            if added.is_empty() {
+                return Ok(Events::new(&filters));
+            }
+            client.connect().await;
+            Ok(client.fetch_events(filters, self.timeout().await?).await?)
+        }
+        .await;
+
+        if let Err(e) = client.shutdown().await {
+            tracing::warn!(
+                target: "whitenoise::nostr_manager::fetch",
+                "Error shutting down temporary client: {}",
+                e
+            );
+        }
+        result
     }
 
     pub async fn fetch_user_metadata(&self, pubkey: PublicKey) -> Result<Option<Metadata>> {
         match self
-            .client
+            .client()
             .fetch_metadata(pubkey, self.timeout().await?)
             .await
         {
@@ -41,7 +62,7 @@ impl NostrManager {
         let filter = Filter::new().author(pubkey).kind(Kind::RelayList).limit(1);
 
         let events = self
-            .client
+            .client()
             .fetch_events(vec![filter], self.timeout().await?)
             .await
             .map_err(NostrManagerError::from)?;
@@ -55,7 +76,7 @@ impl NostrManager {
             .kind(Kind::InboxRelays)
             .limit(1);
         let events = self
-            .client
+            .client()
             .fetch_events(vec![filter], self.timeout().await?)
             .await
             .map_err(NostrManagerError::from)?;
@@ -69,7 +90,7 @@ impl NostrManager {
             .kind(Kind::MlsKeyPackageRelays)
             .limit(1);
         let events = self
-            .client
+            .client()
             .fetch_events(vec![filter], self.timeout().await?)
             .await
             .map_err(NostrManagerError::from)?;
@@ -80,7 +101,7 @@ impl NostrManager {
     pub async fn fetch_user_key_packages(&self, pubkey: PublicKey) -> Result<Events> {
         let filter = Filter::new().author(pubkey).kind(Kind::MlsKeyPackage);
         let events = self
-            .client
+            .client()
             .fetch_events(vec![filter], self.timeout().await?)
             .await
             .map_err(NostrManagerError::from)?;
@@ -91,17 +112,22 @@ impl NostrManager {
         tracing::debug!(
             target: "whitenoise::nostr_client::fetch_contacts",
             "Fetching contacts for: {:?}",
-            self.client.signer().await?.get_public_key().await.unwrap().to_hex()
+            self.client().signer().await?.get_public_key().await.unwrap().to_hex()
         );
         let contacts_pubkeys = self
-            .client
+            .client()
             .get_contact_list_public_keys(self.timeout().await?)
             .await?;
 
         let filter = Filter::new().kind(Kind::Metadata).authors(contacts_pubkeys);
-        let database_contacts = self.client.database().query(vec![filter.clone()]).await?;
+        let database_contacts = self.client().database().query(vec![filter.clone()]).await?;
+
+        if self.lockdown_mode() {
+            return Ok(database_contacts.into_iter().collect());
+        }
+
         let fetched_contacts = self
-            .client
+            .client()
             .fetch_events(vec![filter], self.timeout().await?)
             .await?;
 
@@ -109,25 +135,6 @@ impl NostrManager {
         Ok(contacts.into_iter().collect())
     }
 
-    async fn fetch_user_giftwrapped_events(&self, pubkey: PublicKey) -> Result<Vec<Event>> {
-        let filter = Filter::new().kind(Kind::GiftWrap).pubkey(pubkey);
-        let stored_events = self.client.database().query(vec![filter.clone()]).await?;
-        let fetched_events = self
-            .client
-            .fetch_events(vec![filter], self.timeout().await?)
-            .await?;
-
-        let events = stored_events.merge(fetched_events);
-        for event in events.iter() {
-            let processor = self.event_processor.lock().await;
-            processor
-                .queue_event(ProcessableEvent::GiftWrap(event.clone()))
-                .await
-                .map_err(|e| NostrManagerError::FailedToQueueEvent(e.to_string()))?;
-        }
-        Ok(events.into_iter().collect())
-    }
-
     pub async fn fetch_group_messages(
         &self,
         last_synced: Timestamp,
@@ -139,16 +146,16 @@ impl NostrManager {
             .since(last_synced)
             .until(Timestamp::now());
 
-        let stored_events = self.client.database().query(vec![filter.clone()]).await?;
+        let stored_events = self.client().database().query(vec![filter.clone()]).await?;
         let fetched_events = self
-            .client
+            .client()
             .fetch_events(vec![filter], self.timeout().await?)
             .await?;
 
         let events = stored_events.merge(fetched_events);
 
+        let processor = self.active_event_processor()?;
         for event in events.iter() {
-            let processor = self.event_processor.lock().await;
             processor
                 .queue_event(ProcessableEvent::MlsMessage(event.clone()))
                 .await
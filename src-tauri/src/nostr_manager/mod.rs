@@ -1,19 +1,33 @@
 use crate::accounts::Account;
-use crate::nostr_manager::event_processor::EventProcessor;
+use crate::nostr_manager::auth::RelayAuthStates;
+use crate::nostr_manager::network::NetworkProfile;
+use crate::nostr_manager::proxy::ProxySettings;
+use crate::nostr_manager::relay_status::RelayHealthTracker;
+use crate::nostr_manager::routing::RelayRoutes;
+use crate::nostr_manager::sessions::AccountSession;
+use crate::nostr_manager::sync::NegentropySupport;
+use crate::sync_cursors::SyncCursorError;
 use crate::types::NostrEncryptionMethod;
 use crate::Whitenoise;
 use nostr_sdk::prelude::*;
+use std::collections::{HashMap, HashSet};
 use std::path::PathBuf;
-use std::sync::Arc;
+use std::sync::{Arc, RwLock};
 use std::time::Duration;
-use tauri::{AppHandle, Manager};
 use thiserror::Error;
 use tokio::{spawn, sync::Mutex};
 
+pub mod auth;
 pub mod event_processor;
 pub mod fetch;
+pub mod network;
+pub mod proxy;
 pub mod query;
+pub mod relay_status;
+pub mod remote_signer;
+pub mod routing;
 pub mod search;
+pub mod sessions;
 pub mod subscriptions;
 pub mod sync;
 
@@ -29,52 +43,74 @@ pub enum NostrManagerError {
     Signer(#[from] nostr_sdk::signer::SignerError),
     #[error("Error with secrets store: {0}")]
     SecretsStoreError(String),
-    #[error("Tauri error: {0}")]
-    TauriError(#[from] tauri::Error),
     #[error("Failed to queue event: {0}")]
     FailedToQueueEvent(String),
     #[error("Failed to shutdown event processor: {0}")]
     FailedToShutdownEventProcessor(String),
+    #[error("Not allowed in lockdown mode: {0}")]
+    LockdownMode(String),
+    #[error("Remote signer error: {0}")]
+    RemoteSigner(String),
+    #[error("Account error: {0}")]
+    AccountError(String),
+    #[error("Invalid relay URL: {0}")]
+    InvalidRelayUrl(String),
+    #[error("Proxy error: {0}")]
+    Proxy(String),
+    #[error("Network profile error: {0}")]
+    NetworkProfile(String),
+    #[error("Direct connection refused: {0}")]
+    DirectConnectionRefused(String),
+    #[error("Sync cursor error: {0}")]
+    SyncCursor(#[from] SyncCursorError),
+    #[error("Not caught up on: {0}")]
+    SyncIncomplete(String),
+    #[error("Invalid input: {0}")]
+    InvalidInput(String),
 }
 
-#[derive(Debug, Clone)]
+/// The kind of relay an event is being published to.
+/// Used to decide whether a publish is allowed while lockdown mode is enabled.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PublishTarget {
+    /// The relays of an MLS group (kind 445 messages)
+    Group,
+    /// A user's NIP-17 inbox relays (gift-wrapped welcomes)
+    Inbox,
+    /// A user's key package relays (kind 443 and deletions of them)
+    KeyPackage,
+    /// A user's NIP-65 relays or the default relay pool
+    Outbox,
+}
+
+#[derive(Debug, Clone, Default)]
 pub struct NostrManagerSettings {
-    pub timeout: Duration,
-    pub relays: Vec<String>,
+    pub network: NetworkProfile,
+    pub proxy: ProxySettings,
 }
 
 #[derive(Debug, Clone)]
 pub struct NostrManager {
-    pub client: Client,
+    /// The client used while no account is active. Its database is shared by all account sessions.
+    default_client: Client,
     pub settings: Arc<Mutex<NostrManagerSettings>>,
-    event_processor: Arc<Mutex<EventProcessor>>,
+    sessions: Arc<Mutex<HashMap<PublicKey, AccountSession>>>,
+    active_session: Arc<RwLock<Option<AccountSession>>>,
+    relay_health: RelayHealthTracker,
+    relay_routes: RelayRoutes,
+    relay_auth: RelayAuthStates,
+    negentropy_support: NegentropySupport,
 }
 
-impl Default for NostrManagerSettings {
-    fn default() -> Self {
-        let mut relays = vec![];
-        if cfg!(dev) {
-            relays.push("ws://localhost:8080".to_string());
-            relays.push("wss://purplepag.es".to_string());
-            // relays.push("wss://nos.lol".to_string());
-        } else {
-            relays.push("wss://relay.damus.io".to_string());
-            relays.push("wss://purplepag.es".to_string());
-            relays.push("wss://relay.primal.net".to_string());
-            relays.push("wss://nostr.oxtr.dev".to_string());
-        }
-
-        Self {
-            timeout: Duration::from_secs(5),
-            relays,
-        }
-    }
-}
 pub type Result<T> = std::result::Result<T, NostrManagerError>;
 
 impl NostrManager {
-    pub async fn new(db_path: PathBuf, app_handle: AppHandle) -> Result<Self> {
-        let opts = Options::default();
+    pub async fn new(db_path: PathBuf) -> Result<Self> {
+        let settings = NostrManagerSettings {
+            network: NetworkProfile::load(&db_path)?,
+            proxy: ProxySettings::load(&db_path)?,
+        };
+        let opts = settings.proxy.client_options();
 
         // Initialize the client with the appropriate database based on platform
         let client = {
@@ -94,33 +130,127 @@ impl NostrManager {
             }
         };
 
-        let settings = NostrManagerSettings::default();
+        let relays = settings.network.default_relays.clone();
+        let nostr = Self {
+            default_client: client,
+            settings: Arc::new(Mutex::new(settings)),
+            sessions: Arc::new(Mutex::new(HashMap::new())),
+            active_session: Arc::new(RwLock::new(None)),
+            relay_health: RelayHealthTracker::default(),
+            relay_routes: RelayRoutes::default(),
+            relay_auth: RelayAuthStates::default(),
+            negentropy_support: NegentropySupport::default(),
+        };
+
+        // Add and connect to the default relays
+        nostr
+            .add_relays_to(&nostr.default_client, &relays, false)
+            .await?;
+        nostr.default_client.connect().await;
+
+        Ok(nostr)
+    }
+
+    /// Whether lockdown mode is enabled for the active account. Accounts synced in the background
+    /// follow their own setting, see `AccountSession::lockdown_mode`.
+    ///
+    /// In lockdown mode we:
+    /// - show no notification previews
+    /// - make no unsolicited fetches of contact metadata or profile images
+    /// - publish only to group and inbox relays, so key packages used for invites aren't replaced
+    /// - refuse NIP-04 encryption and decryption
+    /// - redact message content in logs
+    pub fn lockdown_mode(&self) -> bool {
+        self.active_session
+            .read()
+            .unwrap()
+            .as_ref()
+            .is_some_and(|session| session.lockdown_mode())
+    }
 
-        // Add the default relays
-        for relay in &settings.relays {
-            client.add_relay(relay).await?;
+    /// Returns the given content, or a placeholder if lockdown mode is enabled.
+    /// Use this whenever message content or decrypted payloads are written to the logs.
+    pub fn loggable<'a>(&self, content: &'a str) -> &'a str {
+        if self.lockdown_mode() {
+            "[redacted]"
+        } else {
+            content
         }
+    }
 
-        // Connect to the default relays
-        client.connect().await;
+    /// Checks that publishing to the given kind of relay is allowed for the active account.
+    ///
+    /// # Errors
+    /// Returns `NostrManagerError::LockdownMode` if lockdown mode is enabled and the
+    /// target is anything other than group or inbox relays.
+    pub fn check_publish_allowed(&self, target: PublishTarget) -> Result<()> {
+        Self::check_publish_allowed_with(self.lockdown_mode(), target)
+    }
 
-        let event_processor = Arc::new(Mutex::new(EventProcessor::new(app_handle)));
+    /// Checks the lockdown policy for an identity other than the active one,
+    /// e.g. an account that is being synced in the background.
+    pub fn check_publish_allowed_with(lockdown_mode: bool, target: PublishTarget) -> Result<()> {
+        if lockdown_mode && !matches!(target, PublishTarget::Group | PublishTarget::Inbox) {
+            return Err(NostrManagerError::LockdownMode(format!(
+                "publishing to {:?} relays is disabled",
+                target
+            )));
+        }
+        Ok(())
+    }
 
-        Ok(Self {
-            client,
-            settings: Arc::new(Mutex::new(settings)),
-            event_processor,
-        })
+    /// Publishes a signed event to the given relays after checking the lockdown policy.
+    pub async fn publish_event_to(
+        &self,
+        target: PublishTarget,
+        urls: Vec<String>,
+        event: Event,
+    ) -> Result<Output<EventId>> {
+        self.check_publish_allowed(target)?;
+        let output = self.client().send_event_to(urls, event).await?;
+        self.relay_health.record_output(&output);
+        Ok(output)
+    }
+
+    /// Builds, signs and publishes an event to the given relays after checking the lockdown policy.
+    pub async fn publish_event_builder_to(
+        &self,
+        target: PublishTarget,
+        urls: Vec<String>,
+        builder: EventBuilder,
+    ) -> Result<Output<EventId>> {
+        self.check_publish_allowed(target)?;
+        let output = self.client().send_event_builder_to(urls, builder).await?;
+        self.relay_health.record_output(&output);
+        Ok(output)
+    }
+
+    /// Publishes a signed event to all relays in the pool after checking the lockdown policy.
+    pub async fn publish_event(&self, event: Event) -> Result<Output<EventId>> {
+        self.check_publish_allowed(PublishTarget::Outbox)?;
+        let output = self.client().send_event(event).await?;
+        self.relay_health.record_output(&output);
+        Ok(output)
+    }
+
+    /// Strips profile images from contact metadata when lockdown mode is enabled
+    /// so that the frontend doesn't load them from third-party servers.
+    pub fn sanitize_contact_metadata(&self, mut metadata: Metadata) -> Metadata {
+        if self.lockdown_mode() {
+            metadata.picture = None;
+            metadata.banner = None;
+        }
+        metadata
     }
 
     pub async fn timeout(&self) -> Result<Duration> {
         let guard = self.settings.lock().await;
-        Ok(guard.timeout)
+        Ok(guard.network.timeout())
     }
 
     pub async fn relays(&self) -> Result<Vec<String>> {
         let guard = self.settings.lock().await;
-        Ok(guard.relays.clone())
+        Ok(guard.network.default_relays.clone())
     }
 
     /// Extracts welcome events from a list of giftwrapped events.
@@ -135,67 +265,75 @@ impl NostrManager {
     /// # Returns
     ///
     /// A vector of tuples containing the gift-wrap event id and the inner welcome event (the gift wrap rumor event)
-    async fn extract_invite_events(&self, gw_events: Vec<Event>) -> Vec<(EventId, UnsignedEvent)> {
+    ///
+    /// # Errors
+    /// Returns an error if the client has no signer.
+    async fn extract_invite_events(
+        &self,
+        gw_events: Vec<Event>,
+    ) -> Result<Vec<(EventId, UnsignedEvent)>> {
+        let signer = self.client().signer().await?;
         let mut invite_events: Vec<(EventId, UnsignedEvent)> = Vec::new();
 
         for event in gw_events {
-            if let Ok(unwrapped) = extract_rumor(&self.client.signer().await.unwrap(), &event).await
-            {
+            if let Ok(unwrapped) = extract_rumor(&signer, &event).await {
                 if unwrapped.rumor.kind == Kind::MlsWelcome {
                     invite_events.push((event.id, unwrapped.rumor));
                 }
             }
         }
 
-        invite_events
+        Ok(invite_events)
     }
 
-    pub async fn set_nostr_identity(
-        &self,
-        account: &Account,
-        wn: tauri::State<'_, Whitenoise>,
-        app_handle: &tauri::AppHandle,
-    ) -> Result<()> {
+    /// Makes the given account the active identity.
+    ///
+    /// Switching to an account whose session is cached only swaps the active session. The first time
+    /// a session becomes active we also connect to the user's relays, set up the subscriptions only
+    /// the active account needs and catch up on events since the account's sync cursors.
+    pub async fn set_nostr_identity(&self, account: &Account, wn: &Whitenoise) -> Result<()> {
         tracing::debug!(
             target: "whitenoise::nostr_manager::set_nostr_identity",
             "Starting Nostr identity update for {}",
             account.pubkey
         );
 
-        let keys = account
-            .keys(wn.clone())
-            .map_err(|e| NostrManagerError::SecretsStoreError(e.to_string()))?;
+        let first_activation = self.activate_session(account, wn).await?;
 
-        // Shutdown existing event processor
-        self.event_processor
-            .lock()
-            .await
-            .clear_queue()
-            .await
-            .map_err(|e| NostrManagerError::FailedToShutdownEventProcessor(e.to_string()))?;
-
-        // Reset the client
-        self.client.reset().await.map_err(NostrManagerError::from)?;
-
-        // Set the new signer
-        self.client.set_signer(keys.clone()).await;
+        // Keep the other accounts synced in the background
+        let wn_background = wn.clone();
+        let active_pubkey = account.pubkey;
+        spawn(async move {
+            if let Err(e) = wn_background
+                .nostr
+                .start_background_sessions(&active_pubkey, &wn_background)
+                .await
+            {
+                tracing::error!(
+                    target: "whitenoise::nostr_manager::set_nostr_identity",
+                    "Error starting background sessions: {}",
+                    e
+                );
+            }
+        });
 
-        // Add the default relays
-        for relay in self.relays().await? {
-            self.client.add_relay(relay).await?;
+        if !first_activation {
+            tracing::debug!(
+                target: "whitenoise::nostr_manager::set_nostr_identity",
+                "Switched to cached session for {}",
+                account.pubkey
+            );
+            return Ok(());
         }
 
-        // Connect to the default relays
-        self.client.connect().await;
+        let client = self.client();
 
-        // We only want to connect to user relays in release mode
-        if !cfg!(dev) {
+        if self.network_profile().await.use_user_relays {
             // Add the new user's relays
             // TODO: We should query first and only fetch if we don't have them
-            let relays = self.fetch_user_relays(keys.public_key()).await?;
-            for relay in relays.iter() {
-                self.client.add_relay(relay).await?;
-                self.client.connect_relay(relay).await?;
+            let relays = self.fetch_user_relays(account.pubkey).await?;
+            for relay in self.add_relays_to(&client, &relays, false).await?.iter() {
+                client.connect_relay(relay).await?;
                 tracing::debug!(
                     target: "whitenoise::nostr_manager::set_nostr_identity",
                     "Connected to user relay: {}",
@@ -205,10 +343,13 @@ impl NostrManager {
 
             // Add the new user's inbox relays
             // TODO: We should query first and only fetch if we don't have them
-            let inbox_relays = self.fetch_user_inbox_relays(keys.public_key()).await?;
-            for relay in inbox_relays.iter() {
-                self.client.add_read_relay(relay).await?;
-                self.client.connect_relay(relay).await?;
+            let inbox_relays = self.fetch_user_inbox_relays(account.pubkey).await?;
+            for relay in self
+                .add_relays_to(&client, &inbox_relays, true)
+                .await?
+                .iter()
+            {
+                client.connect_relay(relay).await?;
                 tracing::debug!(
                     target: "whitenoise::nostr_manager::set_nostr_identity",
                     "Connected to user inbox relay: {}",
@@ -218,12 +359,13 @@ impl NostrManager {
 
             // Add the new user's key package relays
             // TODO: We should query first and only fetch if we don't have them
-            let key_package_relays = self
-                .fetch_user_key_package_relays(keys.public_key())
-                .await?;
-            for relay in key_package_relays.iter() {
-                self.client.add_relay(relay).await?;
-                self.client.connect_relay(relay).await?;
+            let key_package_relays = self.fetch_user_key_package_relays(account.pubkey).await?;
+            for relay in self
+                .add_relays_to(&client, &key_package_relays, false)
+                .await?
+                .iter()
+            {
+                client.connect_relay(relay).await?;
                 tracing::debug!(
                     target: "whitenoise::nostr_manager::set_nostr_identity",
                     "Connected to user key package relay: {}",
@@ -235,7 +377,7 @@ impl NostrManager {
         tracing::debug!(
             target: "whitenoise::nostr_manager::set_nostr_identity",
             "Connected to relays: {:?}",
-            self.client
+            client
                 .relays()
                 .await
                 .keys()
@@ -243,106 +385,35 @@ impl NostrManager {
                 .collect::<Vec<_>>()
         );
 
-        tracing::debug!(
-            target: "whitenoise::nostr_manager::set_nostr_identity",
-            "Nostr identity updated and connected to relays"
-        );
-
-        // Create and store new processor
-        let new_processor = EventProcessor::new(app_handle.clone());
-        *self.event_processor.lock().await = new_processor;
-
         // Spawn two tasks in parallel:
-        // 1. Setup subscriptions to catch future events
-        // 2. Fetch past events
-        let app_handle_clone_subs = app_handle.clone();
-        let account_clone_subs = account.clone();
-        spawn(async move {
-            tracing::debug!(
-                target: "whitenoise::nostr_manager::set_nostr_identity",
-                "Starting subscriptions"
-            );
-            let wn_state = app_handle_clone_subs.state::<Whitenoise>();
-
-            let group_ids = account_clone_subs
-                .nostr_group_ids(wn_state.clone())
-                .await
-                .expect("Couldn't get nostr group ids");
-
-            match wn_state
-                .nostr
-                .setup_subscriptions(account_clone_subs.pubkey, group_ids)
-                .await
-            {
-                Ok(_) => {
-                    tracing::debug!(
-                        target: "whitenoise::nostr_manager::set_nostr_identity",
-                        "Subscriptions shutdown triggered"
-                    );
-                }
-                Err(e) => {
-                    tracing::error!(
-                    target: "whitenoise::nostr_manager::set_nostr_identity",
-                    "Error subscribing to events: {}",
-                    e
-                    );
-                }
-            }
-        });
-
-        let app_handle_clone_fetch = app_handle.clone();
+        // 1. Setup the subscriptions only the active account needs
+        // 2. Sync past events
+        let wn_subs = wn.clone();
         let pubkey = account.pubkey;
-        let last_synced = account.last_synced;
         spawn(async move {
-            tracing::debug!(
-                target: "whitenoise::nostr_manager::set_nostr_identity",
-                "Starting fetch for {}",
-                pubkey
-            );
-            let wn_state = app_handle_clone_fetch.state::<Whitenoise>();
-
-            let group_ids = Account::find_by_pubkey(&pubkey, wn_state.clone())
-                .await
-                .expect("Couldn't get account")
-                .nostr_group_ids(wn_state.clone())
-                .await
-                .expect("Couldn't get nostr group ids");
-
-            match &wn_state
-                .nostr
-                .fetch_for_user(pubkey, last_synced, group_ids)
-                .await
-            {
+            match wn_subs.nostr.setup_subscriptions(pubkey).await {
                 Ok(_) => {
                     tracing::debug!(
                         target: "whitenoise::nostr_manager::set_nostr_identity",
-                        "Fetch completed for {}",
+                        "Subscriptions set up for {}",
                         pubkey
                     );
-                    // Update last_synced through a new database query
-                    if let Ok(mut account) =
-                        Account::find_by_pubkey(&pubkey, wn_state.clone()).await
-                    {
-                        account.last_synced = Timestamp::now();
-                        if let Err(e) = account.save(wn_state.clone()).await {
-                            tracing::error!(
-                                target: "whitenoise::nostr_manager::set_nostr_identity",
-                                "Error updating last_synced: {}",
-                                e
-                            );
-                        }
-                    }
                 }
                 Err(e) => {
                     tracing::error!(
                         target: "whitenoise::nostr_manager::set_nostr_identity",
-                        "Error in fetch: {}",
+                        "Error subscribing to events: {}",
                         e
                     );
                 }
             }
         });
 
+        let wn_fetch = wn.clone();
+        spawn(async move {
+            wn_fetch.nostr.catch_up(pubkey, &wn_fetch).await;
+        });
+
         Ok(())
     }
 
@@ -352,21 +423,22 @@ impl NostrManager {
         pubkey: String,
         method: NostrEncryptionMethod,
     ) -> Result<String> {
-        let recipient_pubkey = PublicKey::from_hex(&pubkey).unwrap();
-        let signer = self.client.signer().await.unwrap();
+        if method == NostrEncryptionMethod::Nip04 && self.lockdown_mode() {
+            return Err(NostrManagerError::LockdownMode(
+                "NIP-04 encryption is disabled".to_string(),
+            ));
+        }
+        let recipient_pubkey = PublicKey::from_hex(&pubkey).map_err(|e| {
+            NostrManagerError::InvalidInput(format!("Invalid public key {}: {}", pubkey, e))
+        })?;
+        let signer = self.client().signer().await?;
         match method {
             NostrEncryptionMethod::Nip04 => {
-                let encrypted = signer
-                    .nip04_encrypt(&recipient_pubkey, &content)
-                    .await
-                    .unwrap();
+                let encrypted = signer.nip04_encrypt(&recipient_pubkey, &content).await?;
                 Ok(encrypted)
             }
             NostrEncryptionMethod::Nip44 => {
-                let encrypted = signer
-                    .nip44_encrypt(&recipient_pubkey, &content)
-                    .await
-                    .unwrap();
+                let encrypted = signer.nip44_encrypt(&recipient_pubkey, &content).await?;
                 Ok(encrypted)
             }
         }
@@ -378,21 +450,22 @@ impl NostrManager {
         pubkey: String,
         method: NostrEncryptionMethod,
     ) -> Result<String> {
-        let author_pubkey = PublicKey::from_hex(&pubkey).unwrap();
-        let signer = self.client.signer().await.unwrap();
+        if method == NostrEncryptionMethod::Nip04 && self.lockdown_mode() {
+            return Err(NostrManagerError::LockdownMode(
+                "NIP-04 decryption is disabled".to_string(),
+            ));
+        }
+        let author_pubkey = PublicKey::from_hex(&pubkey).map_err(|e| {
+            NostrManagerError::InvalidInput(format!("Invalid public key {}: {}", pubkey, e))
+        })?;
+        let signer = self.client().signer().await?;
         match method {
             NostrEncryptionMethod::Nip04 => {
-                let decrypted = signer
-                    .nip04_decrypt(&author_pubkey, &content)
-                    .await
-                    .unwrap();
+                let decrypted = signer.nip04_decrypt(&author_pubkey, &content).await?;
                 Ok(decrypted)
             }
             NostrEncryptionMethod::Nip44 => {
-                let decrypted = signer
-                    .nip44_decrypt(&author_pubkey, &content)
-                    .await
-                    .unwrap();
+                let decrypted = signer.nip44_decrypt(&author_pubkey, &content).await?;
                 Ok(decrypted)
             }
         }
@@ -407,17 +480,72 @@ impl NostrManager {
             .collect()
     }
 
+    /// Deletes the cached events of a single account from the nostr database: events it authored,
+    /// gift-wraps addressed to it, and the messages of the given groups.
+    ///
+    /// Events authored by one of `local_pubkeys`, the other accounts on this device, are kept, so
+    /// that their contact lists, reactions and messages aren't removed with the account.
+    ///
+    /// Returns the number of events deleted.
+    pub async fn delete_events_for_pubkey(
+        &self,
+        pubkey: PublicKey,
+        nostr_group_ids: Vec<String>,
+        local_pubkeys: &[PublicKey],
+    ) -> Result<usize> {
+        let mut filters = vec![
+            Filter::new().author(pubkey),
+            Filter::new().kind(Kind::GiftWrap).pubkey(pubkey),
+        ];
+        if !nostr_group_ids.is_empty() {
+            filters.push(
+                Filter::new()
+                    .kind(Kind::MlsGroupMessage)
+                    .custom_tag(SingleLetterTag::lowercase(Alphabet::H), nostr_group_ids),
+            );
+        }
+
+        let client = self.client();
+        let database = client.database();
+        // An event can match more than one filter, so deleting by id also counts it once
+        let ids: HashSet<EventId> = database
+            .query(filters)
+            .await?
+            .into_iter()
+            .filter(|event| !local_pubkeys.contains(&event.pubkey))
+            .map(|event| event.id)
+            .collect();
+        let deleted = ids.len();
+        if !ids.is_empty() {
+            database.delete(Filter::new().ids(ids)).await?;
+        }
+
+        tracing::debug!(
+            target: "whitenoise::nostr_manager::delete_events_for_pubkey",
+            "Deleted {} events for {}",
+            deleted,
+            pubkey
+        );
+
+        Ok(deleted)
+    }
+
     pub async fn delete_all_data(&self) -> Result<()> {
         tracing::debug!(
             target: "whitenoise::nostr_manager::delete_all_data",
             "Deleting Nostr data"
         );
-        self.client.reset().await.map_err(NostrManagerError::from)?;
-        self.client
+        self.stop_all_sessions().await?;
+        self.default_client
+            .reset()
+            .await
+            .map_err(NostrManagerError::from)?;
+        self.default_client
             .database()
             .wipe()
             .await
             .map_err(NostrManagerError::from)?;
+        self.relay_routes.clear();
         Ok(())
     }
 }
@@ -0,0 +1,250 @@
+//! Network profile functions for NostrManager
+//! A network profile decides which relays we use: the default relays, the timeout for fetches, and
+//! optionally fixed relays for key packages and welcomes instead of the users' own relays. Local
+//! development, staging and self-hosted deployments each use their own profile without a rebuild.
+//!
+//! The profile is selected at startup, in order of precedence:
+//! 1. `WHITENOISE_NETWORK_PROFILE`, either `production`, `development` or the path to a profile file
+//! 2. `network.json` in the data directory, written whenever the profile is changed at runtime
+//! 3. `development` for dev builds and `production` for release builds
+
+use crate::nostr_manager::{NostrManager, NostrManagerError, Result};
+use crate::Whitenoise;
+use nostr_sdk::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use std::time::Duration;
+
+const NETWORK_PROFILE_FILE: &str = "network.json";
+const NETWORK_PROFILE_ENV: &str = "WHITENOISE_NETWORK_PROFILE";
+
+/// Fields missing from a profile file take the `production` values, in every build.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default = "NetworkProfile::production")]
+pub struct NetworkProfile {
+    pub name: String,
+    /// The relays every client connects to.
+    pub default_relays: Vec<String>,
+    /// How long fetches wait for relays, in seconds.
+    pub timeout_secs: u64,
+    /// Relays key packages are published to and deleted from instead of the account's key package relays.
+    pub key_package_relays: Option<Vec<String>>,
+    /// Relays welcomes are sent to instead of the members' inbox relays.
+    pub welcome_relays: Option<Vec<String>>,
+    /// Whether to connect to the relays on the users' relay lists.
+    pub use_user_relays: bool,
+}
+
+impl Default for NetworkProfile {
+    fn default() -> Self {
+        if cfg!(dev) {
+            Self::development()
+        } else {
+            Self::production()
+        }
+    }
+}
+
+impl NetworkProfile {
+    pub fn production() -> Self {
+        Self {
+            name: "production".to_string(),
+            default_relays: vec![
+                "wss://relay.damus.io".to_string(),
+                "wss://purplepag.es".to_string(),
+                "wss://relay.primal.net".to_string(),
+                "wss://nostr.oxtr.dev".to_string(),
+            ],
+            timeout_secs: 5,
+            key_package_relays: None,
+            welcome_relays: None,
+            use_user_relays: true,
+        }
+    }
+
+    /// Everything goes through a local relay on `ws://localhost:8080`, see the README.
+    pub fn development() -> Self {
+        let local_relay = "ws://localhost:8080".to_string();
+        Self {
+            name: "development".to_string(),
+            default_relays: vec![local_relay.clone(), "wss://purplepag.es".to_string()],
+            timeout_secs: 5,
+            key_package_relays: Some(vec![local_relay.clone()]),
+            welcome_relays: Some(vec![local_relay]),
+            use_user_relays: false,
+        }
+    }
+
+    /// Selects the network profile, see the module docs for the order of precedence.
+    pub fn load(data_dir: &Path) -> Result<Self> {
+        if let Ok(selected) = std::env::var(NETWORK_PROFILE_ENV) {
+            let profile = match selected.as_str() {
+                "production" => Self::production(),
+                "development" => Self::development(),
+                path => Self::from_file(Path::new(path))?,
+            };
+            tracing::info!(
+                target: "whitenoise::nostr_manager::network",
+                "Using network profile {} from {}",
+                profile.name,
+                NETWORK_PROFILE_ENV
+            );
+            return Ok(profile);
+        }
+
+        let path = data_dir.join(NETWORK_PROFILE_FILE);
+        if path.exists() {
+            return Self::from_file(&path);
+        }
+        Ok(Self::default())
+    }
+
+    fn from_file(path: &Path) -> Result<Self> {
+        let json = std::fs::read_to_string(path).map_err(|e| {
+            NostrManagerError::NetworkProfile(format!("Error reading {}: {}", path.display(), e))
+        })?;
+        let profile: Self = serde_json::from_str(&json).map_err(|e| {
+            NostrManagerError::NetworkProfile(format!("Error parsing {}: {}", path.display(), e))
+        })?;
+        profile.validate()?;
+        Ok(profile)
+    }
+
+    /// Saves the network profile to the data directory.
+    pub fn save(&self, data_dir: &Path) -> Result<()> {
+        let json = serde_json::to_string_pretty(self)
+            .map_err(|e| NostrManagerError::NetworkProfile(e.to_string()))?;
+        std::fs::write(data_dir.join(NETWORK_PROFILE_FILE), json)
+            .map_err(|e| NostrManagerError::NetworkProfile(format!("Error saving profile: {}", e)))
+    }
+
+    /// Checks that the profile can be used.
+    pub fn validate(&self) -> Result<()> {
+        if self.default_relays.is_empty() {
+            return Err(NostrManagerError::NetworkProfile(
+                "At least one default relay is required".to_string(),
+            ));
+        }
+        if self.timeout_secs == 0 {
+            return Err(NostrManagerError::NetworkProfile(
+                "The timeout must be at least one second".to_string(),
+            ));
+        }
+        for relays in [&self.key_package_relays, &self.welcome_relays]
+            .into_iter()
+            .flatten()
+        {
+            if relays.is_empty() {
+                return Err(NostrManagerError::NetworkProfile(
+                    "Fixed relay lists can't be empty, leave them unset to use the users' relays"
+                        .to_string(),
+                ));
+            }
+        }
+        let all_relays = self
+            .default_relays
+            .iter()
+            .chain(self.key_package_relays.iter().flatten())
+            .chain(self.welcome_relays.iter().flatten());
+        for url in all_relays {
+            RelayUrl::parse(url).map_err(|e| {
+                NostrManagerError::NetworkProfile(format!("Invalid relay URL {}: {}", url, e))
+            })?;
+        }
+        Ok(())
+    }
+
+    pub fn timeout(&self) -> Duration {
+        Duration::from_secs(self.timeout_secs)
+    }
+}
+
+impl NostrManager {
+    pub async fn network_profile(&self) -> NetworkProfile {
+        self.settings.lock().await.network.clone()
+    }
+
+    /// Switches to a new network profile.
+    ///
+    /// The default client and the running account sessions connect to relays the new profile
+    /// calls for and drop the ones only the previous profile called for. If the default client
+    /// can't be switched, the previous profile stays in use.
+    pub async fn set_network_profile(
+        &self,
+        profile: NetworkProfile,
+        wn: &Whitenoise,
+    ) -> Result<()> {
+        profile.validate()?;
+        let previous = std::mem::replace(&mut self.settings.lock().await.network, profile.clone());
+
+        if let Err(e) = self.apply_network_profile(&previous, &profile).await {
+            self.settings.lock().await.network = previous;
+            return Err(e);
+        }
+        self.apply_network_profile_to_sessions(&previous, &profile, wn)
+            .await;
+        Ok(())
+    }
+
+    async fn apply_network_profile(
+        &self,
+        previous: &NetworkProfile,
+        profile: &NetworkProfile,
+    ) -> Result<()> {
+        for url in previous
+            .default_relays
+            .iter()
+            .filter(|url| !profile.default_relays.contains(url))
+        {
+            self.default_client.remove_relay(url).await?;
+        }
+        for url in self
+            .add_relays_to(&self.default_client, &profile.default_relays, false)
+            .await?
+        {
+            self.default_client.connect_relay(&url).await?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_built_in_profiles_are_valid() {
+        assert!(NetworkProfile::production().validate().is_ok());
+        assert!(NetworkProfile::development().validate().is_ok());
+    }
+
+    #[test]
+    fn test_validate() {
+        let mut profile = NetworkProfile::production();
+        profile.welcome_relays = Some(vec![]);
+        assert!(profile.validate().is_err());
+
+        profile.welcome_relays = Some(vec!["not a relay".to_string()]);
+        assert!(profile.validate().is_err());
+
+        profile.welcome_relays = None;
+        profile.timeout_secs = 0;
+        assert!(profile.validate().is_err());
+    }
+
+    #[test]
+    fn test_partial_profile_uses_defaults() {
+        let profile: NetworkProfile = serde_json::from_str(
+            r#"{"name": "staging", "default_relays": ["wss://relay.example.com"]}"#,
+        )
+        .unwrap();
+        assert_eq!(profile.name, "staging");
+        assert_eq!(profile.default_relays, vec!["wss://relay.example.com"]);
+
+        let production = NetworkProfile::production();
+        assert_eq!(profile.timeout_secs, production.timeout_secs);
+        assert_eq!(profile.key_package_relays, production.key_package_relays);
+        assert_eq!(profile.welcome_relays, production.welcome_relays);
+        assert_eq!(profile.use_user_relays, production.use_user_relays);
+    }
+}
@@ -0,0 +1,367 @@
+//! Proxy functions for NostrManager
+//! Relay connections can be routed through a SOCKS5 proxy such as Tor, either for all relays or only
+//! for `.onion` relays, with per-relay overrides. With `refuse_direct` enabled, relays that would be
+//! connected to directly are never added to a relay pool, so no relay traffic leaves the proxy.
+//! Connections outside the relay pools, to a wallet or a remote signer, follow the same settings.
+
+use crate::nostr_manager::{NostrManager, NostrManagerError, Result};
+use nostr_sdk::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::path::Path;
+
+const PROXY_SETTINGS_FILE: &str = "proxy.json";
+
+/// Which relays are connected to through the proxy, unless overridden per relay.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ProxyTarget {
+    #[default]
+    All,
+    Onion,
+}
+
+/// How a single relay is connected to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum RelayProxyMode {
+    Proxy,
+    Direct,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ProxySettings {
+    /// The SOCKS5 proxy, e.g. `127.0.0.1:9050` for Tor. No proxy is used if unset.
+    pub address: Option<SocketAddr>,
+    pub target: ProxyTarget,
+    /// Per-relay overrides of `target`, keyed by relay URL.
+    pub overrides: HashMap<String, RelayProxyMode>,
+    /// Refuse to connect to relays directly, e.g. in lockdown mode.
+    pub refuse_direct: bool,
+}
+
+impl ProxySettings {
+    /// Loads the proxy settings saved in the data directory, or the defaults if none were saved.
+    pub fn load(data_dir: &Path) -> Result<Self> {
+        let path = data_dir.join(PROXY_SETTINGS_FILE);
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let json = std::fs::read_to_string(path)
+            .map_err(|e| NostrManagerError::Proxy(format!("Error reading settings: {}", e)))?;
+        let settings: Self = serde_json::from_str(&json)
+            .map_err(|e| NostrManagerError::Proxy(format!("Error parsing settings: {}", e)))?;
+        settings.validate()?;
+        Ok(settings)
+    }
+
+    /// Saves the proxy settings to the data directory.
+    pub fn save(&self, data_dir: &Path) -> Result<()> {
+        let json = serde_json::to_string_pretty(self)
+            .map_err(|e| NostrManagerError::Proxy(e.to_string()))?;
+        std::fs::write(data_dir.join(PROXY_SETTINGS_FILE), json)
+            .map_err(|e| NostrManagerError::Proxy(format!("Error saving settings: {}", e)))
+    }
+
+    /// Checks that the settings can be applied.
+    pub fn validate(&self) -> Result<()> {
+        if self.address.is_none() {
+            if self.refuse_direct {
+                return Err(NostrManagerError::Proxy(
+                    "Refusing direct connections requires a proxy".to_string(),
+                ));
+            }
+            if self.overrides.values().any(|m| *m == RelayProxyMode::Proxy) {
+                return Err(NostrManagerError::Proxy(
+                    "Relays can't be routed through a proxy without a proxy address".to_string(),
+                ));
+            }
+        }
+        for url in self.overrides.keys() {
+            RelayUrl::parse(url).map_err(|e| {
+                NostrManagerError::Proxy(format!("Invalid relay URL {}: {}", url, e))
+            })?;
+        }
+        Ok(())
+    }
+
+    /// Returns how the relay should be connected to.
+    pub fn mode_for(&self, url: &RelayUrl) -> RelayProxyMode {
+        if self.address.is_none() {
+            return RelayProxyMode::Direct;
+        }
+
+        let overridden = self.overrides.iter().find_map(|(override_url, mode)| {
+            RelayUrl::parse(override_url)
+                .is_ok_and(|override_url| &override_url == url)
+                .then_some(*mode)
+        });
+        if let Some(mode) = overridden {
+            return mode;
+        }
+
+        let is_onion = url
+            .as_url()
+            .host_str()
+            .is_some_and(|host| host.ends_with(".onion"));
+        match self.target {
+            ProxyTarget::All => RelayProxyMode::Proxy,
+            ProxyTarget::Onion if is_onion => RelayProxyMode::Proxy,
+            ProxyTarget::Onion => RelayProxyMode::Direct,
+        }
+    }
+
+    /// Returns the proxy to use for the relay, or an error if the relay would be connected
+    /// to directly while direct connections are refused.
+    pub fn proxy_for(&self, url: &RelayUrl) -> Result<Option<SocketAddr>> {
+        match (self.mode_for(url), self.address) {
+            (RelayProxyMode::Proxy, Some(address)) => Ok(Some(address)),
+            _ if self.refuse_direct => {
+                Err(NostrManagerError::DirectConnectionRefused(url.to_string()))
+            }
+            _ => Ok(None),
+        }
+    }
+
+    /// Returns how to connect to relays outside the relay pools, e.g. a wallet's or a remote
+    /// signer's, which share one set of relay options. If any of the relays would be connected to
+    /// through the proxy, all of them are. Without known relays the proxy is used if there is one.
+    pub fn connection_mode_for(&self, urls: &[RelayUrl]) -> Result<ConnectionMode> {
+        let mut proxy = if urls.is_empty() { self.address } else { None };
+        for url in urls {
+            if let Some(address) = self.proxy_for(url)? {
+                proxy = Some(address);
+            }
+        }
+        Ok(match proxy {
+            Some(address) => ConnectionMode::Proxy(address),
+            None => ConnectionMode::Direct,
+        })
+    }
+
+    /// The client options, so that relays without an override are routed by `target`.
+    pub fn client_options(&self) -> Options {
+        let Some(address) = self.address else {
+            return Options::default();
+        };
+        let target = match self.target {
+            ProxyTarget::All => ConnectionTarget::All,
+            ProxyTarget::Onion => ConnectionTarget::Onion,
+        };
+        Options::default().connection(Connection::new().proxy(address).target(target))
+    }
+}
+
+/// Returns the `relay` parameters of a `bunker://`, `nostrconnect://` or `nostr+walletconnect://` URI.
+fn relays_of_uri(uri: &str) -> Vec<RelayUrl> {
+    Url::parse(uri.trim())
+        .map(|url| {
+            url.query_pairs()
+                .filter(|(key, _)| key == "relay")
+                .filter_map(|(_, relay)| RelayUrl::parse(&relay).ok())
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+impl NostrManager {
+    /// Returns how to connect to the relays of a wallet or remote signer URI.
+    ///
+    /// # Errors
+    /// Returns `NostrManagerError::DirectConnectionRefused` if a relay would be connected to
+    /// directly while direct connections are refused.
+    pub async fn connection_mode_for_uri(&self, uri: &str) -> Result<ConnectionMode> {
+        self.settings
+            .lock()
+            .await
+            .proxy
+            .connection_mode_for(&relays_of_uri(uri))
+    }
+
+    /// Adds a relay to the client's pool, connecting to it through the proxy if the proxy settings say so.
+    ///
+    /// # Errors
+    /// Returns `NostrManagerError::DirectConnectionRefused` if the relay would be connected to
+    /// directly while direct connections are refused.
+    pub async fn add_relay_to(&self, client: &Client, url: &str, read_only: bool) -> Result<bool> {
+        let relay_url = RelayUrl::parse(url).map_err(|e| {
+            NostrManagerError::InvalidRelayUrl(format!("Invalid relay URL {}: {}", url, e))
+        })?;
+        let proxy = self.settings.lock().await.proxy.proxy_for(&relay_url)?;
+
+        let mut opts = RelayOptions::new().connection_mode(match proxy {
+            Some(address) => ConnectionMode::Proxy(address),
+            None => ConnectionMode::Direct,
+        });
+        if read_only {
+            opts = opts.write(false);
+        }
+
+        Ok(client
+            .pool()
+            .add_relay(relay_url, opts)
+            .await
+            .map_err(nostr_sdk::client::Error::from)?)
+    }
+
+    /// Adds relays to the client's pool, skipping relays that the proxy settings refuse.
+    ///
+    /// Returns the relays that are in the pool.
+    pub async fn add_relays_to(
+        &self,
+        client: &Client,
+        urls: &[String],
+        read_only: bool,
+    ) -> Result<Vec<String>> {
+        let mut added = Vec::new();
+        for url in urls {
+            match self.add_relay_to(client, url, read_only).await {
+                Ok(_) => added.push(url.clone()),
+                Err(NostrManagerError::DirectConnectionRefused(url)) => {
+                    tracing::warn!(
+                        target: "whitenoise::nostr_manager::proxy",
+                        "Skipping relay {}, direct connections are refused",
+                        url
+                    );
+                }
+                Err(e) => return Err(e),
+            }
+        }
+        Ok(added)
+    }
+
+    /// Switches to new proxy settings and adds the relays of the default client and of every
+    /// account session again, so open connections are reconnected with the new settings.
+    /// Relays that the new settings refuse to connect to directly are dropped.
+    ///
+    /// # Errors
+    /// Returns an error if the settings are invalid.
+    pub async fn set_proxy_settings(&self, settings: ProxySettings) -> Result<()> {
+        settings.validate()?;
+        self.settings.lock().await.proxy = settings;
+
+        let mut clients = vec![self.default_client.clone()];
+        clients.extend(
+            self.sessions
+                .lock()
+                .await
+                .values()
+                .map(|session| session.client.clone()),
+        );
+        for client in clients {
+            if let Err(e) = self.apply_proxy_settings(&client).await {
+                tracing::error!(
+                    target: "whitenoise::nostr_manager::proxy",
+                    "Error applying proxy settings to relays: {}",
+                    e
+                );
+            }
+        }
+        Ok(())
+    }
+
+    async fn apply_proxy_settings(&self, client: &Client) -> Result<()> {
+        for (url, relay) in client.relays().await {
+            let read_only = !relay.flags().has_write();
+            client.remove_relay(&url).await?;
+            // The client's subscriptions are sent to the relay as it is added again
+            match self.add_relay_to(client, url.as_str(), read_only).await {
+                Ok(_) => client.connect_relay(&url).await?,
+                Err(NostrManagerError::DirectConnectionRefused(url)) => {
+                    tracing::warn!(
+                        target: "whitenoise::nostr_manager::proxy",
+                        "Dropping relay {}, direct connections are refused",
+                        url
+                    );
+                }
+                Err(e) => return Err(e),
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_proxy_for() {
+        let clearnet = RelayUrl::parse("wss://relay.example.com").unwrap();
+        let onion = RelayUrl::parse("ws://example.onion").unwrap();
+        let address: SocketAddr = "127.0.0.1:9050".parse().unwrap();
+
+        let mut settings = ProxySettings::default();
+        assert_eq!(settings.proxy_for(&clearnet).unwrap(), None);
+
+        settings.address = Some(address);
+        settings.target = ProxyTarget::Onion;
+        assert_eq!(settings.proxy_for(&clearnet).unwrap(), None);
+        assert_eq!(settings.proxy_for(&onion).unwrap(), Some(address));
+
+        settings
+            .overrides
+            .insert(clearnet.to_string(), RelayProxyMode::Proxy);
+        assert_eq!(settings.proxy_for(&clearnet).unwrap(), Some(address));
+
+        settings
+            .overrides
+            .insert(clearnet.to_string(), RelayProxyMode::Direct);
+        settings.refuse_direct = true;
+        assert!(matches!(
+            settings.proxy_for(&clearnet),
+            Err(NostrManagerError::DirectConnectionRefused(_))
+        ));
+    }
+
+    #[test]
+    fn test_connection_mode_for_uri_relays() {
+        let address: SocketAddr = "127.0.0.1:9050".parse().unwrap();
+        let relays = relays_of_uri(
+            "nostr+walletconnect://b889ff5b1513b641e2a139f661a661364979c5beee91842f8f0ef42ab558e9d4?relay=wss%3A%2F%2Frelay.example.com&secret=71a8c14c1407c113601079c4302dab36460f0ccd0ad506f1f2dc73b5100e4f3c",
+        );
+        assert_eq!(
+            relays,
+            vec![RelayUrl::parse("wss://relay.example.com").unwrap()]
+        );
+
+        let mut settings = ProxySettings::default();
+        assert!(matches!(
+            settings.connection_mode_for(&relays),
+            Ok(ConnectionMode::Direct)
+        ));
+
+        settings.address = Some(address);
+        assert!(matches!(
+            settings.connection_mode_for(&relays),
+            Ok(ConnectionMode::Proxy(proxy)) if proxy == address
+        ));
+        assert!(matches!(
+            settings.connection_mode_for(&[]),
+            Ok(ConnectionMode::Proxy(_))
+        ));
+
+        settings.target = ProxyTarget::Onion;
+        settings.refuse_direct = true;
+        assert!(matches!(
+            settings.connection_mode_for(&relays),
+            Err(NostrManagerError::DirectConnectionRefused(_))
+        ));
+    }
+
+    #[test]
+    fn test_validate() {
+        let settings = ProxySettings {
+            refuse_direct: true,
+            ..Default::default()
+        };
+        assert!(settings.validate().is_err());
+
+        let settings = ProxySettings {
+            address: Some("127.0.0.1:9050".parse().unwrap()),
+            refuse_direct: true,
+            ..Default::default()
+        };
+        assert!(settings.validate().is_ok());
+    }
+}
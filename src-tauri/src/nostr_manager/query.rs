@@ -3,25 +3,60 @@
 
 use crate::nostr_manager::{NostrManager, Result};
 use nostr_sdk::prelude::*;
+use std::collections::HashMap;
 
 impl NostrManager {
     pub async fn query_user_metadata(&self, pubkey: PublicKey) -> Result<Option<Metadata>> {
-        Ok(self.client.database().metadata(pubkey).await?)
+        Ok(self.client().database().metadata(pubkey).await?)
     }
 
     #[allow(dead_code)]
     pub async fn query_user_relays(&self, pubkey: PublicKey) -> Result<Vec<String>> {
         let filter = Filter::new().author(pubkey).kind(Kind::RelayList).limit(1);
-        let events = self.client.database().query(vec![filter]).await?;
+        let events = self.client().database().query(vec![filter]).await?;
         Ok(Self::relay_urls_from_events(events))
     }
 
+    /// Returns the NIP-65 write relays (unmarked or marked `write`) for a user from the database cache.
+    pub async fn query_user_write_relays(&self, pubkey: PublicKey) -> Result<Vec<String>> {
+        let filter = Filter::new().author(pubkey).kind(Kind::RelayList).limit(1);
+        let events = self.client().database().query(vec![filter]).await?;
+
+        Ok(events
+            .first()
+            .map(|event| {
+                nip65::extract_relay_list(event)
+                    .filter(|(_, metadata)| !matches!(metadata, Some(RelayMetadata::Read)))
+                    .map(|(url, _)| url.to_string())
+                    .collect()
+            })
+            .unwrap_or_default())
+    }
+
+    /// Returns the read/write markers of a user's NIP-65 relay list from the database cache.
+    pub async fn query_user_relay_metadata(
+        &self,
+        pubkey: PublicKey,
+    ) -> Result<HashMap<RelayUrl, Option<RelayMetadata>>> {
+        let filter = Filter::new().author(pubkey).kind(Kind::RelayList).limit(1);
+        let events = self.client().database().query(vec![filter]).await?;
+
+        Ok(events
+            .first()
+            .map(|event| {
+                nip65::extract_relay_list(event)
+                    .map(|(url, metadata)| (url.clone(), metadata.clone()))
+                    .collect()
+            })
+            .unwrap_or_default())
+    }
+
     pub async fn query_user_inbox_relays(&self, pubkey: PublicKey) -> Result<Vec<String>> {
         let filter = Filter::new()
             .author(pubkey)
             .kind(Kind::InboxRelays)
             .limit(1);
-        let events = self.client.database().query(vec![filter]).await?;
+        let events = self.client().database().query(vec![filter]).await?;
 
         Ok(Self::relay_urls_from_events(events))
     }
@@ -31,25 +66,35 @@ impl NostrManager {
             .author(pubkey)
             .kind(Kind::MlsKeyPackageRelays)
             .limit(1);
-        let events = self.client.database().query(vec![filter]).await?;
+        let events = self.client().database().query(vec![filter]).await?;
 
         Ok(Self::relay_urls_from_events(events))
     }
 
     pub async fn query_user_key_packages(&self, pubkey: PublicKey) -> Result<Events> {
         let filter = Filter::new().author(pubkey).kind(Kind::MlsKeyPackage);
-        let events = self.client.database().query(vec![filter]).await?;
+        let events = self.client().database().query(vec![filter]).await?;
         Ok(events)
     }
 
     pub async fn query_contact_list_pubkeys(&self) -> Result<Vec<PublicKey>> {
-        let pubkey = self.client.signer().await?.get_public_key().await.unwrap();
+        let pubkey = self
+            .client()
+            .signer()
+            .await?
+            .get_public_key()
+            .await
+            .unwrap();
+        self.query_contact_list_pubkeys_of(pubkey).await
+    }
 
+    /// Returns the pubkeys on a user's contact list from the database cache.
+    pub async fn query_contact_list_pubkeys_of(&self, pubkey: PublicKey) -> Result<Vec<PublicKey>> {
         let filter = Filter::new()
             .kind(Kind::ContactList)
             .author(pubkey)
             .limit(1);
-        let events = self.client.database().query(vec![filter]).await?;
+        let events = self.client().database().query(vec![filter]).await?;
 
         let contacts_pubkeys = if let Some(event) = events.first() {
             event
@@ -72,7 +117,7 @@ impl NostrManager {
             return Ok(vec![]);
         }
         let filter = Filter::new().kind(Kind::Metadata).authors(contacts_pubkeys);
-        let events = self.client.database().query(vec![filter]).await?;
+        let events = self.client().database().query(vec![filter]).await?;
 
         Ok(events.into_iter().collect())
     }
@@ -83,14 +128,13 @@ impl NostrManager {
         pubkey: PublicKey,
     ) -> Result<Vec<(EventId, UnsignedEvent)>> {
         let gw_events = self.query_user_giftwrapped_events(pubkey).await?;
-        let invites = self.extract_invite_events(gw_events).await;
-        Ok(invites)
+        self.extract_invite_events(gw_events).await
     }
 
     #[allow(dead_code)]
     async fn query_user_giftwrapped_events(&self, pubkey: PublicKey) -> Result<Vec<Event>> {
         let filter = Filter::new().kind(Kind::GiftWrap).pubkeys(vec![pubkey]);
-        let events = self.client.database().query(vec![filter]).await?;
+        let events = self.client().database().query(vec![filter]).await?;
         Ok(events.into_iter().collect())
     }
 
@@ -99,7 +143,7 @@ impl NostrManager {
         let filter = Filter::new()
             .kind(Kind::MlsGroupMessage)
             .custom_tag(SingleLetterTag::lowercase(Alphabet::H), group_ids);
-        let events = self.client.database().query(vec![filter]).await?;
+        let events = self.client().database().query(vec![filter]).await?;
         Ok(events.into_iter().collect())
     }
 }
@@ -0,0 +1,439 @@
+//! Relay status functions for NostrManager
+//! Tracks the health of every relay in the pools of the default client and the account sessions:
+//! status, connect and disconnect counts, latency, the last error and the relay's NIP-11 document.
+//! Relays that keep disconnecting are flagged as flapping.
+//!
+//! Statuses are sampled, but connect and disconnect counts come from each relay's own connection
+//! stats, so a drop and reconnect between two samples is still counted.
+
+use crate::event_sink::EventSink;
+use crate::nostr_manager::NostrManager;
+use nostr_sdk::prelude::*;
+use serde::Serialize;
+use std::collections::{HashMap, VecDeque};
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// How often the relay pools are checked for status and connection count changes.
+const POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+/// A relay that disconnects `FLAPPING_THRESHOLD` times within `FLAPPING_WINDOW` is flapping.
+const FLAPPING_WINDOW: Duration = Duration::from_secs(300);
+const FLAPPING_THRESHOLD: usize = 4;
+
+/// The health of a single relay, as returned by `get_relay_status` and
+/// emitted with `relay_status_changed`.
+#[derive(Debug, Clone, Serialize)]
+pub struct RelayHealth {
+    pub url: String,
+    pub status: String,
+    pub connect_count: u64,
+    pub disconnect_count: u64,
+    pub latency_ms: Option<u64>,
+    pub last_error: Option<String>,
+    pub last_status_change: Option<Timestamp>,
+    pub flapping: bool,
+    /// The relay's NIP-11 information document, once it has been fetched.
+    pub information: Option<RelayInformationDocument>,
+}
+
+/// Identifies a relay pool: the default client's, or the session client of an account.
+pub type PoolKey = Option<PublicKey>;
+
+/// How often a relay has connected and disconnected in one relay pool, from the relay's own stats.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ConnectionCounts {
+    pub connects: u64,
+    pub disconnects: u64,
+}
+
+impl ConnectionCounts {
+    /// Every successful connection but the current one has ended in a disconnect.
+    pub fn from_stats(success: usize, connected: bool) -> Self {
+        let connects = success as u64;
+        Self {
+            connects,
+            disconnects: connects.saturating_sub(connected as u64),
+        }
+    }
+}
+
+#[derive(Debug)]
+struct RelayHealthEntry {
+    health: RelayHealth,
+    /// The counts last seen in each pool, so that only new connections are added.
+    pools: HashMap<PoolKey, ConnectionCounts>,
+    /// When the relay recently disconnected, used to detect flapping.
+    disconnects: VecDeque<Instant>,
+    information_requested: bool,
+    /// Whether the health changed since it was last emitted.
+    changed: bool,
+}
+
+impl RelayHealthEntry {
+    fn new(url: &RelayUrl) -> Self {
+        Self {
+            health: RelayHealth {
+                url: url.to_string(),
+                status: RelayStatus::Initialized.to_string(),
+                connect_count: 0,
+                disconnect_count: 0,
+                latency_ms: None,
+                last_error: None,
+                last_status_change: None,
+                flapping: false,
+                information: None,
+            },
+            pools: HashMap::new(),
+            disconnects: VecDeque::new(),
+            information_requested: false,
+            changed: true,
+        }
+    }
+}
+
+/// Keeps the health of all relays we know about, keyed by relay URL.
+#[derive(Debug, Clone, Default)]
+pub struct RelayHealthTracker {
+    relays: Arc<Mutex<HashMap<RelayUrl, RelayHealthEntry>>>,
+}
+
+impl RelayHealthTracker {
+    /// Records the current status of a relay and its connection counts in every pool it's in.
+    /// Returns true if this is the first time the relay is seen connected,
+    /// i.e. its NIP-11 document should be fetched.
+    pub fn record_status(
+        &self,
+        url: &RelayUrl,
+        status: RelayStatus,
+        latency: Option<Duration>,
+        pools: &[(PoolKey, ConnectionCounts)],
+        now: Instant,
+    ) -> bool {
+        let mut relays = self.relays.lock().unwrap();
+        let entry = relays
+            .entry(url.clone())
+            .or_insert_with(|| RelayHealthEntry::new(url));
+
+        let status_string = status.to_string();
+        if entry.health.status != status_string {
+            entry.health.status = status_string;
+            entry.health.last_status_change = Some(Timestamp::now());
+            entry.changed = true;
+        }
+
+        let mut seen = HashMap::new();
+        for (pool, counts) in pools {
+            // Lower counts mean the pool's client was recreated, its stats start over
+            let previous = entry
+                .pools
+                .get(pool)
+                .copied()
+                .filter(|previous| previous.connects <= counts.connects)
+                .unwrap_or_default();
+            let disconnects = counts.disconnects.saturating_sub(previous.disconnects);
+            entry.health.connect_count += counts.connects - previous.connects;
+            entry.health.disconnect_count += disconnects;
+            if disconnects > 0 {
+                entry.changed = true;
+            }
+            for _ in 0..disconnects {
+                entry.disconnects.push_back(now);
+            }
+            seen.insert(*pool, *counts);
+        }
+        entry.pools = seen;
+
+        while entry
+            .disconnects
+            .front()
+            .is_some_and(|at| now.duration_since(*at) > FLAPPING_WINDOW)
+        {
+            entry.disconnects.pop_front();
+        }
+        let flapping = entry.disconnects.len() >= FLAPPING_THRESHOLD;
+        if flapping != entry.health.flapping {
+            entry.health.flapping = flapping;
+            entry.changed = true;
+        }
+
+        if let Some(latency) = latency {
+            entry.health.latency_ms = Some(latency.as_millis() as u64);
+        }
+
+        if status == RelayStatus::Connected && !entry.information_requested {
+            entry.information_requested = true;
+            return true;
+        }
+        false
+    }
+
+    /// Records an error reported by, or about, a relay.
+    pub fn record_error(&self, url: &RelayUrl, error: impl Into<String>) {
+        let mut relays = self.relays.lock().unwrap();
+        let entry = relays
+            .entry(url.clone())
+            .or_insert_with(|| RelayHealthEntry::new(url));
+        entry.health.last_error = Some(error.into());
+        entry.changed = true;
+    }
+
+    /// Records the errors of the relays a publish failed on.
+    pub fn record_output<T>(&self, output: &Output<T>) {
+        for (url, error) in output.failed.iter() {
+            self.record_error(url, error.clone());
+        }
+    }
+
+    fn set_information(&self, url: &RelayUrl, information: RelayInformationDocument) {
+        if let Some(entry) = self.relays.lock().unwrap().get_mut(url) {
+            entry.health.information = Some(information);
+            entry.changed = true;
+        }
+    }
+
+    /// Forgets relays that are no longer in any relay pool.
+    fn retain(&self, urls: &[RelayUrl]) {
+        self.relays
+            .lock()
+            .unwrap()
+            .retain(|url, _| urls.contains(url));
+    }
+
+    /// Returns the health of the relays that changed since the last call.
+    fn take_changed(&self) -> Vec<RelayHealth> {
+        self.relays
+            .lock()
+            .unwrap()
+            .values_mut()
+            .filter(|entry| entry.changed)
+            .map(|entry| {
+                entry.changed = false;
+                entry.health.clone()
+            })
+            .collect()
+    }
+
+    /// Returns the health of all relays, sorted by URL.
+    pub fn all(&self) -> Vec<RelayHealth> {
+        let mut relays: Vec<RelayHealth> = self
+            .relays
+            .lock()
+            .unwrap()
+            .values()
+            .map(|entry| entry.health.clone())
+            .collect();
+        relays.sort_by(|a, b| a.url.cmp(&b.url));
+        relays
+    }
+}
+
+/// What one poll saw of a relay across all relay pools.
+struct RelayObservation {
+    status: RelayStatus,
+    latency: Option<Duration>,
+    pools: Vec<(PoolKey, ConnectionCounts)>,
+}
+
+/// Returns how far along connecting a status is, so that the best status
+/// can be picked when several relay pools contain the same relay.
+fn status_rank(status: &RelayStatus) -> u8 {
+    match status {
+        RelayStatus::Connected => 3,
+        RelayStatus::Connecting | RelayStatus::Pending => 2,
+        RelayStatus::Disconnected => 1,
+        _ => 0,
+    }
+}
+
+impl NostrManager {
+    /// Returns the health of all relays in the relay pools.
+    pub fn relay_status(&self) -> Vec<RelayHealth> {
+        self.relay_health.all()
+    }
+
+    /// Starts watching the relay pools of the default client and the account sessions.
+    /// Emits `relay_status_changed` with a `RelayHealth` whenever a relay's status,
+    /// flapping state, last error or NIP-11 document changes.
+    pub fn start_relay_monitor(&self, events: Arc<dyn EventSink>) {
+        let nostr = self.clone();
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(POLL_INTERVAL);
+            loop {
+                interval.tick().await;
+                nostr.poll_relay_status().await;
+
+                for health in nostr.relay_health.take_changed() {
+                    events.emit_serialized("relay_status_changed", health);
+                }
+            }
+        });
+    }
+
+    async fn poll_relay_status(&self) {
+        let mut clients: Vec<(PoolKey, Client)> = vec![(None, self.default_client.clone())];
+        clients.extend(
+            self.sessions
+                .lock()
+                .await
+                .values()
+                .map(|session| (Some(session.pubkey), session.client.clone())),
+        );
+
+        // The same relay can be in several pools, keep the best status and latency
+        // and the connection counts of each pool
+        let mut relays: HashMap<RelayUrl, RelayObservation> = HashMap::new();
+        for (pool, client) in clients {
+            for (url, relay) in client.relays().await {
+                let status = relay.status();
+                let stats = relay.stats();
+                let latency = stats.latency().await;
+                let counts =
+                    ConnectionCounts::from_stats(stats.success(), status == RelayStatus::Connected);
+                let observation = relays.entry(url).or_insert(RelayObservation {
+                    status,
+                    latency,
+                    pools: Vec::new(),
+                });
+                if status_rank(&status) > status_rank(&observation.status) {
+                    observation.status = status;
+                }
+                observation.latency = match (observation.latency, latency) {
+                    (Some(a), Some(b)) => Some(a.min(b)),
+                    (a, b) => a.or(b),
+                };
+                observation.pools.push((pool, counts));
+            }
+        }
+
+        let proxy = self.settings.lock().await.proxy.clone();
+        let now = Instant::now();
+        for (url, observation) in relays.iter() {
+            if self.relay_health.record_status(
+                url,
+                observation.status,
+                observation.latency,
+                &observation.pools,
+                now,
+            ) {
+                // The NIP-11 request goes through the same proxy as the relay connection
+                if let Ok(proxy) = proxy.proxy_for(url) {
+                    self.fetch_relay_information(url.clone(), proxy);
+                }
+            }
+        }
+        self.relay_health
+            .retain(&relays.into_keys().collect::<Vec<_>>());
+    }
+
+    fn fetch_relay_information(&self, url: RelayUrl, proxy: Option<SocketAddr>) {
+        let relay_health = self.relay_health.clone();
+        tokio::spawn(async move {
+            match RelayInformationDocument::get(url.as_url().clone(), proxy).await {
+                Ok(information) => relay_health.set_information(&url, information),
+                Err(e) => {
+                    tracing::debug!(
+                        target: "whitenoise::nostr_manager::relay_status",
+                        "Error fetching NIP-11 document for {}: {}",
+                        url,
+                        e
+                    );
+                }
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn connected(success: usize) -> [(PoolKey, ConnectionCounts); 1] {
+        [(None, ConnectionCounts::from_stats(success, true))]
+    }
+
+    #[test]
+    fn test_record_status_counts_connections() {
+        let tracker = RelayHealthTracker::default();
+        let url = RelayUrl::parse("wss://relay.example.com").unwrap();
+        let now = Instant::now();
+
+        let connecting = [(None, ConnectionCounts::from_stats(0, false))];
+        assert!(!tracker.record_status(&url, RelayStatus::Connecting, None, &connecting, now));
+        assert!(tracker.record_status(
+            &url,
+            RelayStatus::Connected,
+            Some(Duration::from_millis(42)),
+            &connected(1),
+            now
+        ));
+        // The relay dropped and reconnected between two polls, only its stats show it
+        assert!(!tracker.record_status(&url, RelayStatus::Connected, None, &connected(2), now));
+
+        let health = &tracker.all()[0];
+        assert_eq!(health.connect_count, 2);
+        assert_eq!(health.disconnect_count, 1);
+        assert_eq!(health.latency_ms, Some(42));
+        assert!(!health.flapping);
+    }
+
+    #[test]
+    fn test_record_status_sums_pools() {
+        let tracker = RelayHealthTracker::default();
+        let url = RelayUrl::parse("wss://relay.example.com").unwrap();
+        let session = Some(Keys::generate().public_key());
+        let now = Instant::now();
+
+        let pools = [
+            (None, ConnectionCounts::from_stats(2, true)),
+            (session, ConnectionCounts::from_stats(1, true)),
+        ];
+        tracker.record_status(&url, RelayStatus::Connected, None, &pools, now);
+        // The session was recreated, its stats started over
+        let pools = [
+            (None, ConnectionCounts::from_stats(2, true)),
+            (session, ConnectionCounts::from_stats(0, false)),
+        ];
+        tracker.record_status(&url, RelayStatus::Connected, None, &pools, now);
+        let pools = [
+            (None, ConnectionCounts::from_stats(2, true)),
+            (session, ConnectionCounts::from_stats(1, true)),
+        ];
+        tracker.record_status(&url, RelayStatus::Connected, None, &pools, now);
+
+        let health = &tracker.all()[0];
+        assert_eq!(health.connect_count, 4);
+        assert_eq!(health.disconnect_count, 1);
+    }
+
+    #[test]
+    fn test_record_status_flags_flapping() {
+        let tracker = RelayHealthTracker::default();
+        let url = RelayUrl::parse("wss://relay.example.com").unwrap();
+        let start = Instant::now();
+
+        for i in 1..=FLAPPING_THRESHOLD as u64 {
+            let at = start + Duration::from_secs(i * 10);
+            tracker.record_status(
+                &url,
+                RelayStatus::Connected,
+                None,
+                &connected(i as usize + 1),
+                at,
+            );
+        }
+        assert!(tracker.all()[0].flapping);
+
+        // Once the disconnects are outside the window the relay is no longer flapping
+        let later = start + FLAPPING_WINDOW + Duration::from_secs(60);
+        tracker.record_status(
+            &url,
+            RelayStatus::Connected,
+            None,
+            &connected(FLAPPING_THRESHOLD + 1),
+            later,
+        );
+        assert!(!tracker.all()[0].flapping);
+    }
+}
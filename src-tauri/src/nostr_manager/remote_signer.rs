@@ -0,0 +1,138 @@
+//! NIP-46 remote signer (bunker) support for NostrManager
+//! This handles connecting to a remote signer so that the user's private key never has to be stored locally.
+
+use crate::nostr_manager::{NostrManagerError, Result};
+use nostr_connect::client::NostrConnect;
+use nostr_sdk::prelude::*;
+use std::time::Duration;
+
+/// How long to wait for the remote signer to respond to a request.
+/// This is generous since some signers require the user to approve requests manually.
+pub const REMOTE_SIGNER_TIMEOUT: Duration = Duration::from_secs(120);
+
+/// The app name advertised to remote signers in `nostrconnect://` URIs.
+const APP_NAME: &str = "White Noise";
+
+/// Returns true if the given login string is a NIP-46 `bunker://` or `nostrconnect://` URI.
+pub fn is_remote_signer_uri(value: &str) -> bool {
+    let value = value.trim();
+    value.starts_with("bunker://") || value.starts_with("nostrconnect://")
+}
+
+/// Parses a `bunker://` or `nostrconnect://` URI.
+pub fn parse_remote_signer_uri(value: &str) -> Result<NostrConnectURI> {
+    NostrConnectURI::parse(value.trim())
+        .map_err(|e| NostrManagerError::RemoteSigner(format!("Invalid remote signer URI: {}", e)))
+}
+
+/// Creates a NIP-46 signer for the given URI, talking to the remote signer with `app_keys`.
+/// `connection_mode` should come from [`crate::nostr_manager::NostrManager::connection_mode_for_uri`].
+///
+/// The connection is established lazily on the first request to the signer.
+pub fn connect_remote_signer(
+    uri: NostrConnectURI,
+    app_keys: Keys,
+    connection_mode: ConnectionMode,
+) -> Result<NostrConnect> {
+    let opts = RelayOptions::new().connection_mode(connection_mode);
+    NostrConnect::new(uri, app_keys, REMOTE_SIGNER_TIMEOUT, Some(opts))
+        .map_err(|e| NostrManagerError::RemoteSigner(e.to_string()))
+}
+
+/// Builds a `nostrconnect://` URI that a remote signer can scan to initiate a login.
+pub fn nostr_connect_uri(app_keys: &Keys, relays: &[String]) -> NostrConnectURI {
+    NostrConnectURI::client(
+        app_keys.public_key(),
+        relays.iter().filter_map(|r| Url::parse(r).ok()),
+        APP_NAME,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use nostr_connect::prelude::{NostrConnectKeys, NostrConnectRemoteSigner};
+    use nostr_relay_builder::MockRelay;
+
+    /// Starts a local relay and an in-process bunker signing for `user_keys`.
+    /// Returns the relay (which must be kept alive) and the bunker URI.
+    async fn setup_bunker(user_keys: &Keys) -> (MockRelay, NostrConnectURI) {
+        let relay = MockRelay::run().await.unwrap();
+        let bunker = NostrConnectRemoteSigner::new(
+            NostrConnectKeys {
+                signer: Keys::generate(),
+                user: user_keys.clone(),
+            },
+            [relay.url()],
+            None,
+            None,
+        )
+        .unwrap();
+        let uri = bunker.bunker_uri();
+
+        tokio::spawn(async move {
+            bunker.serve(AutoApprove).await.unwrap();
+        });
+
+        (relay, uri)
+    }
+
+    struct AutoApprove;
+
+    impl nostr_connect::prelude::NostrConnectSignerActions for AutoApprove {
+        fn approve(&self, _public_key: &PublicKey, _req: &nostr_connect::prelude::Request) -> bool {
+            true
+        }
+    }
+
+    #[test]
+    fn test_is_remote_signer_uri() {
+        assert!(is_remote_signer_uri(
+            "bunker://79dff8f82963424e0bb02708a22e44b4980893e3a4be0fa3cb60a43b946764e3?relay=wss://relay.nsec.app"
+        ));
+        assert!(is_remote_signer_uri(
+            " nostrconnect://79dff8f82963424e0bb02708a22e44b4980893e3a4be0fa3cb60a43b946764e3?relay=wss://relay.nsec.app"
+        ));
+        assert!(!is_remote_signer_uri(
+            "nsec1d4ed5x49d7p24xn63flj4985dc4gpfngdhtqcxpth0ywhm6czxcs5l2exj"
+        ));
+    }
+
+    #[test]
+    fn test_nostr_connect_uri_round_trip() {
+        let app_keys = Keys::generate();
+        let uri = nostr_connect_uri(&app_keys, &["wss://relay.damus.io".to_string()]);
+        let parsed = parse_remote_signer_uri(&uri.to_string()).unwrap();
+        assert_eq!(uri, parsed);
+    }
+
+    #[tokio::test]
+    async fn test_remote_signer_signs_and_decrypts() {
+        let user_keys = Keys::generate();
+        let (_relay, uri) = setup_bunker(&user_keys).await;
+
+        let signer = connect_remote_signer(uri, Keys::generate(), ConnectionMode::Direct).unwrap();
+        assert_eq!(
+            signer.get_public_key().await.unwrap(),
+            user_keys.public_key()
+        );
+
+        // Events are signed by the user's key
+        let event = EventBuilder::text_note("hello")
+            .sign(&signer)
+            .await
+            .unwrap();
+        assert_eq!(event.pubkey, user_keys.public_key());
+        assert!(event.verify().is_ok());
+
+        // Gift-wraps addressed to the user can be unwrapped via NIP-44 on the remote signer
+        let sender = Keys::generate();
+        let rumor = EventBuilder::new(Kind::MlsWelcome, "welcome");
+        let gift_wrap = EventBuilder::gift_wrap(&sender, &user_keys.public_key(), rumor, [])
+            .await
+            .unwrap();
+        let unwrapped = extract_rumor(&signer, &gift_wrap).await.unwrap();
+        assert_eq!(unwrapped.sender, sender.public_key());
+        assert_eq!(unwrapped.rumor.content, "welcome");
+    }
+}
@@ -0,0 +1,335 @@
+//! Outbox routing functions for NostrManager
+//! Users publish their events to the relays on their own relay lists, so instead of asking whatever
+//! relays we happen to be connected to, we resolve a user's NIP-65 write relays (kind 10002) and key
+//! package relays (kind 10051) first and fetch their events from there.
+//! Resolved relay lists are cached for `ROUTE_TTL`, users without relay lists for `EMPTY_ROUTE_TTL`.
+//! Network profiles that don't use the users' relays fetch everything from the relays in the pool.
+
+use crate::nostr_manager::{NostrManager, NostrManagerError, Result};
+use nostr_sdk::prelude::*;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::task::JoinSet;
+
+/// How long resolved relay lists are cached.
+const ROUTE_TTL: Duration = Duration::from_secs(600);
+
+/// How long it's cached that a user has no relay lists, they may just not have reached us yet.
+const EMPTY_ROUTE_TTL: Duration = Duration::from_secs(60);
+
+/// The maximum number of relays of each list we fetch from per user.
+const MAX_RELAYS_PER_LIST: usize = 3;
+
+/// The relays a user publishes to, taken from their relay lists.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct UserRelays {
+    /// NIP-65 write relays (unmarked or marked `write`)
+    pub write: Vec<String>,
+    /// Key package relays (kind 10051)
+    pub key_package: Vec<String>,
+}
+
+impl UserRelays {
+    /// Builds the relays of each author from their newest relay list events.
+    fn from_events<'a>(events: impl IntoIterator<Item = &'a Event>) -> HashMap<PublicKey, Self> {
+        let mut newest: HashMap<(PublicKey, Kind), &Event> = HashMap::new();
+        for event in events {
+            newest
+                .entry((event.pubkey, event.kind))
+                .and_modify(|current| {
+                    if event.created_at > current.created_at {
+                        *current = event;
+                    }
+                })
+                .or_insert(event);
+        }
+
+        let mut relays: HashMap<PublicKey, Self> = HashMap::new();
+        for ((pubkey, kind), event) in newest {
+            let user_relays = relays.entry(pubkey).or_default();
+            match kind {
+                Kind::RelayList => {
+                    user_relays.write = nip65::extract_relay_list(event)
+                        .filter(|(_, metadata)| !matches!(metadata, Some(RelayMetadata::Read)))
+                        .map(|(url, _)| url.to_string())
+                        .collect();
+                }
+                Kind::MlsKeyPackageRelays => {
+                    user_relays.key_package = event
+                        .tags
+                        .iter()
+                        .filter(|tag| tag.kind() == TagKind::Relay)
+                        .filter_map(|tag| tag.content())
+                        .filter_map(|url| RelayUrl::parse(url).ok())
+                        .map(|url| url.to_string())
+                        .collect();
+                }
+                _ => {}
+            }
+        }
+        relays
+    }
+
+    /// The relays to fetch the user's events from: a few of their write
+    /// relays and a few of their key package relays.
+    pub fn outboxes(&self) -> Vec<String> {
+        let mut relays: Vec<String> = self
+            .write
+            .iter()
+            .take(MAX_RELAYS_PER_LIST)
+            .chain(self.key_package.iter().take(MAX_RELAYS_PER_LIST))
+            .cloned()
+            .collect();
+        relays.sort();
+        relays.dedup();
+        relays
+    }
+}
+
+/// Cache of resolved relay lists, keyed by user.
+#[derive(Debug, Clone, Default)]
+pub struct RelayRoutes {
+    routes: Arc<Mutex<HashMap<PublicKey, (Instant, UserRelays)>>>,
+}
+
+impl RelayRoutes {
+    fn cached(&self, pubkeys: &[PublicKey]) -> HashMap<PublicKey, UserRelays> {
+        let routes = self.routes.lock().unwrap();
+        pubkeys
+            .iter()
+            .filter_map(|pubkey| {
+                routes
+                    .get(pubkey)
+                    .filter(|(resolved_at, relays)| {
+                        let ttl = if *relays == UserRelays::default() {
+                            EMPTY_ROUTE_TTL
+                        } else {
+                            ROUTE_TTL
+                        };
+                        resolved_at.elapsed() < ttl
+                    })
+                    .map(|(_, relays)| (*pubkey, relays.clone()))
+            })
+            .collect()
+    }
+
+    fn insert(&self, pubkey: PublicKey, relays: UserRelays) {
+        self.routes
+            .lock()
+            .unwrap()
+            .insert(pubkey, (Instant::now(), relays));
+    }
+
+    /// Forgets all resolved relay lists.
+    pub fn clear(&self) {
+        self.routes.lock().unwrap().clear();
+    }
+}
+
+impl NostrManager {
+    /// Resolves the relay lists of the given users, from the cache where possible.
+    ///
+    /// Relay lists that aren't cached are read from the database and fetched from the
+    /// relays in the pool in a single request. If the fetch fails we use what's stored,
+    /// without caching it.
+    pub async fn resolve_user_relays(
+        &self,
+        pubkeys: &[PublicKey],
+    ) -> Result<HashMap<PublicKey, UserRelays>> {
+        let mut resolved = self.relay_routes.cached(pubkeys);
+        let missing: Vec<PublicKey> = pubkeys
+            .iter()
+            .filter(|pubkey| !resolved.contains_key(pubkey))
+            .copied()
+            .collect();
+        if missing.is_empty() {
+            return Ok(resolved);
+        }
+
+        let filter = Filter::new()
+            .kinds(vec![Kind::RelayList, Kind::MlsKeyPackageRelays])
+            .authors(missing.clone());
+        let client = self.client();
+        let mut events = client.database().query(vec![filter.clone()]).await?;
+        let fetched = match client
+            .fetch_events(vec![filter], self.timeout().await?)
+            .await
+        {
+            Ok(fetched) => {
+                events = events.merge(fetched);
+                true
+            }
+            Err(e) => {
+                tracing::warn!(
+                    target: "whitenoise::nostr_manager::routing",
+                    "Error fetching relay lists, using stored ones: {}",
+                    e
+                );
+                false
+            }
+        };
+
+        let mut routes = UserRelays::from_events(events.iter());
+        for pubkey in missing {
+            let relays = routes.remove(&pubkey).unwrap_or_default();
+            if fetched {
+                self.relay_routes.insert(pubkey, relays.clone());
+            }
+            resolved.insert(pubkey, relays);
+        }
+
+        Ok(resolved)
+    }
+
+    /// Fetches events of the given kinds by the given authors from the authors' own relays.
+    ///
+    /// Authors are grouped by relay and all relays are queried in parallel. Authors without
+    /// relay lists are fetched from the relays in the pool. Relays that fail are logged and skipped.
+    /// Outbox relays are connected to through a temporary client, so they don't pile up in the pool
+    /// and concurrent fetches can't disconnect each other's relays.
+    ///
+    /// If the network profile doesn't use the users' relays, everything is fetched from the pool.
+    pub async fn fetch_events_from_outboxes(
+        &self,
+        authors: &[PublicKey],
+        kinds: Vec<Kind>,
+    ) -> Result<Events> {
+        let client = self.client();
+        let timeout = self.timeout().await?;
+
+        if !self.network_profile().await.use_user_relays {
+            let filter = Filter::new().kinds(kinds).authors(authors.to_vec());
+            return Ok(client
+                .fetch_events(vec![filter], timeout)
+                .await
+                .map_err(NostrManagerError::from)?);
+        }
+
+        let routes = self.resolve_user_relays(authors).await?;
+
+        let mut authors_by_relay: HashMap<String, Vec<PublicKey>> = HashMap::new();
+        let mut unrouted_authors: Vec<PublicKey> = Vec::new();
+        for author in authors {
+            let relays = routes
+                .get(author)
+                .map(UserRelays::outboxes)
+                .unwrap_or_default();
+            if relays.is_empty() {
+                unrouted_authors.push(*author);
+            }
+            for relay in relays {
+                authors_by_relay.entry(relay).or_default().push(*author);
+            }
+        }
+
+        let mut requests = JoinSet::new();
+
+        // Relays refused by the proxy settings are skipped
+        let outbox_client = self.temporary_client().await;
+        let relays: Vec<String> = authors_by_relay.keys().cloned().collect();
+        let added = match self.add_relays_to(&outbox_client, &relays, false).await {
+            Ok(added) => added,
+            Err(e) => {
+                outbox_client.shutdown().await?;
+                return Err(e);
+            }
+        };
+        if !added.is_empty() {
+            outbox_client.connect().await;
+        }
+
+        for (relay, authors) in authors_by_relay
+            .into_iter()
+            .filter(|(relay, _)| added.contains(relay))
+        {
+            let client = outbox_client.clone();
+            let filter = Filter::new().kinds(kinds.clone()).authors(authors);
+            requests.spawn(async move {
+                client
+                    .fetch_events_from(vec![relay], vec![filter], timeout)
+                    .await
+            });
+        }
+
+        if !unrouted_authors.is_empty() {
+            let client = client.clone();
+            let filter = Filter::new().kinds(kinds.clone()).authors(unrouted_authors);
+            requests.spawn(async move { client.fetch_events(vec![filter], timeout).await });
+        }
+
+        let mut events = Events::new(&[Filter::new().kinds(kinds).authors(authors.to_vec())]);
+        while let Some(result) = requests.join_next().await {
+            match result {
+                Ok(Ok(fetched)) => events = events.merge(fetched),
+                Ok(Err(e)) => {
+                    tracing::warn!(
+                        target: "whitenoise::nostr_manager::routing",
+                        "Error fetching events from outbox relay: {}",
+                        e
+                    );
+                }
+                Err(e) => {
+                    tracing::error!(
+                        target: "whitenoise::nostr_manager::routing",
+                        "Outbox fetch task failed: {}",
+                        e
+                    );
+                }
+            }
+        }
+
+        if let Err(e) = outbox_client.shutdown().await {
+            tracing::warn!(
+                target: "whitenoise::nostr_manager::routing",
+                "Error shutting down outbox client: {}",
+                e
+            );
+        }
+
+        Ok(events)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_user_relays_from_events() {
+        let keys = Keys::generate();
+        let old_relay = RelayUrl::parse("wss://old.example.com").unwrap();
+        let write_relay = RelayUrl::parse("wss://write.example.com").unwrap();
+        let read_relay = RelayUrl::parse("wss://read.example.com").unwrap();
+        let key_package_relay = RelayUrl::parse("wss://keys.example.com").unwrap();
+
+        let old_list = EventBuilder::relay_list(vec![(old_relay, None)])
+            .custom_created_at(Timestamp::from(1000))
+            .sign_with_keys(&keys)
+            .unwrap();
+        let new_list = EventBuilder::relay_list(vec![
+            (write_relay.clone(), None),
+            (read_relay, Some(RelayMetadata::Read)),
+        ])
+        .custom_created_at(Timestamp::from(2000))
+        .sign_with_keys(&keys)
+        .unwrap();
+        let key_package_list = EventBuilder::new(Kind::MlsKeyPackageRelays, "")
+            .tags(vec![Tag::custom(
+                TagKind::Relay,
+                [key_package_relay.to_string()],
+            )])
+            .sign_with_keys(&keys)
+            .unwrap();
+
+        let relays = UserRelays::from_events([&old_list, &new_list, &key_package_list]);
+        let user_relays = relays.get(&keys.public_key()).unwrap();
+
+        assert_eq!(user_relays.write, vec![write_relay.to_string()]);
+        assert_eq!(user_relays.key_package, vec![key_package_relay.to_string()]);
+        assert_eq!(
+            user_relays.outboxes(),
+            vec![key_package_relay.to_string(), write_relay.to_string()]
+        );
+    }
+}
@@ -9,13 +9,13 @@ impl NostrManager {
     pub async fn search_users(
         &self,
         query: String,
-        wn: tauri::State<'_, Whitenoise>,
+        wn: &Whitenoise,
     ) -> Result<HashMap<String, EnrichedContact>> {
         let filter = Filter::new().kind(Kind::Metadata).search(query);
 
         let stored_events = wn
             .nostr
-            .client
+            .client()
             .database()
             .query(vec![filter.clone()])
             .await
@@ -23,7 +23,7 @@ impl NostrManager {
 
         let fetched_events = wn
             .nostr
-            .client
+            .client()
             .fetch_events(vec![filter.clone()], wn.nostr.timeout().await.unwrap())
             .await
             .map_err(NostrManagerError::from)?;
@@ -37,7 +37,7 @@ impl NostrManager {
 
         let enriching_events = wn
             .nostr
-            .client
+            .client()
             .fetch_events(
                 vec![Filter::new().authors(pubkeys).kinds(vec![
                     Kind::MlsKeyPackageRelays,
@@ -0,0 +1,534 @@
+//! Account session functions for NostrManager
+//! Every signed-in account gets a session: a client holding its signer and relays, an MLS instance and
+//! an event processor. Sessions are cached so that switching accounts only changes which session is
+//! active, and so that inactive accounts keep receiving their gift-wraps and group messages.
+//! Once there are more than `MAX_ACCOUNT_SESSIONS` the least recently used inactive session is evicted.
+//!
+//! Every session catches up from the account's sync cursors when it starts and then follows its live
+//! subscriptions. Accounts past the limit aren't synced in the background. They catch up when they
+//! become active again, and a `background_sync_limited` event lists them so the UI can
+//! tell the user.
+
+use crate::accounts::{Account, AccountSettings};
+use crate::nostr_manager::auth::RelayAuthenticator;
+use crate::nostr_manager::event_processor::{EventProcessor, ProcessingContext};
+use crate::nostr_manager::network::NetworkProfile;
+use crate::nostr_manager::sync::scope_since;
+use crate::nostr_manager::{NostrManager, NostrManagerError, Result};
+use crate::relays::RelayType;
+use crate::sync_cursors::SyncScope;
+use crate::Whitenoise;
+use nostr_openmls::NostrMls;
+use nostr_sdk::prelude::*;
+use std::fmt;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, RwLock};
+use std::time::Instant;
+use tokio::sync::Mutex;
+
+/// The maximum number of account sessions that are kept connected at once.
+pub const MAX_ACCOUNT_SESSIONS: usize = 4;
+
+/// The client, MLS instance and event processor of a signed-in account.
+#[derive(Clone)]
+pub struct AccountSession {
+    pub pubkey: PublicKey,
+    pub client: Client,
+    pub nostr_mls: Arc<Mutex<NostrMls>>,
+    /// The account's current settings, shared with the event processor
+    settings: Arc<RwLock<AccountSettings>>,
+    event_processor: Arc<EventProcessor>,
+    /// Whether the session has been the active one before, i.e. whether the account's
+    /// full subscriptions and catch-up fetch have already been started.
+    activated: Arc<AtomicBool>,
+    last_used: Instant,
+}
+
+impl AccountSession {
+    pub(crate) fn event_processor(&self) -> &Arc<EventProcessor> {
+        &self.event_processor
+    }
+
+    /// Whether lockdown mode is enabled for the session's account.
+    pub fn lockdown_mode(&self) -> bool {
+        self.settings.read().unwrap().lockdown_mode
+    }
+}
+
+impl fmt::Debug for AccountSession {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("AccountSession")
+            .field("pubkey", &self.pubkey)
+            .field("last_used", &self.last_used)
+            .finish_non_exhaustive()
+    }
+}
+
+impl NostrManager {
+    /// Returns the client of the active account, or the default client if no account is active.
+    pub fn client(&self) -> Client {
+        match self.active_session.read().unwrap().as_ref() {
+            Some(session) => session.client.clone(),
+            None => self.default_client.clone(),
+        }
+    }
+
+    /// Returns the client options for account sessions, built with the proxy settings.
+    /// Automatic authentication is disabled, NIP-42 challenges are answered by the session's
+    /// `RelayAuthenticator` according to the account's relay auth policy.
+    async fn client_options(&self) -> Options {
+        self.settings
+            .lock()
+            .await
+            .proxy
+            .client_options()
+            .automatic_authentication(false)
+    }
+
+    /// Returns the MLS instance of the active account, if there is one.
+    pub fn active_nostr_mls(&self) -> Option<Arc<Mutex<NostrMls>>> {
+        self.active_session
+            .read()
+            .unwrap()
+            .as_ref()
+            .map(|session| session.nostr_mls.clone())
+    }
+
+    /// Returns the cached session of the account.
+    pub(crate) async fn account_session(&self, pubkey: &PublicKey) -> Result<AccountSession> {
+        self.sessions
+            .lock()
+            .await
+            .get(pubkey)
+            .cloned()
+            .ok_or_else(|| NostrManagerError::AccountError(format!("No session for {}", pubkey)))
+    }
+
+    /// Returns the MLS instance of the account's session, if the account has one.
+    pub async fn session_nostr_mls(&self, pubkey: &PublicKey) -> Option<Arc<Mutex<NostrMls>>> {
+        self.sessions
+            .lock()
+            .await
+            .get(pubkey)
+            .map(|session| session.nostr_mls.clone())
+    }
+
+    /// Applies new settings of an account to its session, if it has one, so that everything the
+    /// session does from now on follows them, including the lockdown mode of the active account.
+    pub async fn set_session_settings(&self, pubkey: &PublicKey, settings: &AccountSettings) {
+        if let Some(session) = self.sessions.lock().await.get(pubkey) {
+            *session.settings.write().unwrap() = settings.clone();
+        }
+    }
+
+    /// Returns the event processor of the active account.
+    pub(crate) fn active_event_processor(&self) -> Result<Arc<EventProcessor>> {
+        self.active_session
+            .read()
+            .unwrap()
+            .as_ref()
+            .map(|session| session.event_processor.clone())
+            .ok_or_else(|| NostrManagerError::AccountError("No active account".to_string()))
+    }
+
+    fn active_pubkey(&self) -> Option<PublicKey> {
+        self.active_session
+            .read()
+            .unwrap()
+            .as_ref()
+            .map(|session| session.pubkey)
+    }
+
+    /// Makes the account's session the active one, creating the session if it isn't cached.
+    ///
+    /// Returns true if this is the first time the session became active, in which case the
+    /// account's full subscriptions and catch-up fetch still need to be started.
+    pub async fn activate_session(&self, account: &Account, wn: &Whitenoise) -> Result<bool> {
+        let session = self.session(account.clone(), wn).await?;
+        let first_activation = !session.activated.swap(true, Ordering::SeqCst);
+        *self.active_session.write().unwrap() = Some(session);
+        Ok(first_activation)
+    }
+
+    /// Starts sessions for the most recently used accounts other than the active one, so that
+    /// they are synced in the background. Accounts that already have a session are left alone.
+    ///
+    /// Accounts past `MAX_ACCOUNT_SESSIONS` are logged and reported with a
+    /// `background_sync_limited` event.
+    ///
+    /// Errors for a single account are logged and skipped so that, for example, an unreachable
+    /// remote signer doesn't stop the other accounts from syncing.
+    pub async fn start_background_sessions(
+        &self,
+        active_pubkey: &PublicKey,
+        wn: &Whitenoise,
+    ) -> Result<()> {
+        let mut accounts = Account::all(wn)
+            .await
+            .map_err(|e| NostrManagerError::AccountError(e.to_string()))?;
+        accounts.sort_by(|a, b| b.last_used.cmp(&a.last_used));
+        accounts.retain(|account| account.pubkey != *active_pubkey);
+
+        let skipped: Vec<String> = accounts
+            .iter()
+            .skip(MAX_ACCOUNT_SESSIONS - 1)
+            .map(|account| account.pubkey.to_hex())
+            .collect();
+        if !skipped.is_empty() {
+            tracing::info!(
+                target: "whitenoise::nostr_manager::sessions",
+                "Not syncing {} accounts in the background, at most {} sessions are kept: {:?}",
+                skipped.len(),
+                MAX_ACCOUNT_SESSIONS,
+                skipped
+            );
+            wn.emit(
+                "background_sync_limited",
+                serde_json::json!({ "max_sessions": MAX_ACCOUNT_SESSIONS, "pubkeys": skipped }),
+            );
+        }
+
+        for account in accounts.into_iter().take(MAX_ACCOUNT_SESSIONS - 1) {
+            if self.sessions.lock().await.contains_key(&account.pubkey) {
+                continue;
+            }
+
+            let pubkey = account.pubkey;
+            match self.session(account, wn).await {
+                Ok(_) => {
+                    let wn = wn.clone();
+                    tokio::spawn(async move {
+                        wn.nostr.catch_up(pubkey, &wn).await;
+                    });
+                }
+                Err(e) => {
+                    tracing::error!(
+                        target: "whitenoise::nostr_manager::sessions",
+                        "Error starting session for {}: {}",
+                        pubkey,
+                        e
+                    );
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Returns the cached session of the account, or creates one.
+    async fn session(&self, account: Account, wn: &Whitenoise) -> Result<AccountSession> {
+        if let Some(session) = self.sessions.lock().await.get_mut(&account.pubkey) {
+            session.last_used = Instant::now();
+            return Ok(session.clone());
+        }
+
+        let session = self.create_session(account, wn).await?;
+
+        {
+            let mut sessions = self.sessions.lock().await;
+            // Another task may have created a session for the same account in the meantime
+            if let Some(existing) = sessions.get(&session.pubkey).cloned() {
+                drop(sessions);
+                Self::shutdown_session(session).await?;
+                return Ok(existing);
+            }
+            sessions.insert(session.pubkey, session.clone());
+        }
+
+        self.evict_idle_sessions().await?;
+
+        Ok(session)
+    }
+
+    async fn create_session(&self, account: Account, wn: &Whitenoise) -> Result<AccountSession> {
+        let signer = account
+            .signer(wn)
+            .await
+            .map_err(|e| NostrManagerError::SecretsStoreError(e.to_string()))?;
+
+        // All sessions share the nostr database
+        let client = Client::builder()
+            .signer(signer)
+            .database(self.default_client.database().clone())
+            .opts(self.client_options().await)
+            .build();
+
+        let group_ids = account
+            .nostr_group_ids(wn)
+            .await
+            .map_err(|e| NostrManagerError::AccountError(e.to_string()))?;
+
+        let pubkey = account.pubkey;
+
+        let relays = self
+            .session_relays(&account, &self.network_profile().await, wn)
+            .await?;
+
+        let nostr_mls = Arc::new(Mutex::new(NostrMls::new(
+            wn.data_dir.clone(),
+            Some(pubkey.to_hex()),
+        )));
+        let settings = Arc::new(RwLock::new(account.settings.clone()));
+        let context = ProcessingContext {
+            account,
+            settings: settings.clone(),
+            client: client.clone(),
+            nostr_mls: nostr_mls.clone(),
+        };
+        let event_processor = Arc::new(EventProcessor::new(wn.clone(), context));
+        let authenticator =
+            RelayAuthenticator::new(pubkey, client.clone(), wn.clone(), self.relay_auth.clone());
+
+        // Start handling notifications before connecting so that no AUTH challenge is missed
+        let notifications_client = client.clone();
+        let notifications_processor = event_processor.clone();
+        let notifications_relay_health = self.relay_health.clone();
+        tokio::spawn(async move {
+            if let Err(e) = notifications_client
+                .handle_notifications(|notification| {
+                    let event_processor = notifications_processor.clone();
+                    let relay_health = notifications_relay_health.clone();
+                    let authenticator = authenticator.clone();
+                    async move {
+                        match notification {
+                            RelayPoolNotification::Event { event, .. } => {
+                                Self::handle_event(&event_processor, *event).await?;
+                                Ok(false)
+                            }
+                            RelayPoolNotification::Message { relay_url, message } => {
+                                authenticator.handle_message(&relay_url, &message).await;
+                                Self::handle_message(&relay_health, relay_url, message)?;
+                                Ok(false)
+                            }
+                            RelayPoolNotification::Shutdown => {
+                                Self::handle_shutdown()?;
+                                Ok(true)
+                            }
+                            _ => {
+                                tracing::debug!(
+                                    target: "whitenoise::nostr_client::handle_notifications",
+                                    "Received unknown notification: {:?}",
+                                    notification
+                                );
+                                Ok(false)
+                            }
+                        }
+                    }
+                })
+                .await
+            {
+                tracing::error!(
+                    target: "whitenoise::nostr_manager::sessions",
+                    "Notification handler error for {}: {:?}",
+                    pubkey,
+                    e
+                );
+            }
+        });
+
+        // Shutting the client down also stops the notification handler
+        if let Err(e) = self
+            .connect_session(&client, &relays, pubkey, group_ids)
+            .await
+        {
+            client.shutdown().await?;
+            return Err(e);
+        }
+
+        tracing::debug!(
+            target: "whitenoise::nostr_manager::sessions",
+            "Started session for {}",
+            pubkey
+        );
+
+        Ok(AccountSession {
+            pubkey,
+            client,
+            nostr_mls,
+            settings,
+            event_processor,
+            activated: Arc::new(AtomicBool::new(false)),
+            last_used: Instant::now(),
+        })
+    }
+
+    /// The relays an account's session connects to under a network profile: the profile's default
+    /// relays and, if the profile uses them, the account's own relays.
+    async fn session_relays(
+        &self,
+        account: &Account,
+        profile: &NetworkProfile,
+        wn: &Whitenoise,
+    ) -> Result<Vec<String>> {
+        let mut relays = profile.default_relays.clone();
+        if profile.use_user_relays {
+            for relay_type in [RelayType::Nostr, RelayType::Inbox, RelayType::KeyPackage] {
+                relays.extend(
+                    account
+                        .relays(relay_type, wn)
+                        .await
+                        .map_err(|e| NostrManagerError::AccountError(e.to_string()))?,
+                );
+            }
+        }
+        relays.sort();
+        relays.dedup();
+        Ok(relays)
+    }
+
+    /// Moves the running account sessions over to a new network profile: relays only the previous
+    /// profile called for are removed, and the ones the new profile calls for are added and connected.
+    ///
+    /// Errors for a single session are logged and skipped.
+    pub(crate) async fn apply_network_profile_to_sessions(
+        &self,
+        previous: &NetworkProfile,
+        profile: &NetworkProfile,
+        wn: &Whitenoise,
+    ) {
+        let sessions: Vec<AccountSession> = self.sessions.lock().await.values().cloned().collect();
+        for session in sessions {
+            if let Err(e) = self
+                .apply_network_profile_to_session(&session, previous, profile, wn)
+                .await
+            {
+                tracing::error!(
+                    target: "whitenoise::nostr_manager::sessions",
+                    "Error applying network profile {} to session of {}: {}",
+                    profile.name,
+                    session.pubkey,
+                    e
+                );
+            }
+        }
+    }
+
+    async fn apply_network_profile_to_session(
+        &self,
+        session: &AccountSession,
+        previous: &NetworkProfile,
+        profile: &NetworkProfile,
+        wn: &Whitenoise,
+    ) -> Result<()> {
+        let account = Account::find_by_pubkey(&session.pubkey, wn)
+            .await
+            .map_err(|e| NostrManagerError::AccountError(e.to_string()))?;
+        let previous_relays = self.session_relays(&account, previous, wn).await?;
+        let relays = self.session_relays(&account, profile, wn).await?;
+
+        for url in previous_relays.iter().filter(|url| !relays.contains(url)) {
+            session.client.remove_relay(url).await?;
+        }
+        // The session's subscriptions are sent to the new relays as they are added
+        for url in self.add_relays_to(&session.client, &relays, false).await? {
+            session.client.connect_relay(&url).await?;
+        }
+        Ok(())
+    }
+
+    /// Connects the session's client to its relays and subscribes to the account's new
+    /// gift-wraps and group messages. Anything older is caught up on from the sync cursors.
+    async fn connect_session(
+        &self,
+        client: &Client,
+        relays: &[String],
+        pubkey: PublicKey,
+        group_ids: Vec<String>,
+    ) -> Result<()> {
+        self.add_relays_to(client, relays, false).await?;
+        client.connect().await;
+
+        let now = Timestamp::now();
+        let giftwraps_since = scope_since(&SyncScope::Kind(Kind::GiftWrap), now);
+        self.subscribe_giftwraps(client, pubkey, giftwraps_since)
+            .await?;
+        if !group_ids.is_empty() {
+            self.subscribe_mls_group_messages_on(client, group_ids, now)
+                .await?;
+        }
+        Ok(())
+    }
+
+    /// Shuts down the least recently used inactive sessions until at most
+    /// `MAX_ACCOUNT_SESSIONS` are left.
+    async fn evict_idle_sessions(&self) -> Result<()> {
+        let active_pubkey = self.active_pubkey();
+        let evicted = {
+            let mut sessions = self.sessions.lock().await;
+            let mut evicted = Vec::new();
+            while sessions.len() > MAX_ACCOUNT_SESSIONS {
+                let Some(pubkey) = sessions
+                    .values()
+                    .filter(|session| Some(session.pubkey) != active_pubkey)
+                    .min_by_key(|session| session.last_used)
+                    .map(|session| session.pubkey)
+                else {
+                    break;
+                };
+                evicted.extend(sessions.remove(&pubkey));
+            }
+            evicted
+        };
+
+        for session in evicted {
+            tracing::debug!(
+                target: "whitenoise::nostr_manager::sessions",
+                "Evicting idle session for {}",
+                session.pubkey
+            );
+            Self::shutdown_session(session).await?;
+        }
+
+        Ok(())
+    }
+
+    /// Stops the session of the given account, e.g. because the account was removed.
+    /// Does nothing if the account has no session.
+    pub async fn stop_session(&self, pubkey: &PublicKey) -> Result<()> {
+        if self.active_pubkey() == Some(*pubkey) {
+            *self.active_session.write().unwrap() = None;
+        }
+        self.relay_auth.remove_account(pubkey);
+
+        let Some(session) = self.sessions.lock().await.remove(pubkey) else {
+            return Ok(());
+        };
+        Self::shutdown_session(session).await?;
+
+        tracing::debug!(
+            target: "whitenoise::nostr_manager::sessions",
+            "Stopped session for {}",
+            pubkey
+        );
+
+        Ok(())
+    }
+
+    /// Stops the sessions of all accounts.
+    pub async fn stop_all_sessions(&self) -> Result<()> {
+        *self.active_session.write().unwrap() = None;
+
+        let sessions: Vec<AccountSession> = self
+            .sessions
+            .lock()
+            .await
+            .drain()
+            .map(|(_, session)| session)
+            .collect();
+        for session in sessions {
+            Self::shutdown_session(session).await?;
+        }
+
+        Ok(())
+    }
+
+    async fn shutdown_session(session: AccountSession) -> Result<()> {
+        session
+            .event_processor
+            .clear_queue()
+            .await
+            .map_err(|e| NostrManagerError::FailedToShutdownEventProcessor(e.to_string()))?;
+        session.client.shutdown().await?;
+        Ok(())
+    }
+}
@@ -1,7 +1,8 @@
 //! Subscription functions for NostrManager
-//! This mostly handles subscribing and processing events as they come in while the user is active.
+//! This mostly handles subscribing and processing events as they come in.
 
-use crate::nostr_manager::event_processor::ProcessableEvent;
+use crate::nostr_manager::event_processor::{EventProcessor, ProcessableEvent};
+use crate::nostr_manager::relay_status::RelayHealthTracker;
 use crate::nostr_manager::{NostrManager, NostrManagerError, Result};
 use nostr_sdk::prelude::*;
 
@@ -14,12 +15,12 @@ impl NostrManager {
             .author(pubkey)
             .since(Timestamp::now());
 
-        Ok(self.client.subscribe(vec![contacts_filter], None).await?)
+        Ok(self.client().subscribe(vec![contacts_filter], None).await?)
     }
 
     async fn subscribe_contacts_metadata(&self) -> Result<Output<SubscriptionId>> {
         let contact_list_pubkeys = self
-            .client
+            .client()
             .get_contact_list_public_keys(self.timeout().await?)
             .await?;
 
@@ -29,7 +30,7 @@ impl NostrManager {
             .since(Timestamp::now());
 
         Ok(self
-            .client
+            .client()
             .subscribe(vec![contact_metadata_filter], None)
             .await?)
     }
@@ -40,7 +41,7 @@ impl NostrManager {
             .author(pubkey)
             .since(Timestamp::now());
 
-        Ok(self.client.subscribe(vec![metadata_filter], None).await?)
+        Ok(self.client().subscribe(vec![metadata_filter], None).await?)
     }
 
     async fn subscribe_relay_list(&self, pubkey: PublicKey) -> Result<Output<SubscriptionId>> {
@@ -49,7 +50,10 @@ impl NostrManager {
             .author(pubkey)
             .since(Timestamp::now());
 
-        Ok(self.client.subscribe(vec![relay_list_filter], None).await?)
+        Ok(self
+            .client()
+            .subscribe(vec![relay_list_filter], None)
+            .await?)
     }
 
     async fn subscribe_inbox_relay_list(
@@ -62,130 +66,108 @@ impl NostrManager {
             .since(Timestamp::now());
 
         Ok(self
-            .client
+            .client()
             .subscribe(vec![inbox_relay_list_filter], None)
             .await?)
     }
 
-    async fn subscribe_giftwraps(&self, pubkey: PublicKey) -> Result<Output<SubscriptionId>> {
-        // This is a hack to get the client to do the initial authenticate on relays that require it.
-        // https://github.com/rust-nostr/nostr/issues/509
-        let null_filter = Filter::new().kind(Kind::GiftWrap).pubkey(pubkey).limit(0);
-        self.client
-            .fetch_events(vec![null_filter], self.timeout().await?)
-            .await?;
-
+    pub(crate) async fn subscribe_giftwraps(
+        &self,
+        client: &Client,
+        pubkey: PublicKey,
+        since: Timestamp,
+    ) -> Result<Output<SubscriptionId>> {
+        // Inbox relays usually require NIP-42 authentication to serve gift-wraps. If they close
+        // the subscription, it's sent again once the session's `RelayAuthenticator` has authenticated.
         let giftwrap_filter = Filter::new()
             .kind(Kind::GiftWrap)
             .pubkey(pubkey)
-            .since(Timestamp::now());
+            .since(since);
 
-        Ok(self.client.subscribe(vec![giftwrap_filter], None).await?)
+        Ok(client.subscribe(vec![giftwrap_filter], None).await?)
     }
 
+    /// Subscribes the active account to new messages in the given groups.
+    /// This replaces the account's previous group message subscription.
     pub async fn subscribe_mls_group_messages(&self, group_ids: Vec<String>) -> Result<Output<()>> {
+        self.subscribe_mls_group_messages_on(&self.client(), group_ids, Timestamp::now())
+            .await
+    }
+
+    pub(crate) async fn subscribe_mls_group_messages_on(
+        &self,
+        client: &Client,
+        group_ids: Vec<String>,
+        since: Timestamp,
+    ) -> Result<Output<()>> {
         let sub_id = SubscriptionId::new(MLS_MESSAGES_SUB);
         let mls_message_filter = Filter::new()
             .kind(Kind::MlsGroupMessage)
             .custom_tag(SingleLetterTag::lowercase(Alphabet::H), group_ids)
-            .since(Timestamp::now());
+            .since(since);
 
-        Ok(self
-            .client
+        Ok(client
             .subscribe_with_id(sub_id, vec![mls_message_filter], None)
             .await?)
     }
 
-    pub async fn setup_subscriptions(
-        &self,
-        pubkey: PublicKey,
-        nostr_group_ids: Vec<String>,
-    ) -> Result<()> {
+    /// Sets up the subscriptions the active account needs on top of the gift-wrap and group message
+    /// subscriptions that every account session has.
+    pub async fn setup_subscriptions(&self, pubkey: PublicKey) -> Result<()> {
         self.subscribe_contact_list(pubkey).await?;
-        self.subscribe_contacts_metadata().await?;
+        if !self.lockdown_mode() {
+            self.subscribe_contacts_metadata().await?;
+        }
         self.subscribe_metadata(pubkey).await?;
         self.subscribe_relay_list(pubkey).await?;
         self.subscribe_inbox_relay_list(pubkey).await?;
-        self.subscribe_giftwraps(pubkey).await?;
-
-        if !nostr_group_ids.is_empty() {
-            self.subscribe_mls_group_messages(nostr_group_ids).await?;
-        }
-
-        if let Err(e) = self
-            .client
-            .handle_notifications(|notification| async {
-                match notification {
-                    RelayPoolNotification::Event { event, .. } => {
-                        self.handle_event(*event).await?;
-                        Ok(false)
-                    }
-                    RelayPoolNotification::Message { relay_url, message } => {
-                        self.handle_message(relay_url, message)?;
-                        Ok(false)
-                    }
-                    RelayPoolNotification::Shutdown => {
-                        self.handle_shutdown()?;
-                        Ok(true)
-                    }
-                    _ => {
-                        tracing::debug!(
-                            target: "whitenoise::nostr_client::handle_notifications",
-                            "Received unknown notification: {:?}",
-                            notification
-                        );
-                        Ok(false)
-                    }
-                }
-            })
-            .await
-        {
-            tracing::error!(
-                target: "whitenoise::nostr_client::handle_notifications",
-                "Notification handler error: {:?}",
-                e
-            );
-        }
-
         Ok(())
     }
 
     // Handle events
-    async fn handle_event(&self, event: Event) -> Result<()> {
+    pub(crate) async fn handle_event(event_processor: &EventProcessor, event: Event) -> Result<()> {
         tracing::debug!(
             target: "whitenoise::nostr_client::subscriptions::handle_event",
             "Received event: {:?}",
             event
         );
-        match event.kind {
-            Kind::GiftWrap => {
-                self.event_processor
-                    .lock()
-                    .await
-                    .queue_event(ProcessableEvent::GiftWrap(event))
-                    .await
-                    .map_err(|e| NostrManagerError::FailedToQueueEvent(e.to_string()))?;
-            }
-            Kind::MlsGroupMessage => {
-                self.event_processor
-                    .lock()
-                    .await
-                    .queue_event(ProcessableEvent::MlsMessage(event))
-                    .await
-                    .map_err(|e| NostrManagerError::FailedToQueueEvent(e.to_string()))?;
-            }
-            _ => {}
-        }
+        let event = match event.kind {
+            Kind::GiftWrap => ProcessableEvent::GiftWrap(event),
+            Kind::MlsGroupMessage => ProcessableEvent::MlsMessage(event),
+            _ => return Ok(()),
+        };
+        event_processor
+            .queue_event(event)
+            .await
+            .map_err(|e| NostrManagerError::FailedToQueueEvent(e.to_string()))?;
         Ok(())
     }
 
-    // Handle other types of notifications
-    fn handle_message(&self, relay_url: RelayUrl, message: RelayMessage) -> Result<()> {
-        let variant_name = match message {
+    // Handle other types of notifications, errors reported by relays are recorded in the relay status
+    pub(crate) fn handle_message(
+        relay_health: &RelayHealthTracker,
+        relay_url: RelayUrl,
+        message: RelayMessage,
+    ) -> Result<()> {
+        let variant_name = match &message {
             RelayMessage::Event { .. } => "Event",
+            RelayMessage::Ok {
+                status: false,
+                message,
+                ..
+            } => {
+                relay_health.record_error(&relay_url, message.clone());
+                "Ok"
+            }
             RelayMessage::Ok { .. } => "Ok",
-            RelayMessage::Notice { .. } => "Notice",
-            RelayMessage::Closed { .. } => "Closed",
+            RelayMessage::Notice { message } => {
+                relay_health.record_error(&relay_url, message.clone());
+                "Notice"
+            }
+            RelayMessage::Closed { message, .. } => {
+                relay_health.record_error(&relay_url, message.clone());
+                "Closed"
+            }
             RelayMessage::EndOfStoredEvents(_) => "EndOfStoredEvents",
             RelayMessage::Auth { .. } => "Auth",
             RelayMessage::Count { .. } => "Count",
@@ -201,7 +183,7 @@ impl NostrManager {
         Ok(())
     }
 
-    fn handle_shutdown(&self) -> Result<()> {
+    pub(crate) fn handle_shutdown() -> Result<()> {
         tracing::debug!(
             target: "whitenoise::nostr_client::handle_notifications",
             "Relay pool shutdown"
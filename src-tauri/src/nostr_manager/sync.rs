@@ -1,356 +1,465 @@
 //! Negentropy syncing functions for NostrManager
 //! Negentropy is a fast/efficient way to fetch only the events that we don't have.
-//! It's currently only suppoted by strfry relays so this is not used as extensively as it will be in the future.
-
-#![allow(unused)]
+//! It's only supported by some relays (e.g. strfry), so every sync falls back to a filtered fetch
+//! for the relays that don't support it. Relays whose sync fails are remembered as unsupported
+//! for `UNSUPPORTED_TTL` so that we don't try them again on every catch-up.
+//! Catch-up is tracked per kind and per group with sync cursors, see `crate::sync_cursors`.
 
+use crate::accounts::Account;
+use crate::nostr_manager::event_processor::ProcessableEvent;
+use crate::nostr_manager::sessions::AccountSession;
 use crate::nostr_manager::{NostrManager, NostrManagerError, Result};
+use crate::sync_cursors::{SyncCursor, SyncGap, SyncScope};
+use crate::Whitenoise;
 use nostr_sdk::prelude::*;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::task::JoinSet;
 
-impl NostrManager {
-    pub async fn sync_for_user(
-        &self,
-        pubkey: PublicKey,
-        last_synced: Timestamp,
-        group_ids: Vec<String>,
-    ) -> Result<()> {
-        self.sync_user_metadata(pubkey, last_synced).await?;
-        self.sync_contacts(last_synced).await?;
-        self.sync_contacts_metadata(last_synced).await?;
-        self.sync_user_relays(pubkey, last_synced).await?;
-        self.sync_user_inbox_relays(pubkey, last_synced).await?;
-        self.sync_user_key_package_relays(pubkey, last_synced)
-            .await?;
-        self.sync_user_key_packages(pubkey, last_synced).await?;
-        self.sync_user_giftwrapped_events(pubkey, last_synced)
-            .await?;
-        self.sync_group_messages(last_synced, group_ids).await?;
-        Ok(())
+/// How long a relay whose sync failed is fetched from instead.
+const UNSUPPORTED_TTL: Duration = Duration::from_secs(3600);
+
+/// How far before a cursor gift-wraps are synced from. NIP-59 backdates the `created_at` of gift-wraps
+/// by up to two days, so a gift-wrap received after the last sync can be older than the cursor.
+/// Invites that were already processed are skipped by the event processor.
+const GIFT_WRAP_LOOKBACK: Duration = Duration::from_secs(2 * 24 * 3600);
+
+/// The relays known not to support negentropy, and since when.
+#[derive(Debug, Clone, Default)]
+pub struct NegentropySupport {
+    unsupported: Arc<Mutex<HashMap<RelayUrl, Instant>>>,
+}
+
+impl NegentropySupport {
+    fn is_supported(&self, url: &RelayUrl, now: Instant) -> bool {
+        let mut unsupported = self.unsupported.lock().unwrap();
+        match unsupported.get(url) {
+            Some(since) if now.duration_since(*since) < UNSUPPORTED_TTL => false,
+            Some(_) => {
+                unsupported.remove(url);
+                true
+            }
+            None => true,
+        }
     }
 
-    pub async fn sync_user_metadata(
-        &self,
-        pubkey: PublicKey,
-        last_synced: Timestamp,
-    ) -> Result<()> {
-        let filter = Filter::new()
-            .kind(Kind::Metadata)
-            .author(pubkey)
-            .since(last_synced)
-            .until(Timestamp::now());
-        let output = self
-            .client
-            .sync(filter, &nostr_sdk::SyncOptions::default())
-            .await?;
-        tracing::debug!(
-            target: "whitenoise::nostr_client::sync_user_metadata",
-            "SUCCESS: {:?}",
-            output.success
-        );
-        tracing::debug!(
-            target: "whitenoise::nostr_client::sync_user_metadata",
-            "FAILED: {:?}",
-            output.failed
-        );
-        tracing::debug!(
-            target: "whitenoise::nostr_client::sync_user_metadata",
-            "VALUES: local: {:?}, remote: {:?}, sent: {:?}, received: {:?}, send_failed: {:?}",
-            output.val.local.len(),
-            output.val.remote.len(),
-            output.val.sent.len(),
-            output.val.received.len(),
-            output.val.send_failures.len()
-        );
-        Ok(())
+    fn set_unsupported(&self, url: RelayUrl) {
+        self.unsupported.lock().unwrap().insert(url, Instant::now());
     }
+}
 
-    async fn sync_user_relays(&self, pubkey: PublicKey, last_synced: Timestamp) -> Result<()> {
-        let filter = Filter::new()
-            .author(pubkey)
-            .kind(Kind::RelayList)
-            .since(last_synced)
-            .until(Timestamp::now());
-        let output = self
-            .client
-            .sync(filter, &nostr_sdk::SyncOptions::default())
-            .await?;
-        tracing::debug!(
-            target: "whitenoise::nostr_client::sync_user_relays",
-            "SUCCESS: {:?}",
-            output.success
-        );
-        tracing::debug!(
-            target: "whitenoise::nostr_client::sync_user_relays",
-            "FAILED: {:?}",
-            output.failed
-        );
+/// The result of syncing a filter with a set of relays.
+#[derive(Debug)]
+pub struct SyncedEvents {
+    /// All stored events matching the filter, including the ones we already had
+    pub events: Events,
+    /// The relays that could neither be synced nor fetched from
+    pub unreachable: Vec<RelayUrl>,
+}
+
+impl NostrManager {
+    /// Catches up on the account's groups and events through its session and logs the outcome.
+    /// Used when a session starts, so that it has everything up to its live subscriptions.
+    pub async fn catch_up(&self, pubkey: PublicKey, wn: &Whitenoise) {
         tracing::debug!(
-            target: "whitenoise::nostr_client::sync_user_relays",
-            "VALUES: local: {:?}, remote: {:?}, sent: {:?}, received: {:?}, send_failed: {:?}",
-            output.val.local.len(),
-            output.val.remote.len(),
-            output.val.sent.len(),
-            output.val.received.len(),
-            output.val.send_failures.len()
+            target: "whitenoise::nostr_manager::sync",
+            "Starting catch-up for {}",
+            pubkey
         );
-        Ok(())
+        let group_ids = match Account::find_by_pubkey(&pubkey, wn).await {
+            Ok(account) => account.nostr_group_ids(wn).await,
+            Err(e) => Err(e),
+        };
+        let result = match group_ids {
+            Ok(group_ids) => self.sync_for_user(pubkey, group_ids, wn).await,
+            Err(e) => Err(NostrManagerError::AccountError(e.to_string())),
+        };
+        match result {
+            Ok(()) => tracing::debug!(
+                target: "whitenoise::nostr_manager::sync",
+                "Catch-up completed for {}",
+                pubkey
+            ),
+            Err(e) => tracing::error!(
+                target: "whitenoise::nostr_manager::sync",
+                "Error in catch-up for {}: {}",
+                pubkey,
+                e
+            ),
+        }
     }
 
-    async fn sync_user_inbox_relays(
+    /// Catches up on everything since the account's sync cursors, using negentropy where relays
+    /// support it. Gaps left by unreachable relays are retried first.
+    ///
+    /// Each kind and each group has its own cursor, which is only advanced once the events up to it
+    /// have been processed without errors, so a newly joined group is synced from the start and a
+    /// failing scope doesn't hold back the others. Cursors stay put if no relay could be reached.
+    ///
+    /// Everything goes through the account's own session, so switching accounts while catching up
+    /// doesn't hand this account's events to another account.
+    ///
+    /// # Errors
+    /// Returns `NostrManagerError::SyncIncomplete` with the scopes whose cursor couldn't be advanced,
+    /// after all other scopes have been synced.
+    pub async fn sync_for_user(
         &self,
         pubkey: PublicKey,
-        last_synced: Timestamp,
+        group_ids: Vec<String>,
+        wn: &Whitenoise,
     ) -> Result<()> {
-        let filter = Filter::new()
-            .author(pubkey)
-            .kind(Kind::InboxRelays)
-            .since(last_synced)
-            .until(Timestamp::now());
-        let output = self
-            .client
-            .sync(filter, &nostr_sdk::SyncOptions::default())
-            .await?;
-        tracing::debug!(
-            target: "whitenoise::nostr_client::sync_user_inbox_relays",
-            "SUCCESS: {:?}",
-            output.success
-        );
-        tracing::debug!(
-            target: "whitenoise::nostr_client::sync_user_inbox_relays",
-            "FAILED: {:?}",
-            output.failed
-        );
-        tracing::debug!(
-            target: "whitenoise::nostr_client::sync_user_inbox_relays",
-            "VALUES: local: {:?}, remote: {:?}, sent: {:?}, received: {:?}, send_failed: {:?}",
-            output.val.local.len(),
-            output.val.remote.len(),
-            output.val.sent.len(),
-            output.val.received.len(),
-            output.val.send_failures.len()
+        let session = self.account_session(&pubkey).await?;
+
+        if let Err(e) = self.fill_sync_gaps(&session, wn).await {
+            tracing::warn!(
+                target: "whitenoise::nostr_manager::sync",
+                "Error filling sync gaps for {}: {}",
+                pubkey,
+                e
+            );
+        }
+
+        let mut scopes = vec![SyncScope::Kind(Kind::Metadata)];
+        // In lockdown mode we don't fetch contact metadata unless the user asks for it
+        if !session.lockdown_mode() {
+            scopes.push(SyncScope::Kind(Kind::ContactList));
+            scopes.push(SyncScope::ContactsMetadata);
+        }
+        scopes.extend(
+            [
+                Kind::RelayList,
+                Kind::InboxRelays,
+                Kind::MlsKeyPackageRelays,
+                Kind::MlsKeyPackage,
+                Kind::GiftWrap,
+            ]
+            .map(SyncScope::Kind),
         );
+        scopes.extend(group_ids.into_iter().map(SyncScope::Group));
+
+        let mut incomplete = Vec::new();
+        for scope in scopes {
+            match self.sync_scope(&session, &scope, wn).await {
+                Ok(true) => {}
+                Ok(false) => incomplete.push(scope.to_string()),
+                Err(e) => {
+                    tracing::warn!(
+                        target: "whitenoise::nostr_manager::sync",
+                        "Error syncing {} for {}, keeping its sync cursor: {}",
+                        scope,
+                        pubkey,
+                        e
+                    );
+                    incomplete.push(scope.to_string());
+                }
+            }
+        }
+
+        if !incomplete.is_empty() {
+            return Err(NostrManagerError::SyncIncomplete(incomplete.join(", ")));
+        }
         Ok(())
     }
 
-    async fn sync_user_key_package_relays(
+    /// Syncs a single scope. Returns whether its cursor was advanced, i.e. whether it's caught up.
+    async fn sync_scope(
         &self,
-        pubkey: PublicKey,
-        last_synced: Timestamp,
-    ) -> Result<()> {
-        let filter = Filter::new()
-            .author(pubkey)
-            .kind(Kind::MlsKeyPackageRelays)
-            .since(last_synced)
-            .until(Timestamp::now());
-        let output = self
-            .client
-            .sync(filter, &nostr_sdk::SyncOptions::default())
+        session: &AccountSession,
+        scope: &SyncScope,
+        wn: &Whitenoise,
+    ) -> Result<bool> {
+        let pubkey = session.pubkey;
+        let Some(filter) = self.scope_filter(pubkey, scope).await? else {
+            return Ok(true);
+        };
+        let since = SyncCursor::find(&pubkey, scope, wn)
+            .await?
+            .map(|cursor| cursor.synced_until);
+        let until = Timestamp::now();
+        let filter = match since {
+            Some(since) => filter.since(scope_since(scope, since)),
+            None => filter,
+        }
+        .until(until);
+
+        let relays: Vec<RelayUrl> = session.client.relays().await.into_keys().collect();
+        let relay_count = relays.len();
+        let synced = self.sync_or_fetch(&session.client, relays, filter).await?;
+        if synced.unreachable.len() == relay_count {
+            tracing::warn!(
+                target: "whitenoise::nostr_manager::sync",
+                "No relay reachable for {}, keeping its sync cursor",
+                scope
+            );
+            return Ok(false);
+        }
+
+        // The events are fetched again next time, events that were processed are skipped then
+        let failed = self
+            .process_synced_events(session, scope, synced.events)
             .await?;
-        tracing::debug!(
-            target: "whitenoise::nostr_client::sync_user_key_package_relays",
-            "SUCCESS: {:?}",
-            output.success
-        );
-        tracing::debug!(
-            target: "whitenoise::nostr_client::sync_user_key_package_relays",
-            "FAILED: {:?}",
-            output.failed
-        );
-        tracing::debug!(
-            target: "whitenoise::nostr_client::sync_user_key_package_relays",
-            "VALUES: local: {:?}, remote: {:?}, sent: {:?}, received: {:?}, send_failed: {:?}",
-            output.val.local.len(),
-            output.val.remote.len(),
-            output.val.sent.len(),
-            output.val.received.len(),
-            output.val.send_failures.len()
-        );
-        Ok(())
-    }
+        if failed > 0 {
+            tracing::warn!(
+                target: "whitenoise::nostr_manager::sync",
+                "{} events of {} failed to process, keeping its sync cursor",
+                failed,
+                scope
+            );
+            return Ok(false);
+        }
 
-    async fn sync_user_key_packages(
-        &self,
-        pubkey: PublicKey,
-        last_synced: Timestamp,
-    ) -> Result<()> {
-        let filter = Filter::new()
-            .author(pubkey)
-            .kind(Kind::MlsKeyPackage)
-            .since(last_synced)
-            .until(Timestamp::now());
-        let output = self
-            .client
-            .sync(filter, &nostr_sdk::SyncOptions::default())
+        for relay_url in synced.unreachable {
+            tracing::debug!(
+                target: "whitenoise::nostr_manager::sync",
+                "Recording sync gap for {} on {}",
+                scope,
+                relay_url
+            );
+            SyncGap::create(
+                &pubkey,
+                scope,
+                &relay_url,
+                since.unwrap_or(Timestamp::zero()),
+                until,
+                wn,
+            )
             .await?;
-        tracing::debug!(
-            target: "whitenoise::nostr_client::sync_user_key_packages",
-            "SUCCESS: {:?}",
-            output.success
-        );
-        tracing::debug!(
-            target: "whitenoise::nostr_client::sync_user_key_packages",
-            "FAILED: {:?}",
-            output.failed
-        );
-        tracing::debug!(
-            target: "whitenoise::nostr_client::sync_user_key_packages",
-            "VALUES: local: {:?}, remote: {:?}, sent: {:?}, received: {:?}, send_failed: {:?}",
-            output.val.local.len(),
-            output.val.remote.len(),
-            output.val.sent.len(),
-            output.val.received.len(),
-            output.val.send_failures.len()
-        );
-        Ok(())
+        }
+        SyncCursor::advance(&pubkey, scope, until, wn).await?;
+        Ok(true)
     }
 
-    async fn sync_contacts(&self, last_synced: Timestamp) -> Result<()> {
-        let pubkey = self
-            .client
-            .signer()
-            .await
-            .map_err(NostrManagerError::Client)?
-            .get_public_key()
-            .await
-            .map_err(NostrManagerError::Signer)?;
+    /// Retries the time ranges that relays were unreachable for during earlier catch-ups.
+    /// A gap is only removed once its events were processed without errors.
+    async fn fill_sync_gaps(&self, session: &AccountSession, wn: &Whitenoise) -> Result<()> {
+        let pool_relays = session.client.relays().await;
+        for gap in SyncGap::for_account(&session.pubkey, wn).await? {
+            let filter = match self.scope_filter(session.pubkey, &gap.scope).await? {
+                // There's nothing left to fill the gap from if the relay was removed
+                Some(filter) if pool_relays.contains_key(&gap.relay_url) => filter,
+                _ => {
+                    gap.delete(wn).await?;
+                    continue;
+                }
+            };
 
-        let filter = Filter::new()
-            .kind(Kind::ContactList)
-            .author(pubkey)
-            .since(last_synced)
-            .until(Timestamp::now());
-
-        let output = self
-            .client
-            .sync(filter, &nostr_sdk::SyncOptions::default())
-            .await?;
-        tracing::debug!(
-            target: "whitenoise::nostr_client::sync_contacts",
-            "SUCCESS: {:?}",
-            output.success
-        );
-        tracing::debug!(
-            target: "whitenoise::nostr_client::sync_contacts",
-            "FAILED: {:?}",
-            output.failed
-        );
-        tracing::debug!(
-            target: "whitenoise::nostr_client::sync_contacts",
-            "VALUES: local: {:?}, remote: {:?}, sent: {:?}, received: {:?}, send_failed: {:?}",
-            output.val.local.len(),
-            output.val.remote.len(),
-            output.val.sent.len(),
-            output.val.received.len(),
-            output.val.send_failures.len()
-        );
+            let synced = self
+                .sync_or_fetch(
+                    &session.client,
+                    vec![gap.relay_url.clone()],
+                    filter
+                        .since(scope_since(&gap.scope, gap.since))
+                        .until(gap.until),
+                )
+                .await?;
+            if synced.unreachable.is_empty()
+                && self
+                    .process_synced_events(session, &gap.scope, synced.events)
+                    .await?
+                    == 0
+            {
+                gap.delete(wn).await?;
+            }
+        }
         Ok(())
     }
 
-    async fn sync_contacts_metadata(&self, last_synced: Timestamp) -> Result<()> {
-        let contacts_pubkeys = self.query_contact_list_pubkeys().await?;
+    /// The filter for the events of a scope, without time bounds.
+    /// Returns None if there is nothing to sync, e.g. no contacts.
+    async fn scope_filter(&self, pubkey: PublicKey, scope: &SyncScope) -> Result<Option<Filter>> {
+        let filter = match scope {
+            SyncScope::Kind(Kind::GiftWrap) => Filter::new().kind(Kind::GiftWrap).pubkey(pubkey),
+            SyncScope::Kind(kind) => Filter::new().kind(*kind).author(pubkey),
+            SyncScope::ContactsMetadata => {
+                let contacts_pubkeys = self.query_contact_list_pubkeys_of(pubkey).await?;
+                if contacts_pubkeys.is_empty() {
+                    return Ok(None);
+                }
+                Filter::new().kind(Kind::Metadata).authors(contacts_pubkeys)
+            }
+            SyncScope::Group(nostr_group_id) => {
+                Filter::new().kind(Kind::MlsGroupMessage).custom_tag(
+                    SingleLetterTag::lowercase(Alphabet::H),
+                    [nostr_group_id.clone()],
+                )
+            }
+        };
+        Ok(Some(filter))
+    }
 
-        let filter = Filter::new()
-            .kind(Kind::Metadata)
-            .authors(contacts_pubkeys)
-            .since(last_synced)
-            .until(Timestamp::now());
+    /// Processes the gift-wraps and group messages of a scope as one batch with the session's event
+    /// processor and waits until it's done with them. Events of other scopes only need to be stored,
+    /// which syncing already did.
+    ///
+    /// Returns how many of the scope's events failed to process and may succeed when retried.
+    async fn process_synced_events(
+        &self,
+        session: &AccountSession,
+        scope: &SyncScope,
+        events: Events,
+    ) -> Result<usize> {
+        let processable: fn(Event) -> ProcessableEvent = match scope {
+            SyncScope::Kind(Kind::GiftWrap) => ProcessableEvent::GiftWrap,
+            SyncScope::Group(_) => ProcessableEvent::MlsMessage,
+            _ => return Ok(0),
+        };
 
-        let output = self
-            .client
-            .sync(filter, &nostr_sdk::SyncOptions::default())
-            .await?;
-        tracing::debug!(
-            target: "whitenoise::nostr_client::sync_contacts_metadata",
-            "SUCCESS: {:?}",
-            output.success
-        );
-        tracing::debug!(
-            target: "whitenoise::nostr_client::sync_contacts_metadata",
-            "FAILED: {:?}",
-            output.failed
-        );
-        tracing::debug!(
-            target: "whitenoise::nostr_client::sync_contacts_metadata",
-            "VALUES: local: {:?}, remote: {:?}, sent: {:?}, received: {:?}, send_failed: {:?}",
-            output.val.local.len(),
-            output.val.remote.len(),
-            output.val.sent.len(),
-            output.val.received.len(),
-            output.val.send_failures.len()
-        );
-        Ok(())
+        session
+            .event_processor()
+            .process_batch(events.into_iter().map(processable).collect())
+            .await
+            .map_err(|e| NostrManagerError::FailedToQueueEvent(e.to_string()))
     }
 
-    async fn sync_group_messages(
+    /// Reconciles the events matching the filter with the given relays.
+    ///
+    /// Relays that support negentropy are synced, all others are fetched from with the filter.
+    /// Everything received is stored in the database, so the events are queried from there and
+    /// include the ones we already had.
+    pub async fn sync_or_fetch(
         &self,
-        last_synced: Timestamp,
-        group_ids: Vec<String>,
-    ) -> Result<()> {
-        let filter = Filter::new()
-            .kind(Kind::MlsGroupMessage)
-            .custom_tag(SingleLetterTag::lowercase(Alphabet::H), group_ids)
-            .since(last_synced)
-            .until(Timestamp::now());
-
-        let output = self
-            .client
-            .sync(filter, &nostr_sdk::SyncOptions::default())
-            .await?;
-        tracing::debug!(
-            target: "whitenoise::nostr_client::sync_group_messages",
-            "SUCCESS: {:?}",
-            output.success
-        );
-        tracing::debug!(
-            target: "whitenoise::nostr_client::sync_group_messages",
-            "FAILED: {:?}",
-            output.failed
-        );
-        tracing::debug!(
-            target: "whitenoise::nostr_client::sync_group_messages",
-            "VALUES: local: {:?}, remote: {:?}, sent: {:?}, received: {:?}, send_failed: {:?}",
-            output.val.local.len(),
-            output.val.remote.len(),
-            output.val.sent.len(),
-            output.val.received.len(),
-            output.val.send_failures.len()
-        );
-        Ok(())
+        client: &Client,
+        relays: Vec<RelayUrl>,
+        filter: Filter,
+    ) -> Result<SyncedEvents> {
+        let now = Instant::now();
+        let (sync_relays, mut fetch_relays): (Vec<RelayUrl>, Vec<RelayUrl>) = relays
+            .into_iter()
+            .partition(|url| self.negentropy_support.is_supported(url, now));
+
+        if !sync_relays.is_empty() {
+            match client
+                .sync_with(
+                    sync_relays.clone(),
+                    filter.clone(),
+                    &nostr_sdk::SyncOptions::default(),
+                )
+                .await
+            {
+                Ok(output) => {
+                    tracing::debug!(
+                        target: "whitenoise::nostr_manager::sync",
+                        "Synced {:?}: received {} events from {} relays",
+                        filter.kinds,
+                        output.val.received.len(),
+                        output.success.len()
+                    );
+
+                    for (url, error) in output.failed {
+                        tracing::debug!(
+                            target: "whitenoise::nostr_manager::sync",
+                            "Sync failed on {}, fetching instead: {}",
+                            url,
+                            error
+                        );
+                        self.negentropy_support.set_unsupported(url.clone());
+                        fetch_relays.push(url);
+                    }
+                }
+                // Nothing was synced at all, which doesn't tell us which relays lack support
+                Err(e) => {
+                    tracing::warn!(
+                        target: "whitenoise::nostr_manager::sync",
+                        "Sync of {:?} failed, fetching instead: {}",
+                        filter.kinds,
+                        e
+                    );
+                    fetch_relays.extend(sync_relays);
+                }
+            }
+        }
+
+        // Fetch from each relay separately so that we know which ones couldn't be reached
+        let timeout = self.timeout().await?;
+        let mut requests = JoinSet::new();
+        for url in fetch_relays {
+            let client = client.clone();
+            let filter = filter.clone();
+            requests.spawn(async move {
+                let result = client
+                    .fetch_events_from([url.clone()], vec![filter], timeout)
+                    .await;
+                (url, result)
+            });
+        }
+
+        let mut unreachable = Vec::new();
+        while let Some(joined) = requests.join_next().await {
+            match joined {
+                Ok((_, Ok(_))) => {}
+                Ok((url, Err(e))) => {
+                    tracing::warn!(
+                        target: "whitenoise::nostr_manager::sync",
+                        "Error fetching {:?} from {}: {}",
+                        filter.kinds,
+                        url,
+                        e
+                    );
+                    unreachable.push(url);
+                }
+                Err(e) => {
+                    tracing::error!(
+                        target: "whitenoise::nostr_manager::sync",
+                        "Fetch task failed: {}",
+                        e
+                    );
+                }
+            }
+        }
+
+        Ok(SyncedEvents {
+            events: client.database().query(vec![filter]).await?,
+            unreachable,
+        })
     }
+}
 
-    async fn sync_user_giftwrapped_events(
-        &self,
-        pubkey: PublicKey,
-        last_synced: Timestamp,
-    ) -> Result<()> {
-        let filter = Filter::new()
-            .kind(Kind::GiftWrap)
-            .pubkeys(vec![pubkey])
-            .since(last_synced)
-            .until(Timestamp::now());
-        let output = self
-            .client
-            .sync(filter, &nostr_sdk::SyncOptions::default())
-            .await?;
-        tracing::debug!(
-            target: "whitenoise::nostr_client::sync_user_giftwrapped_events",
-            "SUCCESS: {:?}",
-            output.success
+/// The timestamp to sync a scope from to catch up on everything received since `since`.
+pub(crate) fn scope_since(scope: &SyncScope, since: Timestamp) -> Timestamp {
+    match scope {
+        SyncScope::Kind(Kind::GiftWrap) => {
+            Timestamp::from(since.as_u64().saturating_sub(GIFT_WRAP_LOOKBACK.as_secs()))
+        }
+        _ => since,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_negentropy_support() {
+        let support = NegentropySupport::default();
+        let url = RelayUrl::parse("wss://relay.example.com").unwrap();
+        let now = Instant::now();
+        assert!(support.is_supported(&url, now));
+
+        support.set_unsupported(url.clone());
+        assert!(!support.is_supported(&url, Instant::now()));
+
+        // Relays are tried again once the TTL has passed
+        let later = Instant::now() + UNSUPPORTED_TTL + Duration::from_secs(1);
+        assert!(support.is_supported(&url, later));
+        assert!(support.is_supported(&url, Instant::now()));
+    }
+
+    #[test]
+    fn test_scope_since() {
+        let since = Timestamp::from(1_000_000);
+        assert_eq!(
+            scope_since(&SyncScope::Kind(Kind::GiftWrap), since),
+            Timestamp::from(1_000_000 - 172_800)
         );
-        tracing::debug!(
-            target: "whitenoise::nostr_client::sync_user_giftwrapped_events",
-            "FAILED: {:?}",
-            output.failed
+        assert_eq!(
+            scope_since(&SyncScope::Kind(Kind::GiftWrap), Timestamp::from(100)),
+            Timestamp::from(0)
         );
-        tracing::debug!(
-            target: "whitenoise::nostr_client::sync_user_giftwrapped_events",
-            "VALUES: local: {:?}, remote: {:?}, sent: {:?}, received: {:?}, send_failed: {:?}",
-            output.val.local.len(),
-            output.val.remote.len(),
-            output.val.sent.len(),
-            output.val.received.len(),
-            output.val.send_failures.len()
+        assert_eq!(scope_since(&SyncScope::Kind(Kind::Metadata), since), since);
+        assert_eq!(
+            scope_since(&SyncScope::Group("abc".to_string()), since),
+            since
         );
-        Ok(())
     }
 }
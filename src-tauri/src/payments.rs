@@ -21,11 +21,16 @@ pub enum PaymentError {
 /// # Arguments
 /// * `bolt11` - The bolt11 invoice to pay
 /// * `nwc_uri` - The Nostr Wallet Connect URI to use for payment
+/// * `connection_mode` - How to connect to the wallet's relays, following the proxy settings
 ///
 /// # Returns
 /// * `Ok(String)` - The payment preimage if successful
 /// * `Err(PaymentError)` - The error if payment fails
-pub async fn pay_bolt11_invoice(bolt11: &str, nwc_uri: &str) -> Result<String, PaymentError> {
+pub async fn pay_bolt11_invoice(
+    bolt11: &str,
+    nwc_uri: &str,
+    connection_mode: ConnectionMode,
+) -> Result<String, PaymentError> {
     let invoice =
         Bolt11Invoice::from_str(bolt11).map_err(|e| PaymentError::InvalidInvoice(e.to_string()))?;
 
@@ -40,7 +45,10 @@ pub async fn pay_bolt11_invoice(bolt11: &str, nwc_uri: &str) -> Result<String, P
 
     let uri = NostrWalletConnectURI::parse(nwc_uri)
         .map_err(|e| PaymentError::InvalidNwcUri(e.to_string()))?;
-    let nwc = NWC::new(uri);
+    let nwc = NWC::with_opts(
+        uri,
+        NostrWalletConnectOptions::new().connection_mode(connection_mode),
+    );
 
     let pay_request = PayInvoiceRequest::new(bolt11.to_string());
     let payment_response = nwc
@@ -57,7 +65,12 @@ mod tests {
 
     #[tokio::test]
     async fn test_pay_bolt11_invoice_invalid_bolt11() {
-        let result = pay_bolt11_invoice("invalid_bolt11", "nostr+walletconnect://test").await;
+        let result = pay_bolt11_invoice(
+            "invalid_bolt11",
+            "nostr+walletconnect://test",
+            ConnectionMode::Direct,
+        )
+        .await;
         assert!(matches!(result, Err(PaymentError::InvalidInvoice(_))));
     }
 }
@@ -1,3 +1,4 @@
+use crate::database::DatabaseError;
 use nostr_sdk::prelude::*;
 use serde::{Deserialize, Serialize};
 
@@ -26,14 +27,19 @@ pub enum RelayType {
     Group,
 }
 
-impl From<String> for RelayType {
-    fn from(s: String) -> Self {
+impl TryFrom<String> for RelayType {
+    type Error = DatabaseError;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
         match s.to_lowercase().as_str() {
-            "nostr" => RelayType::Nostr,
-            "inbox" => RelayType::Inbox,
-            "key_package" => RelayType::KeyPackage,
-            "group" => RelayType::Group,
-            _ => panic!("Invalid relay type: {}", s),
+            "nostr" => Ok(RelayType::Nostr),
+            "inbox" => Ok(RelayType::Inbox),
+            "key_package" => Ok(RelayType::KeyPackage),
+            "group" => Ok(RelayType::Group),
+            _ => Err(DatabaseError::InvalidValue {
+                kind: "relay type",
+                value: s,
+            }),
         }
     }
 }
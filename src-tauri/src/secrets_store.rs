@@ -4,7 +4,6 @@ use nostr_sdk::{util::hex, Keys};
 use serde_json::{json, Value};
 use std::fs;
 use std::path::{Path, PathBuf};
-use tauri::is_dev;
 use thiserror::Error;
 use uuid::Uuid;
 
@@ -39,7 +38,7 @@ pub type Result<T> = std::result::Result<T, SecretsStoreError>;
 
 #[allow(dead_code)]
 fn get_service_name() -> String {
-    match is_dev() {
+    match cfg!(dev) {
         true => "White Noise Dev".to_string(),
         false => "White Noise".to_string(),
     }
@@ -340,6 +339,72 @@ pub fn get_export_secret_keys_for_group(
     // }
 }
 
+/// Retrieves all stored export secrets for a specific MLS group, across every epoch.
+///
+/// # Arguments
+///
+/// * `mls_group_id` - The ID of the MLS group
+/// * `data_dir` - Path to the data directory
+///
+/// # Returns
+///
+/// * `Result<Vec<(u64, String)>>` - The epochs and their hex encoded export secrets, ordered by epoch
+pub fn get_all_export_secrets_for_group(
+    mls_group_id: &[u8],
+    data_dir: &Path,
+) -> Result<Vec<(u64, String)>> {
+    let prefix = format!("{}:", hex::encode(mls_group_id));
+    let secrets = read_secrets_file(data_dir)?;
+
+    let mut export_secrets = Vec::new();
+    if let Some(obj) = secrets.as_object() {
+        for (key, value) in obj {
+            let Some(epoch) = key
+                .strip_prefix(&prefix)
+                .and_then(|epoch| epoch.parse::<u64>().ok())
+            else {
+                continue;
+            };
+            if let Some(obfuscated_secret) = value.as_str() {
+                export_secrets.push((epoch, deobfuscate(obfuscated_secret, data_dir)?));
+            }
+        }
+    }
+    export_secrets.sort_by_key(|(epoch, _)| *epoch);
+
+    Ok(export_secrets)
+}
+
+/// Removes all stored export secrets for a specific MLS group, across every epoch.
+///
+/// # Arguments
+///
+/// * `mls_group_id` - The ID of the MLS group
+/// * `data_dir` - Path to the data directory
+///
+/// # Returns
+///
+/// * `Result<usize>` - The number of export secrets removed
+pub fn remove_export_secrets_for_group(mls_group_id: &[u8], data_dir: &Path) -> Result<usize> {
+    let prefix = format!("{}:", hex::encode(mls_group_id));
+    let mut secrets = read_secrets_file(data_dir)?;
+
+    let removed = match secrets.as_object_mut() {
+        Some(obj) => {
+            let before = obj.len();
+            obj.retain(|key, _| {
+                key.strip_prefix(&prefix)
+                    .is_none_or(|epoch| epoch.parse::<u64>().is_err())
+            });
+            before - obj.len()
+        }
+        None => 0,
+    };
+    write_secrets_file(data_dir, &secrets)?;
+
+    Ok(removed)
+}
+
 /// Stores the NWC (Nostr Wallet Connect) URI for a specific public key in the secrets store.
 ///
 /// # Arguments
@@ -398,6 +463,189 @@ pub fn remove_nostr_wallet_connect_uri(pubkey: &str, data_dir: &Path) -> Result<
     Ok(())
 }
 
+/// Stores the NIP-46 remote signer connection for a specific public key in the secrets store.
+///
+/// The local app keys are stored alongside the bunker URI so that the connection can be
+/// re-established without the remote signer having to approve a new client.
+///
+/// # Arguments
+///
+/// * `pubkey` - The user's public key the remote signer signs for
+/// * `bunker_uri` - The `bunker://` URI of the remote signer
+/// * `app_keys` - The local keys used to talk to the remote signer
+/// * `data_dir` - Path to the data directory
+///
+/// # Returns
+///
+/// * `Result<()>` - Ok(()) if successful, or an error if the operation fails
+pub fn store_remote_signer(
+    pubkey: &str,
+    bunker_uri: &str,
+    app_keys: &Keys,
+    data_dir: &Path,
+) -> Result<()> {
+    let mut secrets = read_secrets_file(data_dir).unwrap_or(json!({}));
+    let value = json!({
+        "uri": bunker_uri,
+        "app_secret": app_keys.secret_key().to_secret_hex(),
+    });
+    secrets[format!("nip46:{}", pubkey)] = json!(obfuscate(&value.to_string(), data_dir));
+    write_secrets_file(data_dir, &secrets)?;
+    Ok(())
+}
+
+/// Retrieves the NIP-46 remote signer connection for a specific public key from the secrets store.
+///
+/// # Arguments
+///
+/// * `pubkey` - The user's public key
+/// * `data_dir` - Path to the data directory
+///
+/// # Returns
+///
+/// * `Result<Option<(String, Keys)>>` - Some((bunker_uri, app_keys)) if found, None if the
+///   account doesn't use a remote signer, or an error if the operation fails
+pub fn get_remote_signer(pubkey: &str, data_dir: &Path) -> Result<Option<(String, Keys)>> {
+    let secrets = read_secrets_file(data_dir)?;
+    let key = format!("nip46:{}", pubkey);
+
+    let Some(obfuscated) = secrets[key].as_str() else {
+        return Ok(None);
+    };
+    let value: Value = serde_json::from_str(&deobfuscate(obfuscated, data_dir)?)?;
+    let uri = value["uri"]
+        .as_str()
+        .ok_or(SecretsStoreError::KeyNotFound)?
+        .to_string();
+    let app_secret = value["app_secret"]
+        .as_str()
+        .ok_or(SecretsStoreError::KeyNotFound)?;
+    let app_keys = Keys::parse(app_secret).map_err(SecretsStoreError::KeyError)?;
+
+    Ok(Some((uri, app_keys)))
+}
+
+/// Removes the NIP-46 remote signer connection for a specific public key from the secrets store.
+///
+/// # Arguments
+///
+/// * `pubkey` - The user's public key
+/// * `data_dir` - Path to the data directory
+///
+/// # Returns
+///
+/// * `Result<()>` - Ok(()) if successful, or an error if the operation fails
+pub fn remove_remote_signer(pubkey: &str, data_dir: &Path) -> Result<()> {
+    let mut secrets = read_secrets_file(data_dir)?;
+    let key = format!("nip46:{}", pubkey);
+    secrets.as_object_mut().map(|obj| obj.remove(&key));
+    write_secrets_file(data_dir, &secrets)?;
+    Ok(())
+}
+
+/// Stores the local app keys of a pending `nostrconnect://` login.
+///
+/// These are the keys advertised in the `nostrconnect://` URI shown to the user and are
+/// needed to complete the login once the remote signer connects.
+///
+/// # Arguments
+///
+/// * `app_keys` - The local keys advertised in the `nostrconnect://` URI
+/// * `data_dir` - Path to the data directory
+///
+/// # Returns
+///
+/// * `Result<()>` - Ok(()) if successful, or an error if the operation fails
+pub fn store_pending_remote_signer_app_keys(app_keys: &Keys, data_dir: &Path) -> Result<()> {
+    let mut secrets = read_secrets_file(data_dir).unwrap_or(json!({}));
+    let key = format!("nip46_pending:{}", app_keys.public_key().to_hex());
+    let obfuscated_key = obfuscate(app_keys.secret_key().to_secret_hex().as_str(), data_dir);
+    secrets[key] = json!(obfuscated_key);
+    write_secrets_file(data_dir, &secrets)?;
+    Ok(())
+}
+
+/// Retrieves the local app keys of a pending `nostrconnect://` login.
+///
+/// The keys are kept until [`remove_pending_remote_signer_app_keys`] is called, so that a login
+/// that times out or is rejected can be retried with the same URI.
+///
+/// # Arguments
+///
+/// * `app_pubkey` - The public key advertised in the `nostrconnect://` URI
+/// * `data_dir` - Path to the data directory
+///
+/// # Returns
+///
+/// * `Result<Keys>` - The app keys, or `SecretsStoreError::KeyNotFound` if there is no pending login
+pub fn get_pending_remote_signer_app_keys(app_pubkey: &str, data_dir: &Path) -> Result<Keys> {
+    let secrets = read_secrets_file(data_dir)?;
+    let key = format!("nip46_pending:{}", app_pubkey);
+    let obfuscated_key = secrets[&key]
+        .as_str()
+        .ok_or(SecretsStoreError::KeyNotFound)?;
+    Keys::parse(&deobfuscate(obfuscated_key, data_dir)?).map_err(SecretsStoreError::KeyError)
+}
+
+/// Removes the local app keys of a pending `nostrconnect://` login once the login has completed.
+///
+/// # Arguments
+///
+/// * `app_pubkey` - The public key advertised in the `nostrconnect://` URI
+/// * `data_dir` - Path to the data directory
+///
+/// # Returns
+///
+/// * `Result<()>` - Ok(()) if successful, or an error if the operation fails
+pub fn remove_pending_remote_signer_app_keys(app_pubkey: &str, data_dir: &Path) -> Result<()> {
+    let mut secrets = read_secrets_file(data_dir)?;
+    let key = format!("nip46_pending:{}", app_pubkey);
+    secrets.as_object_mut().map(|obj| obj.remove(&key));
+    write_secrets_file(data_dir, &secrets)?;
+    Ok(())
+}
+
+/// Stores the NIP-06 mnemonic of a newly created identity until it has been shown to the user.
+///
+/// # Arguments
+///
+/// * `pubkey` - The public key derived from the mnemonic
+/// * `mnemonic` - The BIP-39 mnemonic
+/// * `data_dir` - Path to the data directory
+///
+/// # Returns
+///
+/// * `Result<()>` - Ok(()) if successful, or an error if the operation fails
+pub fn store_mnemonic(pubkey: &str, mnemonic: &str, data_dir: &Path) -> Result<()> {
+    let mut secrets = read_secrets_file(data_dir).unwrap_or(json!({}));
+    let key = format!("mnemonic:{}", pubkey);
+    secrets[key] = json!(obfuscate(mnemonic, data_dir));
+    write_secrets_file(data_dir, &secrets)?;
+    Ok(())
+}
+
+/// Retrieves and removes the NIP-06 mnemonic for a specific public key, so that it can only be shown once.
+///
+/// # Arguments
+///
+/// * `pubkey` - The public key derived from the mnemonic
+/// * `data_dir` - Path to the data directory
+///
+/// # Returns
+///
+/// * `Result<Option<String>>` - Some(mnemonic) if it hasn't been taken yet, None otherwise
+pub fn take_mnemonic(pubkey: &str, data_dir: &Path) -> Result<Option<String>> {
+    let mut secrets = read_secrets_file(data_dir)?;
+    let key = format!("mnemonic:{}", pubkey);
+    let mnemonic = match secrets[&key].as_str() {
+        Some(obfuscated) => deobfuscate(obfuscated, data_dir)?,
+        None => return Ok(None),
+    };
+    secrets.as_object_mut().map(|obj| obj.remove(&key));
+    write_secrets_file(data_dir, &secrets)?;
+    Ok(Some(mnemonic))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -574,4 +822,100 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn test_store_and_retrieve_remote_signer() -> Result<()> {
+        let temp_dir = setup_temp_dir();
+        let pubkey = Keys::generate().public_key().to_hex();
+        let app_keys = Keys::generate();
+        let bunker_uri = "bunker://79dff8f82963424e0bb02708a22e44b4980893e3a4be0fa3cb60a43b946764e3?relay=wss%3A%2F%2Frelay.nsec.app";
+
+        // Test non-existent remote signer returns None
+        assert!(get_remote_signer(&pubkey, temp_dir.path())?.is_none());
+
+        store_remote_signer(&pubkey, bunker_uri, &app_keys, temp_dir.path())?;
+
+        let (retrieved_uri, retrieved_keys) =
+            get_remote_signer(&pubkey, temp_dir.path())?.expect("Remote signer should exist");
+        assert_eq!(bunker_uri, retrieved_uri);
+        assert_eq!(app_keys.public_key(), retrieved_keys.public_key());
+
+        // The remote signer is not stored as a private key
+        assert!(get_nostr_keys_for_pubkey(&pubkey, temp_dir.path()).is_err());
+
+        remove_remote_signer(&pubkey, temp_dir.path())?;
+        assert!(get_remote_signer(&pubkey, temp_dir.path())?.is_none());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_pending_remote_signer_app_keys() -> Result<()> {
+        let temp_dir = setup_temp_dir();
+        let app_keys = Keys::generate();
+        let app_pubkey = app_keys.public_key().to_hex();
+
+        store_pending_remote_signer_app_keys(&app_keys, temp_dir.path())?;
+
+        // Pending keys survive a failed attempt, so they can be read more than once
+        for _ in 0..2 {
+            let pending = get_pending_remote_signer_app_keys(&app_pubkey, temp_dir.path())?;
+            assert_eq!(app_keys.secret_key(), pending.secret_key());
+        }
+
+        remove_pending_remote_signer_app_keys(&app_pubkey, temp_dir.path())?;
+        assert!(matches!(
+            get_pending_remote_signer_app_keys(&app_pubkey, temp_dir.path()),
+            Err(SecretsStoreError::KeyNotFound)
+        ));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_take_mnemonic_only_once() -> Result<()> {
+        let temp_dir = setup_temp_dir();
+        let pubkey = "test_pubkey";
+        let mnemonic =
+            "leader monkey parrot ring guide accident before fence cannon height naive bean";
+
+        store_mnemonic(pubkey, mnemonic, temp_dir.path())?;
+
+        assert_eq!(
+            take_mnemonic(pubkey, temp_dir.path())?.as_deref(),
+            Some(mnemonic)
+        );
+        assert!(take_mnemonic(pubkey, temp_dir.path())?.is_none());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_remove_export_secrets_for_group() -> Result<()> {
+        let temp_dir = setup_temp_dir();
+        let group_id = vec![1, 2, 3];
+        let other_group_id = vec![4, 5, 6];
+
+        for epoch in 0..3 {
+            let secret = Keys::generate().secret_key().to_secret_hex();
+            store_mls_export_secret(group_id.clone(), epoch, secret, temp_dir.path())?;
+        }
+        let other_secret = Keys::generate().secret_key().to_secret_hex();
+        store_mls_export_secret(other_group_id.clone(), 0, other_secret, temp_dir.path())?;
+
+        assert_eq!(
+            get_all_export_secrets_for_group(&group_id, temp_dir.path())?.len(),
+            3
+        );
+        assert_eq!(
+            remove_export_secrets_for_group(&group_id, temp_dir.path())?,
+            3
+        );
+        assert!(get_all_export_secrets_for_group(&group_id, temp_dir.path())?.is_empty());
+
+        // Other groups are left alone
+        assert!(get_export_secret_keys_for_group(other_group_id, 0, temp_dir.path()).is_ok());
+
+        Ok(())
+    }
 }
@@ -0,0 +1,268 @@
+//! In-process harness for end-to-end tests.
+//! Starts an in-memory relay on localhost and any number of `Whitenoise` instances, each with its own
+//! temporary data directory. Every instance uses a network profile that only knows the mock relay,
+//! so scenarios can drive the same API the Tauri commands call without any network.
+//!
+//! Limitation: members only process application messages, not commits (see
+//! `EventProcessor::process_mls_message`). Scenarios can rotate keys, but nothing sent to the group
+//! after a rotation can be read by the other members. `test_messages_after_key_rotation` covers
+//! this and is ignored until members process commits.
+
+use crate::accounts::Account;
+use crate::event_sink::{EventSink, NoopEventSink};
+use crate::groups::Group;
+use crate::key_packages::publish_key_package;
+use crate::nostr_manager::network::NetworkProfile;
+use crate::whitenoise::Whitenoise;
+use nostr_relay_builder::MockRelay;
+use nostr_sdk::prelude::*;
+use std::future::Future;
+use std::sync::Arc;
+use std::time::Duration;
+use tempfile::TempDir;
+
+/// How long to wait for events to make it through the relay and the event processor.
+const WAIT_TIMEOUT: Duration = Duration::from_secs(30);
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// A mock relay that test instances connect to.
+pub struct TestNetwork {
+    relay: MockRelay,
+}
+
+impl TestNetwork {
+    pub async fn start() -> Self {
+        Self {
+            relay: MockRelay::run().await.expect("Failed to start mock relay"),
+        }
+    }
+
+    /// A network profile that sends everything to the mock relay.
+    pub fn profile(&self) -> NetworkProfile {
+        let relays = vec![self.relay.url().to_string()];
+        NetworkProfile {
+            name: "test".to_string(),
+            default_relays: relays.clone(),
+            timeout_secs: 2,
+            key_package_relays: Some(relays.clone()),
+            welcome_relays: Some(relays),
+            use_user_relays: false,
+        }
+    }
+
+    /// Starts a new `Whitenoise` instance with an empty data directory.
+    pub async fn instance(&self) -> TestInstance {
+        self.instance_with_events(Arc::new(NoopEventSink)).await
+    }
+
+    /// Starts a new `Whitenoise` instance with an empty data directory that emits to `events`.
+    pub async fn instance_with_events(&self, events: Arc<dyn EventSink>) -> TestInstance {
+        let dir = TempDir::new().expect("Failed to create temp dir");
+        let data_dir = dir.path().join("data");
+        let logs_dir = dir.path().join("logs");
+        std::fs::create_dir_all(&data_dir).expect("Failed to create data dir");
+        std::fs::create_dir_all(&logs_dir).expect("Failed to create logs dir");
+        self.profile()
+            .save(&data_dir)
+            .expect("Failed to save network profile");
+
+        let wn = Whitenoise::new(data_dir, logs_dir, events).await;
+
+        TestInstance { wn, _dir: dir }
+    }
+}
+
+/// A `Whitenoise` instance with its own data directory.
+pub struct TestInstance {
+    wn: Whitenoise,
+    /// Removed when the instance is dropped
+    _dir: TempDir,
+}
+
+impl TestInstance {
+    pub fn wn(&self) -> &Whitenoise {
+        &self.wn
+    }
+
+    /// Creates a new identity, makes it the active account and publishes a key package for it.
+    pub async fn new_account(&self) -> Account {
+        let account = Account::new(self.wn())
+            .await
+            .expect("Failed to create identity")
+            .set_active(self.wn())
+            .await
+            .expect("Failed to set active account");
+        publish_key_package(self.wn())
+            .await
+            .expect("Failed to publish key package");
+        account
+    }
+
+    /// Waits until the group has a message with the id of the given event.
+    pub async fn wait_for_message(&self, group: &Group, event: &UnsignedEvent) -> UnsignedEvent {
+        wait_for("group message", || async move {
+            group
+                .messages(self.wn())
+                .await
+                .ok()?
+                .into_iter()
+                .find(|message| message.id == event.id)
+        })
+        .await
+    }
+}
+
+/// Polls `check` until it returns something, panicking after `WAIT_TIMEOUT`.
+pub async fn wait_for<T, F, Fut>(what: &str, mut check: F) -> T
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Option<T>>,
+{
+    let deadline = tokio::time::Instant::now() + WAIT_TIMEOUT;
+    loop {
+        if let Some(value) = check().await {
+            return value;
+        }
+        if tokio::time::Instant::now() > deadline {
+            panic!("Timed out waiting for {}", what);
+        }
+        tokio::time::sleep(POLL_INTERVAL).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::invites::Invite;
+
+    /// Creates a group between two new accounts and waits until the second one has joined it.
+    /// Returns each side's view of the group.
+    async fn create_group_between(alice: &TestInstance, bob: &TestInstance) -> (Group, Group) {
+        let alice_account = alice.new_account().await;
+        let bob_account = bob.new_account().await;
+
+        let alice_group = Group::create(
+            alice_account.pubkey.to_hex(),
+            vec![bob_account.pubkey.to_hex()],
+            vec![alice_account.pubkey.to_hex()],
+            "Test group".to_string(),
+            "A group for testing".to_string(),
+            alice.wn(),
+        )
+        .await
+        .expect("Failed to create group");
+
+        let mut invite = wait_for("invite", || async {
+            Invite::pending(bob.wn()).await.ok()?.into_iter().next()
+        })
+        .await;
+        assert_eq!(invite.nostr_group_id, alice_group.nostr_group_id);
+        assert_eq!(invite.inviter, alice_account.pubkey.to_hex());
+
+        invite
+            .accept(bob.wn())
+            .await
+            .expect("Failed to accept invite");
+        let bob_group = Group::find_by_mls_group_id(&alice_group.mls_group_id, bob.wn())
+            .await
+            .expect("Joined group not found");
+
+        (alice_group, bob_group)
+    }
+
+    async fn send_text(instance: &TestInstance, group: &Group, text: &str) -> UnsignedEvent {
+        group
+            .send_message(
+                text.to_string(),
+                Kind::PrivateDirectMessage.as_u16(),
+                None,
+                instance.wn(),
+            )
+            .await
+            .expect("Failed to send message")
+    }
+
+    async fn epoch(instance: &TestInstance, group: &Group) -> u64 {
+        let nostr_mls = instance.wn().nostr_mls();
+        let nostr_mls = nostr_mls.lock().await;
+        let (_, epoch) = nostr_mls
+            .export_secret_as_hex_secret_key_and_epoch(group.mls_group_id.clone())
+            .expect("Failed to get epoch");
+        epoch
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_group_lifecycle() {
+        let network = TestNetwork::start().await;
+        let alice = network.instance().await;
+        let bob = network.instance().await;
+
+        let (alice_group, bob_group) = create_group_between(&alice, &bob).await;
+
+        // Messages go both ways
+        let hello = send_text(&alice, &alice_group, "Hello Bob").await;
+        let received = bob.wait_for_message(&bob_group, &hello).await;
+        assert_eq!(received.content, "Hello Bob");
+
+        let reply = send_text(&bob, &bob_group, "Hi Alice").await;
+        alice.wait_for_message(&alice_group, &reply).await;
+
+        // Deleting a message sends a NIP-09 deletion that references it
+        let deletion = alice_group
+            .delete_message(&hello.id.unwrap().to_hex(), alice.wn())
+            .await
+            .expect("Failed to delete message");
+        let received = bob.wait_for_message(&bob_group, &deletion).await;
+        assert_eq!(received.kind, Kind::EventDeletion);
+        assert!(received.tags.event_ids().any(|id| Some(*id) == hello.id));
+
+        // Only the author of a message can delete it
+        assert!(bob_group
+            .delete_message(&hello.id.unwrap().to_hex(), bob.wn())
+            .await
+            .is_err());
+
+        // Rotating keys moves the group to the next epoch
+        let epoch_before = epoch(&alice, &alice_group).await;
+        alice_group
+            .self_update_keys(alice.wn())
+            .await
+            .expect("Failed to rotate keys");
+        assert_eq!(epoch(&alice, &alice_group).await, epoch_before + 1);
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    #[ignore = "members don't process commits yet, see EventProcessor::process_mls_message"]
+    async fn test_messages_after_key_rotation() {
+        let network = TestNetwork::start().await;
+        let alice = network.instance().await;
+        let bob = network.instance().await;
+
+        let (alice_group, bob_group) = create_group_between(&alice, &bob).await;
+        alice_group
+            .self_update_keys(alice.wn())
+            .await
+            .expect("Failed to rotate keys");
+
+        let message = send_text(&alice, &alice_group, "After rotation").await;
+        bob.wait_for_message(&bob_group, &message).await;
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_remove_account_removes_mls_storage() {
+        let network = TestNetwork::start().await;
+        let alice = network.instance().await;
+
+        // The account's session stores its key package in the MLS storage
+        let account = alice.new_account().await;
+        let mls_storage_dir = account.mls_storage_dir(alice.wn());
+        assert!(mls_storage_dir.is_dir());
+
+        let report = account
+            .remove(alice.wn())
+            .await
+            .expect("Failed to remove account");
+        assert!(report.mls_storage);
+        assert!(!mls_storage_dir.exists());
+    }
+}
@@ -1,50 +1,87 @@
 use crate::database::Database;
+use crate::event_sink::{BroadcastEventSink, EventSink};
 use crate::nostr_manager::NostrManager;
 use nostr_openmls::NostrMls;
+use serde::Serialize;
+use serde_json::Value;
 use std::path::PathBuf;
 use std::sync::Arc;
-use tauri::AppHandle;
-use tokio::sync::Mutex;
+use tokio::sync::{broadcast, Mutex};
 
+/// A handle to the White Noise core. Cloning it is cheap and every clone shares the same state.
 #[derive(Clone)]
 pub struct Whitenoise {
     pub database: Arc<Database>,
     pub nostr: NostrManager,
-    pub nostr_mls: Arc<Mutex<NostrMls>>,
+    /// The MLS instance used while no account is active.
+    /// Each account has its own instance in its session, see `Whitenoise::nostr_mls`.
+    default_nostr_mls: Arc<Mutex<NostrMls>>,
     pub data_dir: PathBuf,
     pub logs_dir: PathBuf,
+    events: Arc<BroadcastEventSink>,
 }
 
 impl Whitenoise {
-    pub async fn new(data_dir: PathBuf, logs_dir: PathBuf, app_handle: AppHandle) -> Self {
+    pub async fn new(data_dir: PathBuf, logs_dir: PathBuf, events: Arc<dyn EventSink>) -> Self {
         tracing::info!(
             target: "whitenoise::whitenoise::new",
             "Creating Whitenoise instance with data_dir: {:?}",
             &data_dir
         );
 
+        let events = Arc::new(BroadcastEventSink::new(events));
+        let nostr = NostrManager::new(data_dir.clone())
+            .await
+            .expect("Failed to create Nostr manager");
+        nostr.start_relay_monitor(events.clone());
+
         Self {
             database: Arc::new(
-                Database::new(data_dir.join("whitenoise.sqlite"), app_handle.clone())
+                Database::new(data_dir.join("whitenoise.sqlite"))
                     .await
                     .expect("Failed to create database"),
             ),
-            nostr: NostrManager::new(data_dir.clone(), app_handle.clone())
-                .await
-                .expect("Failed to create Nostr manager"),
-            nostr_mls: Arc::new(Mutex::new(NostrMls::new(data_dir.clone(), None))),
+            nostr,
+            default_nostr_mls: Arc::new(Mutex::new(NostrMls::new(data_dir.clone(), None))),
             data_dir,
             logs_dir,
+            events,
         }
     }
 
+    /// Emits an event to the event sink.
+    pub fn emit<T: Serialize>(&self, event: &str, payload: T) {
+        let events: &dyn EventSink = self.events.as_ref();
+        events.emit_serialized(event, payload);
+    }
+
+    /// Subscribes to the events emitted from now on, as event name and JSON payload.
+    pub fn subscribe_events(&self) -> broadcast::Receiver<(String, Value)> {
+        self.events.subscribe()
+    }
+
+    /// Asks the event sink to notify the user.
+    pub fn notify(&self, title: &str, body: &str) {
+        self.events.notify(title, body);
+    }
+
+    /// Returns the MLS instance of the active account, or the default instance if no account is active.
+    pub fn nostr_mls(&self) -> Arc<Mutex<NostrMls>> {
+        self.nostr
+            .active_nostr_mls()
+            .unwrap_or_else(|| self.default_nostr_mls.clone())
+    }
+
     pub async fn delete_all_data(&self) -> Result<(), Box<dyn std::error::Error>> {
         tracing::debug!(target: "whitenoise::delete_all_data", "Deleting all data");
 
+        // Grab the MLS instance before the account sessions are stopped
+        let nostr_mls = self.nostr_mls();
+
         // Clear data first
         self.nostr.delete_all_data().await?;
         self.database.delete_all_data().await?;
-        self.nostr_mls.lock().await.delete_all_data()?;
+        nostr_mls.lock().await.delete_all_data()?;
 
         // Remove logs
         if self.logs_dir.exists() {
@@ -1,6 +1,7 @@
 import { invoke } from "@tauri-apps/api/core";
 import { emit } from "@tauri-apps/api/event";
 import { type Writable, get, writable, derived } from "svelte/store";
+import { errorMessage, isCommandError } from "../types/errors";
 import type { NMetadata } from "../types/nostr";
 
 export type Account = {
@@ -87,10 +88,10 @@ export async function createAccount(): Promise<void> {
 
 export async function logout(pubkey: string): Promise<void> {
     await invoke("logout", { hexPubkey: pubkey }).catch((e) => {
-        if (e === "No account found") {
+        if (isCommandError(e) && e.code === "not_found") {
             throw new LogoutError("No account found");
         }
-        throw new LogoutError(e);
+        throw new LogoutError(errorMessage(e));
     });
     await updateAccountsStore();
     await fetchRelays();
@@ -146,7 +147,7 @@ export async function hasNostrWalletConnectUri(): Promise<boolean> {
     try {
         return await invoke("has_nostr_wallet_connect_uri");
     } catch (error) {
-        throw new NostrWalletConnectError(`Failed to check NWC URI: ${error}`);
+        throw new NostrWalletConnectError(`Failed to check NWC URI: ${errorMessage(error)}`);
     }
 }
 
@@ -231,7 +232,7 @@ export async function setNostrWalletConnectUri(uri: string): Promise<void> {
     try {
         await invoke("set_nostr_wallet_connect_uri", { nostrWalletConnectUri: uri });
     } catch (error) {
-        throw new NostrWalletConnectError(`Failed to set NWC URI: ${error}`);
+        throw new NostrWalletConnectError(`Failed to set NWC URI: ${errorMessage(error)}`);
     }
 }
 
@@ -239,6 +240,6 @@ export async function removeNostrWalletConnectUri(): Promise<void> {
     try {
         await invoke("remove_nostr_wallet_connect_uri");
     } catch (error) {
-        throw new NostrWalletConnectError(`Failed to remove NWC URI: ${error}`);
+        throw new NostrWalletConnectError(`Failed to remove NWC URI: ${errorMessage(error)}`);
     }
 }
@@ -0,0 +1,46 @@
+/** The stable codes of the errors commands return, see `src-tauri/src/commands/error.rs`. */
+export type ErrorCode =
+    | "no_active_account"
+    | "not_found"
+    | "already_exists"
+    | "invalid_input"
+    | "wrong_password"
+    | "remote_signer"
+    | "permission_denied"
+    | "no_key_package"
+    | "welcome_not_sent"
+    | "relay_error"
+    | "lockdown_mode"
+    | "no_wallet"
+    | "invalid_invoice"
+    | "payment_failed"
+    | "mls"
+    | "storage"
+    | "internal";
+
+/** The error every command rejects with. */
+export interface CommandError {
+    code: ErrorCode;
+    message: string;
+    /** The user the error is about, e.g. the member without a key package. */
+    pubkey?: string;
+    /** The relays the error is about, e.g. the ones a welcome couldn't be sent to. */
+    relays?: string[];
+}
+
+export function isCommandError(error: unknown): error is CommandError {
+    return (
+        typeof error === "object" &&
+        error !== null &&
+        typeof (error as CommandError).code === "string" &&
+        typeof (error as CommandError).message === "string"
+    );
+}
+
+/** The message to show for an error thrown by `invoke` or anything else. */
+export function errorMessage(error: unknown): string {
+    if (isCommandError(error) || error instanceof Error) {
+        return error.message;
+    }
+    return String(error);
+}